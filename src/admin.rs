@@ -0,0 +1,356 @@
+//! Operator page for database upkeep and switches like read-only mode.
+
+use axum::{
+    extract::{Form, State},
+    http::StatusCode,
+    response::{Html, IntoResponse, Redirect, Response},
+    routing::{get, post},
+    Router,
+};
+use chrono::Local;
+use serde::Deserialize;
+
+use crate::backup;
+use crate::features::Feature;
+use crate::mail::{self, Template};
+use crate::{
+    base_path, error_status, html_escape, page_start, preferences, public_url, AppState, PAGE_END,
+};
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/admin", get(show))
+        .route("/admin/maintenance", post(run_maintenance))
+        .route("/admin/backup", post(run_backup))
+        .route("/admin/snapshots", post(create_snapshot))
+        .route("/admin/snapshots/restore", post(restore_snapshot))
+        .route("/admin/snapshots/delete", post(delete_snapshot))
+        .route("/admin/integrity", get(check_integrity))
+        .route("/admin/read-only", post(toggle_read_only))
+        .route("/admin/features", post(set_feature))
+        .route("/admin/test-mail", post(send_test_mail))
+}
+
+async fn show(State(state): State<AppState>) -> Result<Html<String>, StatusCode> {
+    render(&state, None).map(Html)
+}
+
+/// The page, with `notice` (HTML) above everything else.
+fn render(state: &AppState, notice: Option<&str>) -> Result<String, StatusCode> {
+    let last_run = match state.maintenance.last() {
+        Some(run) => format!(
+            "Zuletzt am {} in {:.1?}: {} → {}.",
+            run.finished_at.format(preferences::date_time()),
+            run.took,
+            format_size(run.size_before),
+            format_size(run.size_after)
+        ),
+        None => "Seit dem Start noch nicht ausgeführt.".to_string(),
+    };
+
+    let last_backup = match state.backups.last() {
+        Some(snapshot) => format!(
+            "Zuletzt am {} ({}).",
+            snapshot.finished_at.format(preferences::date_time()),
+            format_size(snapshot.size)
+        ),
+        None => "Seit dem Start noch keine erstellt.".to_string(),
+    };
+    let backup_count = state.backups.list().map_err(error_status)?.len();
+
+    let mut snapshots = String::new();
+    for (name, snapshot) in state.backups.snapshots().map_err(error_status)? {
+        snapshots.push_str(&format!(
+            r#"<div class="todo">
+  <div class="meta">
+    <div class="title">{name}</div>
+    <div class="time">{finished_at} · {size}</div>
+  </div>
+  <div class="actions">
+    <form method="post" action="{base}/admin/snapshots/restore">
+      <input type="hidden" name="name" value="{name}" />
+      <button type="submit">Wiederherstellen</button>
+    </form>
+    <form method="post" action="{base}/admin/snapshots/delete">
+      <input type="hidden" name="name" value="{name}" />
+      <button class="delete" type="submit">Löschen</button>
+    </form>
+  </div>
+</div>
+"#,
+            base = base_path(),
+            name = html_escape(&name),
+            finished_at = snapshot
+                .finished_at
+                .with_timezone(&Local)
+                .format(preferences::date_time()),
+            size = format_size(snapshot.size),
+        ));
+    }
+    if snapshots.is_empty() {
+        snapshots.push_str("<div class=\"subtitle\">Noch keine Schnappschüsse.</div>\n");
+    }
+
+    let mut features = String::new();
+    for feature in Feature::ALL {
+        let (state_label, action, enable) = if state.features.is_enabled(feature) {
+            ("an", "Ausschalten", false)
+        } else {
+            ("aus", "Einschalten", true)
+        };
+        features.push_str(&format!(
+            r#"    <form method="post" action="{base}/admin/features">
+      <input type="hidden" name="name" value="{name}" />
+      <input type="hidden" name="enabled" value="{enable}" />
+      {label}: {state_label} <button type="submit">{action}</button>
+    </form>
+"#,
+            base = base_path(),
+            name = feature.name(),
+            label = feature.label(),
+        ));
+    }
+
+    let mail = match &state.test_mailer {
+        Some(mailer) => {
+            let status = state
+                .db
+                .lock()
+                .mail_queue_status(mail::MAX_ATTEMPTS)
+                .map_err(error_status)?;
+            let mut text = format!(
+                "{} in der Warteschlange, {} aufgegeben.",
+                status.waiting, status.given_up
+            );
+            if let Some(error) = &status.last_error {
+                text.push_str(&format!(" Letzter Fehler: {}", html_escape(error)));
+            }
+            format!(
+                r#"    <div class="subtitle">{text}</div>
+    <form method="post" action="{base}/admin/test-mail">
+      <button type="submit">Testmail an {to} senden</button>
+    </form>
+"#,
+                base = base_path(),
+                to = html_escape(&mailer.to().to_string()),
+            )
+        }
+        None => "    <div class=\"subtitle\">Kein SMTP-Server eingerichtet.</div>\n".to_string(),
+    };
+
+    let (read_only, read_only_action) = if state.db.is_read_only() {
+        ("Eingeschaltet: Änderungen werden abgelehnt.", "Ausschalten")
+    } else {
+        ("Ausgeschaltet.", "Einschalten")
+    };
+
+    let mut body = page_start();
+    body.push_str("    <h1>Verwaltung</h1>\n");
+    if let Some(notice) = notice {
+        body.push_str(&format!("    <div class=\"notice\">{notice}</div>\n"));
+    }
+    body.push_str(&format!(
+        r#"    <h2>Nur lesen</h2>
+    <div class="subtitle">Lehnt alle Änderungen ab, z. B. während einer Sicherung oder eines Umzugs. {read_only}</div>
+    <form method="post" action="{base}/admin/read-only">
+      <button type="submit">{read_only_action}</button>
+    </form>
+    <h2>Funktionen</h2>
+    <div class="subtitle">Ausgeschaltete Bereiche antworten mit 404.</div>
+{features}    <h2>E-Mail</h2>
+{mail}    <h2>Datenbankpflege</h2>
+    <div class="subtitle">VACUUM, ANALYZE und PRAGMA optimize. {last_run}</div>
+    <form method="post" action="{base}/admin/maintenance">
+      <button type="submit">Jetzt ausführen</button>
+    </form>
+    <h2>Sicherung</h2>
+    <div class="subtitle">{backup_count} Sicherungen in {backup_dir}. {last_backup}</div>
+    <form method="post" action="{base}/admin/backup">
+      <button type="submit">Jetzt sichern</button>
+    </form>
+    <h2>Schnappschüsse</h2>
+    <div class="subtitle">Benannte Kopien, z. B. vor einem Import oder vielen Änderungen, die bleiben, bis sie gelöscht werden. Vor dem Wiederherstellen wird der aktuelle Stand selbst als Schnappschuss gesichert.</div>
+    <form method="post" action="{base}/admin/snapshots">
+      <input type="text" name="name" placeholder="Name, z. B. vor-import" maxlength="64" pattern="[\p{{L}}\p{{N}}_\-]+" required />
+      <button type="submit">Erstellen</button>
+    </form>
+    <div class="todo-list">
+{snapshots}    </div>
+    <h2>Integrität</h2>
+    <div class="subtitle">Prüft die Datenbankdatei mit PRAGMA integrity_check, z. B. nach einem Absturz.</div>
+    <form method="get" action="{base}/admin/integrity">
+      <button type="submit">Prüfen</button>
+    </form>
+    <h2>Protokoll</h2>
+    <div class="subtitle">Wer wann welches Todo angelegt, geändert, erledigt oder gelöscht hat: <a href="{base}/admin/audit">Protokoll ansehen</a></div>
+    <a class="back" href="{base}/">Zurück zur Liste</a>
+"#,
+        base = base_path(),
+        backup_dir = html_escape(&state.backups.dir().display().to_string()),
+    ));
+    body.push_str(PAGE_END);
+    Ok(body)
+}
+
+/// Sends past the queue, so the mail server's answer shows right away.
+async fn send_test_mail(State(state): State<AppState>) -> Result<Html<String>, StatusCode> {
+    let mailer = state.test_mailer.as_ref().ok_or(StatusCode::NOT_FOUND)?;
+    let url = public_url();
+    let notice = match mailer.send_now(Template::Test, &[("url", url)]).await {
+        Ok(()) => format!(
+            "Testmail an {} verschickt.",
+            html_escape(&mailer.to().to_string())
+        ),
+        Err(err) => format!(
+            "Die Testmail konnte nicht verschickt werden: {}",
+            html_escape(&format!("{err:#}"))
+        ),
+    };
+    render(&state, Some(&notice)).map(Html)
+}
+
+async fn run_maintenance(State(state): State<AppState>) -> Result<Response, StatusCode> {
+    let maintenance = state.maintenance.clone();
+    tokio::task::spawn_blocking(move || maintenance.run())
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .map_err(error_status)?;
+    Ok(Redirect::to(&format!("{}/admin", base_path())).into_response())
+}
+
+async fn toggle_read_only(State(state): State<AppState>) -> Result<Response, StatusCode> {
+    let read_only = !state.db.is_read_only();
+    state.db.set_read_only(read_only).map_err(error_status)?;
+    println!("read-only mode {}", if read_only { "on" } else { "off" });
+    Ok(Redirect::to(&format!("{}/admin", base_path())).into_response())
+}
+
+async fn run_backup(State(state): State<AppState>) -> Result<Response, StatusCode> {
+    let backups = state.backups.clone();
+    tokio::task::spawn_blocking(move || backups.run())
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .map_err(error_status)?;
+    Ok(Redirect::to(&format!("{}/admin", base_path())).into_response())
+}
+
+#[derive(Deserialize)]
+struct SnapshotForm {
+    name: String,
+}
+
+async fn create_snapshot(
+    State(state): State<AppState>,
+    Form(form): Form<SnapshotForm>,
+) -> Result<Response, StatusCode> {
+    let backups = state.backups.clone();
+    let name = form.name.trim().to_string();
+    if !backup::is_valid_name(&name) {
+        return Err(StatusCode::UNPROCESSABLE_ENTITY);
+    }
+    tokio::task::spawn_blocking(move || backups.snapshot(&name))
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .map_err(error_status)?;
+    Ok(Redirect::to(&format!("{}/admin", base_path())).into_response())
+}
+
+async fn restore_snapshot(
+    State(state): State<AppState>,
+    Form(form): Form<SnapshotForm>,
+) -> Result<Response, StatusCode> {
+    let backups = state.backups.clone();
+    let before = tokio::task::spawn_blocking(move || backups.restore(&form.name))
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .map_err(error_status)?;
+    println!("database restored from a snapshot, the previous state is in snapshot {before}");
+    Ok(Redirect::to(&format!("{}/admin", base_path())).into_response())
+}
+
+async fn delete_snapshot(
+    State(state): State<AppState>,
+    Form(form): Form<SnapshotForm>,
+) -> Result<Response, StatusCode> {
+    state
+        .backups
+        .delete_snapshot(&form.name)
+        .map_err(error_status)?;
+    Ok(Redirect::to(&format!("{}/admin", base_path())).into_response())
+}
+
+#[derive(Deserialize)]
+struct FeatureForm {
+    name: String,
+    enabled: bool,
+}
+
+async fn set_feature(
+    State(state): State<AppState>,
+    Form(form): Form<FeatureForm>,
+) -> Result<Response, StatusCode> {
+    let feature = Feature::from_name(&form.name).ok_or(StatusCode::NOT_FOUND)?;
+    state
+        .features
+        .set(&state.db, feature, form.enabled)
+        .map_err(error_status)?;
+    Ok(Redirect::to(&format!("{}/admin", base_path())).into_response())
+}
+
+/// Answers 500 when the check finds problems, so monitoring can poll it.
+async fn check_integrity(State(state): State<AppState>) -> Result<Response, StatusCode> {
+    let db = state.db.clone();
+    let health = tokio::task::spawn_blocking(move || db.lock().health())
+        .await
+        .map_err(|_| StatusCode::INTERNAL_SERVER_ERROR)?
+        .map_err(error_status)?;
+
+    let (status, verdict) = if health.problems.is_empty() {
+        (StatusCode::OK, "Keine Fehler gefunden.".to_string())
+    } else {
+        let problems: Vec<String> = health
+            .problems
+            .iter()
+            .map(|problem| html_escape(problem))
+            .collect();
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            format!(
+                "Die Datenbank ist beschädigt:<br />{}",
+                problems.join("<br />")
+            ),
+        )
+    };
+
+    let mut rows = String::new();
+    for (table, count) in &health.row_counts {
+        rows.push_str(&format!(
+            "      <tr><td>{table}</td><td>{count}</td></tr>\n"
+        ));
+    }
+
+    let mut body = page_start();
+    body.push_str(&format!(
+        r#"    <h1>Integrität</h1>
+    <div class="notice">{verdict}</div>
+    <table>
+      <tr><td>Schema-Version</td><td>{schema_version}</td></tr>
+      <tr><td>Dateigröße</td><td>{file_size}</td></tr>
+{rows}    </table>
+    <a class="back" href="{base}/admin">Zurück zur Verwaltung</a>
+"#,
+        base = base_path(),
+        schema_version = health.schema_version,
+        file_size = format_size(health.file_size),
+    ));
+    body.push_str(PAGE_END);
+    Ok((status, Html(body)).into_response())
+}
+
+fn format_size(bytes: u64) -> String {
+    if bytes < 1024 * 1024 {
+        format!("{:.1} KiB", bytes as f64 / 1024.0)
+    } else {
+        format!("{:.1} MiB", bytes as f64 / (1024.0 * 1024.0))
+    }
+}
@@ -0,0 +1,151 @@
+//! Agenda: the open todos of the next two weeks, one day at a time, with
+//! overdue ones first and those without a deadline last.
+
+use axum::{extract::State, http::StatusCode, response::Html, routing::get, Router};
+use chrono::{Datelike, Local, NaiveDate, Weekday};
+
+use crate::models::{Goal, Status, Todo};
+use crate::{
+    base_path, error_status, goals, html_escape, location_link, page_start, preferences, statuses,
+    AppState, PAGE_END,
+};
+
+/// Days shown, starting with today.
+const DAYS: usize = 14;
+
+pub fn router() -> Router<AppState> {
+    Router::new().route("/agenda", get(show))
+}
+
+async fn show(State(state): State<AppState>) -> Result<Html<String>, StatusCode> {
+    let db = state.db.lock();
+    let todos = db.list_todos(false).map_err(error_status)?;
+    let statuses = db.statuses().map_err(error_status)?;
+    let goals = db.list_goals().map_err(error_status)?;
+    Ok(Html(render(
+        &todos,
+        &statuses,
+        &goals,
+        Local::now().date_naive(),
+    )))
+}
+
+pub fn weekday_name(weekday: Weekday) -> &'static str {
+    match weekday {
+        Weekday::Mon => "Montag",
+        Weekday::Tue => "Dienstag",
+        Weekday::Wed => "Mittwoch",
+        Weekday::Thu => "Donnerstag",
+        Weekday::Fri => "Freitag",
+        Weekday::Sat => "Samstag",
+        Weekday::Sun => "Sonntag",
+    }
+}
+
+fn render(todos: &[Todo], statuses: &[Status], goals: &[Goal], today: NaiveDate) -> String {
+    let base = base_path();
+    let mut body = page_start();
+    body.push_str(&format!(
+        "    <h1>Agenda</h1>\n    <div class=\"subtitle\">Was in den nächsten {DAYS} Tagen fällig ist.</div>\n"
+    ));
+
+    // Oldest deadline first, then in the order they were added.
+    let mut todos: Vec<&Todo> = todos.iter().collect();
+    todos.sort_by_key(|todo| (todo.deadline, todo.id));
+
+    let overdue: Vec<&Todo> = todos
+        .iter()
+        .copied()
+        .filter(|todo| todo.deadline.is_some_and(|deadline| deadline < today))
+        .collect();
+    if !overdue.is_empty() {
+        body.push_str(&section("Überfällig", &overdue, true, statuses, goals));
+    }
+    for date in today.iter_days().take(DAYS) {
+        let due: Vec<&Todo> = todos
+            .iter()
+            .copied()
+            .filter(|todo| todo.deadline == Some(date))
+            .collect();
+        let day = match (date - today).num_days() {
+            0 => "Heute".to_string(),
+            1 => "Morgen".to_string(),
+            _ => weekday_name(date.weekday()).to_string(),
+        };
+        let heading = format!("{day}, {}", date.format(preferences::day()));
+        body.push_str(&section(&heading, &due, false, statuses, goals));
+    }
+    let unscheduled: Vec<&Todo> = todos
+        .iter()
+        .copied()
+        .filter(|todo| todo.deadline.is_none())
+        .collect();
+    body.push_str(&section(
+        "Ohne Deadline",
+        &unscheduled,
+        false,
+        statuses,
+        goals,
+    ));
+
+    body.push_str(&format!(
+        "    <a class=\"back\" href=\"{base}/\">Zurück zur Liste</a>\n"
+    ));
+    body.push_str(PAGE_END);
+    body
+}
+
+/// One bucket of the agenda; an empty one still shows, so each day can be
+/// ticked off at a glance. `with_deadline` adds the deadline to each todo,
+/// for buckets that span several days.
+fn section(
+    heading: &str,
+    todos: &[&Todo],
+    with_deadline: bool,
+    statuses: &[Status],
+    goals: &[Goal],
+) -> String {
+    let base = base_path();
+    let mut section = format!("    <h2>{heading}</h2>\n", heading = html_escape(heading));
+    if todos.is_empty() {
+        section.push_str("    <div class=\"subtitle\">Nichts fällig.</div>\n");
+        return section;
+    }
+    section.push_str("    <div class=\"todo-list\">\n");
+    for todo in todos {
+        let mut details = Vec::new();
+        if let Some(location) = &todo.location {
+            details.push(location_link(location));
+        }
+        if let Some(goal) = goals.iter().find(|goal| Some(goal.id) == todo.goal_id) {
+            details.push(goals::badge(goal));
+        }
+        if let Some(deadline) = todo.deadline.filter(|_| with_deadline) {
+            details.push(format!(
+                "fällig am {}",
+                deadline.format(preferences::date())
+            ));
+        }
+        let details = if details.is_empty() {
+            String::new()
+        } else {
+            format!("\n    <div class=\"time\">{}</div>", details.join(" · "))
+        };
+        section.push_str(&format!(
+            r#"<div class="todo">
+  <div class="meta">
+    <div class="title"><a href="{base}/todo/{id}">{title}</a></div>{details}
+  </div>
+  <div class="actions">
+    <span class="status">{status}</span>
+  </div>
+</div>
+"#,
+            id = todo.id,
+            title = html_escape(&todo.title),
+            status = html_escape(statuses::label(todo, statuses)),
+        ));
+    }
+    section.push_str("    </div>\n");
+    section
+}
@@ -0,0 +1,58 @@
+//! Aging: how long an open todo has waited since it was created. Past the
+//! thresholds set under Einstellungen its card turns amber, then red, so
+//! forgotten todos stand out.
+
+use chrono::{Local, NaiveDate};
+
+use crate::models::Todo;
+
+/// Days after which an open todo counts as aging and as stale; 0 turns a
+/// level off.
+#[derive(Clone, Copy)]
+pub struct AgeThresholds {
+    pub aging: u32,
+    pub stale: u32,
+}
+
+impl Default for AgeThresholds {
+    fn default() -> Self {
+        Self {
+            aging: 14,
+            stale: 30,
+        }
+    }
+}
+
+/// Whole days `todo` has been open on `today`; `None` once it is done.
+pub fn days_open(todo: &Todo, today: NaiveDate) -> Option<i64> {
+    if todo.completed_at.is_some() {
+        return None;
+    }
+    let created = todo.created_at.with_timezone(&Local).date_naive();
+    Some((today - created).num_days().max(0))
+}
+
+/// The card class for how long `todo` has been open: ` stale`, ` aging` or
+/// nothing.
+pub fn class(todo: &Todo, thresholds: AgeThresholds, today: NaiveDate) -> &'static str {
+    let Some(days) = days_open(todo, today) else {
+        return "";
+    };
+    let reached = |threshold: u32| threshold > 0 && days >= i64::from(threshold);
+    if reached(thresholds.stale) {
+        " stale"
+    } else if reached(thresholds.aging) {
+        " aging"
+    } else {
+        ""
+    }
+}
+
+/// How long `todo` has been open, for its card; nothing on its first day.
+pub fn label(todo: &Todo, today: NaiveDate) -> String {
+    match days_open(todo, today) {
+        None | Some(0) => String::new(),
+        Some(1) => " · seit einem Tag offen".to_string(),
+        Some(days) => format!(" · seit {days} Tagen offen"),
+    }
+}
@@ -0,0 +1,940 @@
+use std::collections::HashMap;
+
+use axum::{
+    extract::{Path, Query, Request, State},
+    http::{header, HeaderValue, StatusCode},
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::{get, post},
+    Json, Router,
+};
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Deserializer};
+use serde_json::{json, Map, Value};
+
+use crate::audit::AuditQuery;
+use crate::db::{Database, TodoFilter};
+use crate::models::Todo;
+use crate::validate::{self, Invalid};
+use crate::{base_path, error_status, undo, AppState, EditForm};
+
+/// The API version served under `/api/v1`. A breaking change gets a new
+/// prefix, while the previous one keeps working for at least one release,
+/// marked deprecated.
+const VERSION: &str = "1";
+
+/// Page size when only a cursor is given, and the largest one accepted.
+const DEFAULT_PAGE_SIZE: u32 = 100;
+const MAX_PAGE_SIZE: u32 = 500;
+
+/// Most operations accepted in one batch.
+const MAX_BATCH_OPERATIONS: usize = 1000;
+
+/// Todos and goals returned by `/quicksearch`, at most, and how many todos
+/// are ranked to pick them.
+const QUICKSEARCH_TODOS: usize = 8;
+const QUICKSEARCH_GOALS: usize = 5;
+const QUICKSEARCH_CANDIDATES: u32 = 100;
+
+/// Pages offered as actions by `/quicksearch`: label and path.
+const QUICKSEARCH_ACTIONS: &[(&str, &str)] = &[
+    ("Neues Todo", "/"),
+    ("Mein Tag", "/my-day"),
+    ("Agenda", "/agenda"),
+    ("Board", "/board"),
+    ("Gantt", "/gantt"),
+    ("Ziele", "/goals"),
+    ("Notizen", "/notes"),
+    ("Seite erfassen", "/capture"),
+    ("Importieren", "/import"),
+    ("Exportieren", "/export.xlsx"),
+    ("Irgendwann", "/someday"),
+    ("Statistik", "/stats"),
+    ("Archiv", "/archive"),
+    ("Papierkorb", "/trash"),
+    ("Aufs Handy", "/qr"),
+    ("Einstellungen", "/settings"),
+];
+
+#[derive(Deserialize)]
+struct ListQuery {
+    #[serde(default = "default_include_completed")]
+    all: bool,
+    /// Overrides `all`.
+    status: Option<Status>,
+    deadline_from: Option<NaiveDate>,
+    deadline_to: Option<NaiveDate>,
+    /// Text the title contains.
+    q: Option<String>,
+    location: Option<String>,
+    goal_id: Option<i64>,
+    /// `true` for todos put off to someday only, `false` for active ones.
+    someday: Option<bool>,
+    /// Asks for one page instead of the whole list.
+    limit: Option<u32>,
+    /// `next_cursor` of the previous page.
+    cursor: Option<String>,
+}
+
+fn default_include_completed() -> bool {
+    true
+}
+
+#[derive(Deserialize)]
+struct ChangesQuery {
+    /// `cursor` of the last sync; without it, every todo is sent.
+    since: Option<String>,
+}
+
+/// What an offline client changed since its last sync, e.g.
+/// `{"since": "...", "changes": [{"op": "complete", "id": 3}]}`.
+#[derive(Deserialize)]
+struct Upload {
+    since: Option<String>,
+    #[serde(default)]
+    changes: Vec<Change>,
+}
+
+/// One uploaded change: a batch operation, and when the client made it by
+/// the server's clock. Without `at`, it counts as made on upload.
+#[derive(Deserialize)]
+struct Change {
+    at: Option<DateTime<Utc>>,
+    #[serde(flatten)]
+    operation: Operation,
+}
+
+#[derive(Deserialize)]
+struct QuicksearchQuery {
+    #[serde(default)]
+    q: String,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "lowercase")]
+enum Status {
+    Open,
+    Done,
+}
+
+#[derive(Deserialize)]
+struct NewTodo {
+    title: String,
+    #[serde(default)]
+    deadline: Option<String>,
+    #[serde(default)]
+    location: Option<String>,
+}
+
+/// Fields to change; absent ones keep their value and `null` clears them.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct TodoPatch {
+    title: Option<String>,
+    #[serde(default, deserialize_with = "present")]
+    deadline: Option<Option<String>>,
+    #[serde(default, deserialize_with = "present")]
+    location: Option<Option<String>>,
+    #[serde(default, deserialize_with = "present")]
+    points: Option<Option<u32>>,
+    #[serde(default, deserialize_with = "present")]
+    goal_id: Option<Option<i64>>,
+    #[serde(default, deserialize_with = "present")]
+    start_date: Option<Option<String>>,
+    habit: Option<bool>,
+    floating: Option<bool>,
+    someday: Option<bool>,
+    /// Only apply the patch if the todo is still at this version.
+    version: Option<i64>,
+}
+
+/// One entry of a `POST /api/todos/batch` body, e.g.
+/// `{"op": "update", "id": 3, "deadline": null}`.
+#[derive(Deserialize)]
+#[serde(tag = "op", rename_all = "lowercase")]
+enum Operation {
+    Create(NewTodo),
+    Update {
+        id: i64,
+        /// A [`TodoPatch`]; parsed separately since flattening would
+        /// ignore unknown fields.
+        #[serde(flatten)]
+        patch: Map<String, Value>,
+    },
+    Complete {
+        id: i64,
+    },
+    Delete {
+        id: i64,
+    },
+}
+
+impl Operation {
+    /// The todo the operation changes, unless it creates one.
+    fn todo_id(&self) -> Option<i64> {
+        match self {
+            Operation::Create(_) => None,
+            Operation::Update { id, .. }
+            | Operation::Complete { id }
+            | Operation::Delete { id } => Some(*id),
+        }
+    }
+}
+
+/// Why an operation on a todo was not carried out.
+enum Failure {
+    Status(StatusCode),
+    Invalid(Invalid),
+}
+
+impl From<StatusCode> for Failure {
+    fn from(status: StatusCode) -> Self {
+        Failure::Status(status)
+    }
+}
+
+impl Failure {
+    fn status(&self) -> StatusCode {
+        match self {
+            Failure::Status(status) => *status,
+            Failure::Invalid(_) => StatusCode::UNPROCESSABLE_ENTITY,
+        }
+    }
+}
+
+impl Failure {
+    /// The result of a batch operation that failed.
+    fn result(self) -> Value {
+        let status = self.status().as_u16();
+        match self {
+            Failure::Status(_) => json!({ "status": status }),
+            Failure::Invalid(invalid) => json!({ "status": status, "errors": invalid.0 }),
+        }
+    }
+}
+
+impl IntoResponse for Failure {
+    fn into_response(self) -> Response {
+        match self {
+            Failure::Status(status) => status.into_response(),
+            Failure::Invalid(invalid) => invalid.into_response(),
+        }
+    }
+}
+
+/// Tells an explicit `null` (`Some(None)`) apart from a missing field (`None`).
+fn present<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
+}
+
+pub fn router() -> Router<AppState> {
+    let v1 = Router::new()
+        .route("/todos", get(list_todos).post(add_todo))
+        .route("/todos/batch", post(batch))
+        .route(
+            "/todos/:id",
+            get(show_todo).patch(patch_todo).delete(delete_todo),
+        )
+        .route("/todos/:id/complete", post(complete_todo))
+        .route("/changes", get(changes).post(upload))
+        .route("/quicksearch", get(quicksearch))
+        .route("/audit", get(audit_log))
+        .route("/undo/:action_id", post(undo));
+    Router::new()
+        .nest("/api/v1", v1.clone())
+        // Where the API lived before it was versioned.
+        .nest("/api", v1.layer(middleware::from_fn(deprecated)))
+        .layer(middleware::from_fn(version_header))
+}
+
+/// Tells clients which version answered, so scripts can check what they got.
+async fn version_header(request: Request, next: Next) -> Response {
+    let mut response = next.run(request).await;
+    response
+        .headers_mut()
+        .insert("api-version", HeaderValue::from_static(VERSION));
+    response
+}
+
+/// Marks answers from the unversioned paths as deprecated (RFC 9745) since
+/// `/api/v1` was introduced, and points to the same resource there.
+async fn deprecated(request: Request, next: Next) -> Response {
+    // Nested routers see the path without the `/api` prefix.
+    let successor = format!(
+        "<{}/api/v1{}>; rel=\"successor-version\"",
+        base_path(),
+        request.uri().path()
+    );
+    let mut response = next.run(request).await;
+    let headers = response.headers_mut();
+    headers.insert("deprecation", HeaderValue::from_static("@1792108800"));
+    if let Ok(link) = HeaderValue::from_str(&successor) {
+        headers.insert(header::LINK, link);
+    }
+    response
+}
+
+/// Entries of the audit log matching the query's filters, newest first,
+/// e.g. `?todo_id=3&action=update&since=2026-01-01`.
+async fn audit_log(
+    State(state): State<AppState>,
+    Query(query): Query<AuditQuery>,
+) -> Result<Response, StatusCode> {
+    let mut filter = query.filter();
+    filter.limit = Some(
+        filter
+            .limit
+            .unwrap_or(DEFAULT_PAGE_SIZE)
+            .clamp(1, MAX_PAGE_SIZE),
+    );
+    let entries = state.db.lock().audit_log(&filter).map_err(error_status)?;
+    Ok(Json(entries).into_response())
+}
+
+/// The todos matching the query's filters as an array, or with `limit` or
+/// `cursor` one page of them as `{"todos": [...], "next_cursor": ...}`.
+async fn list_todos(
+    State(state): State<AppState>,
+    Query(query): Query<ListQuery>,
+) -> Result<Response, StatusCode> {
+    let mut filter = TodoFilter {
+        completed: match query.status {
+            Some(Status::Open) => Some(false),
+            Some(Status::Done) => Some(true),
+            None => (!query.all).then_some(false),
+        },
+        deadline_from: query.deadline_from,
+        deadline_to: query.deadline_to,
+        text: query.q.filter(|q| !q.is_empty()),
+        location: query.location,
+        goal_id: query.goal_id,
+        someday: query.someday,
+        ..Default::default()
+    };
+    let db = state.db.lock();
+    if query.limit.is_none() && query.cursor.is_none() {
+        let todos = db.find_todos(&filter).map_err(error_status)?;
+        return Ok(Json(todos).into_response());
+    }
+
+    if let Some(cursor) = query.cursor.as_deref() {
+        filter.after = Some(decode_cursor(cursor).ok_or(StatusCode::BAD_REQUEST)?);
+    }
+    let limit = query
+        .limit
+        .unwrap_or(DEFAULT_PAGE_SIZE)
+        .clamp(1, MAX_PAGE_SIZE);
+    // One more than asked for tells whether another page follows.
+    filter.limit = Some(limit + 1);
+    let mut todos = db.find_todos(&filter).map_err(error_status)?;
+    let next_cursor = if todos.len() > limit as usize {
+        todos.truncate(limit as usize);
+        todos.last().map(|todo| encode_cursor(todo.id))
+    } else {
+        None
+    };
+    Ok(Json(json!({ "todos": todos, "next_cursor": next_cursor })).into_response())
+}
+
+/// What a command palette offers for `q`: matching todos (open ones and
+/// those starting with `q` first, `#12` or `12` finding todo 12), goals and
+/// pages, each with the URL to go to.
+async fn quicksearch(
+    State(state): State<AppState>,
+    Query(query): Query<QuicksearchQuery>,
+) -> Result<Json<Value>, StatusCode> {
+    let q = query.q.trim();
+    let needle = q.to_lowercase();
+    let base = base_path();
+    let db = state.db.lock();
+
+    let mut todos = Vec::new();
+    if let Ok(id) = q.trim_start_matches('#').parse::<i64>() {
+        todos.extend(db.get_todo(id).map_err(error_status)?);
+    }
+    if !q.is_empty() {
+        let mut matches = db
+            .find_todos(&TodoFilter {
+                text: Some(q.to_string()),
+                limit: Some(QUICKSEARCH_CANDIDATES),
+                ..Default::default()
+            })
+            .map_err(error_status)?;
+        // Stable, so newer todos stay ahead within each group.
+        matches.sort_by_key(|todo| {
+            (
+                todo.completed_at.is_some(),
+                !todo.title.to_lowercase().starts_with(&needle),
+            )
+        });
+        let exact = todos.first().map(|todo| todo.id);
+        todos.extend(matches.into_iter().filter(|todo| Some(todo.id) != exact));
+    }
+    todos.truncate(QUICKSEARCH_TODOS);
+    let todos: Vec<Value> = todos
+        .iter()
+        .map(|todo| {
+            json!({
+                "id": todo.id,
+                "title": todo.title,
+                "completed": todo.completed_at.is_some(),
+                "url": format!("{base}/todo/{}", todo.id),
+            })
+        })
+        .collect();
+
+    let goals: Vec<Value> = if q.is_empty() {
+        Vec::new()
+    } else {
+        db.list_goals()
+            .map_err(error_status)?
+            .iter()
+            .filter(|goal| goal.title.to_lowercase().contains(&needle))
+            .take(QUICKSEARCH_GOALS)
+            .map(|goal| json!({ "id": goal.id, "title": goal.title, "url": format!("{base}/goals") }))
+            .collect()
+    };
+
+    let actions: Vec<Value> = QUICKSEARCH_ACTIONS
+        .iter()
+        .filter(|(label, _)| label.to_lowercase().contains(&needle))
+        .map(|(label, path)| json!({ "label": label, "url": format!("{base}{path}") }))
+        .collect();
+
+    Ok(Json(
+        json!({ "todos": todos, "goals": goals, "actions": actions }),
+    ))
+}
+
+/// Cursors are the last id of a page, kept opaque so the scheme can change.
+fn encode_cursor(id: i64) -> String {
+    hex::encode(id.to_be_bytes())
+}
+
+fn decode_cursor(cursor: &str) -> Option<i64> {
+    let bytes = hex::decode(cursor).ok()?;
+    Some(i64::from_be_bytes(bytes.try_into().ok()?))
+}
+
+async fn add_todo(
+    State(state): State<AppState>,
+    Json(input): Json<NewTodo>,
+) -> Result<Response, Failure> {
+    let todo = create(&state.db.lock(), input)?;
+    Ok((StatusCode::CREATED, Json(todo)).into_response())
+}
+
+async fn show_todo(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<Json<Todo>, StatusCode> {
+    let db = state.db.lock();
+    let todo = db
+        .get_todo(id)
+        .map_err(error_status)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    Ok(Json(todo))
+}
+
+async fn patch_todo(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    Json(patch): Json<TodoPatch>,
+) -> Result<Json<Todo>, Failure> {
+    Ok(Json(update(&state.db.lock(), id, patch)?))
+}
+
+async fn complete_todo(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<Json<Todo>, StatusCode> {
+    Ok(Json(complete(&state.db.lock(), id)?))
+}
+
+/// Answers with the id to pass to `/undo/:action_id` in the `undo-action`
+/// header.
+async fn delete_todo(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<Response, StatusCode> {
+    let action_id = delete(&state.db.lock(), id)?;
+    Ok((
+        StatusCode::NO_CONTENT,
+        [("undo-action", action_id.to_string())],
+    )
+        .into_response())
+}
+
+/// Undoes a delete, answering with what was restored; 404 once it was
+/// purged from the trash.
+async fn undo(
+    State(state): State<AppState>,
+    Path(action_id): Path<i64>,
+) -> Result<Response, StatusCode> {
+    let db = state.db.lock();
+    let kept = undo::kept(&db, state.undo_window).map_err(error_status)?;
+    let tombstone = db
+        .restore(action_id, undo::since(kept))
+        .map_err(error_status)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    Ok(Json(tombstone).into_response())
+}
+
+/// Runs every operation in one transaction: either all of them are applied,
+/// or none is and the response names the one that failed under `failed` and
+/// ends with its result.
+async fn batch(
+    State(state): State<AppState>,
+    Json(operations): Json<Vec<Operation>>,
+) -> Result<Response, StatusCode> {
+    if operations.len() > MAX_BATCH_OPERATIONS {
+        return Err(StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    let (status, body) = run_batch(&state.db.lock(), operations)?;
+    Ok((status, Json(body)).into_response())
+}
+
+/// The status and body of a batch response.
+fn run_batch(db: &Database, operations: Vec<Operation>) -> Result<(StatusCode, Value), StatusCode> {
+    let tx = db.transaction().map_err(error_status)?;
+    let mut results = Vec::with_capacity(operations.len());
+    for (index, operation) in operations.into_iter().enumerate() {
+        match run(db, operation) {
+            Ok(result) => results.push(result),
+            Err(failure) => {
+                let status = failure.status();
+                results.push(failure.result());
+                // Dropping the transaction rolls back what already ran.
+                drop(tx);
+                let body = json!({ "applied": false, "failed": index, "results": results });
+                return Ok((status, body));
+            }
+        }
+    }
+    tx.commit().map_err(|err| error_status(err.into()))?;
+    Ok((
+        StatusCode::OK,
+        json!({ "applied": true, "results": results }),
+    ))
+}
+
+/// The todos changed since the cursor `since` and the ids of those deleted
+/// or archived since, for a client to catch up with, e.g.
+/// `{"todos": [...], "deleted": [4], "cursor": "..."}`. Without `since`,
+/// every todo. 410 when the audit log no longer reaches back to `since`;
+/// the client then starts over without it.
+async fn changes(
+    State(state): State<AppState>,
+    Query(query): Query<ChangesQuery>,
+) -> Result<Response, StatusCode> {
+    let since = query.since.as_deref().map(parse_cursor).transpose()?;
+    Ok(Json(delta(&state.db.lock(), since)?).into_response())
+}
+
+/// Applies what an offline client changed, then answers like `changes` with
+/// `results` added, one per change as in a batch. Unlike a batch, a failed
+/// change doesn't stop the others; only its own writes are undone. The last
+/// write wins by the server's clock: a change made before the todo last
+/// changed on the server is not applied and answered with 409 and the todo
+/// as it is. Changes that win over what others changed since `since` get
+/// `"conflict": true`, as do those that lose. Changes to todos deleted
+/// meanwhile fail with 404.
+async fn upload(
+    State(state): State<AppState>,
+    Json(upload): Json<Upload>,
+) -> Result<Response, StatusCode> {
+    if upload.changes.len() > MAX_BATCH_OPERATIONS {
+        return Err(StatusCode::PAYLOAD_TOO_LARGE);
+    }
+    let since = upload.since.as_deref().map(parse_cursor).transpose()?;
+
+    let db = state.db.lock();
+    // What others changed, before this upload adds to it.
+    let mut changed = Vec::new();
+    if let Some(since) = since {
+        let (todos, deleted) = db
+            .changes_since(since)
+            .map_err(error_status)?
+            .ok_or(StatusCode::GONE)?;
+        changed.extend(todos.iter().map(|todo| todo.id));
+        changed.extend(deleted);
+    }
+
+    let tx = db.transaction().map_err(error_status)?;
+    let results = apply(&db, upload.changes, &changed)?;
+    let mut body = delta(&db, since)?;
+    tx.commit().map_err(|err| error_status(err.into()))?;
+    body["results"] = results.into();
+    Ok(Json(body).into_response())
+}
+
+/// Carries out uploaded changes, each on its own, and returns their results.
+/// `changed` are the todos others changed since the client's last sync.
+fn apply(db: &Database, changes: Vec<Change>, changed: &[i64]) -> Result<Vec<Value>, StatusCode> {
+    // When each todo last changed before the upload, so that a change isn't
+    // beaten by the ones uploaded before it.
+    let mut modified = HashMap::new();
+    for id in changes
+        .iter()
+        .filter_map(|change| change.operation.todo_id())
+    {
+        if let Some(modified_at) = db.modified_at(id).map_err(error_status)? {
+            modified.insert(id, modified_at);
+        }
+    }
+
+    let mut results = Vec::with_capacity(changes.len());
+    for change in changes {
+        let id = change.operation.todo_id();
+        let outdated = id.zip(change.at).and_then(|(id, at)| {
+            modified
+                .get(&id)
+                .is_some_and(|modified_at| *modified_at > at)
+                .then_some(id)
+        });
+        let mut result = match outdated {
+            Some(id) => {
+                let todo = db.get_todo(id).map_err(error_status)?;
+                json!({ "status": StatusCode::CONFLICT.as_u16(), "todo": todo, "conflict": true })
+            }
+            None => {
+                let savepoint = db.savepoint().map_err(error_status)?;
+                match merge(db, change.operation) {
+                    Ok(result) => {
+                        savepoint.release().map_err(error_status)?;
+                        result
+                    }
+                    Err(failure) => failure.result(),
+                }
+            }
+        };
+        if id.is_some_and(|id| changed.contains(&id)) {
+            result["conflict"] = true.into();
+        }
+        results.push(result);
+    }
+    Ok(results)
+}
+
+fn parse_cursor(cursor: &str) -> Result<i64, StatusCode> {
+    decode_cursor(cursor).ok_or(StatusCode::BAD_REQUEST)
+}
+
+/// The body of `changes`.
+fn delta(db: &Database, since: Option<i64>) -> Result<Value, StatusCode> {
+    let cursor = db.change_cursor().map_err(error_status)?;
+    let (todos, deleted) = match since {
+        Some(since) => db
+            .changes_since(since)
+            .map_err(error_status)?
+            .ok_or(StatusCode::GONE)?,
+        None => (
+            db.find_todos(&TodoFilter::default())
+                .map_err(error_status)?,
+            Vec::new(),
+        ),
+    };
+    Ok(json!({ "todos": todos, "deleted": deleted, "cursor": encode_cursor(cursor) }))
+}
+
+/// Carries out one uploaded change, the last write winning: updates ignore
+/// `version`, and completing a completed todo leaves it as it is.
+fn merge(db: &Database, operation: Operation) -> Result<Value, Failure> {
+    match operation {
+        Operation::Update { id, mut patch } => {
+            patch.remove("version");
+            run(db, Operation::Update { id, patch })
+        }
+        Operation::Complete { id } => {
+            let todo = db
+                .get_todo(id)
+                .map_err(error_status)?
+                .ok_or(StatusCode::NOT_FOUND)?;
+            if todo.completed_at.is_some() {
+                return Ok(json!({ "status": StatusCode::OK.as_u16(), "todo": todo }));
+            }
+            run(db, Operation::Complete { id })
+        }
+        operation => run(db, operation),
+    }
+}
+
+/// Carries out one batch operation, answering like the matching endpoint.
+fn run(db: &Database, operation: Operation) -> Result<Value, Failure> {
+    Ok(match operation {
+        Operation::Create(input) => {
+            json!({ "status": StatusCode::CREATED.as_u16(), "todo": create(db, input)? })
+        }
+        Operation::Update { id, patch } => {
+            let patch = serde_json::from_value(Value::Object(patch))
+                .map_err(|_| StatusCode::UNPROCESSABLE_ENTITY)?;
+            json!({ "status": StatusCode::OK.as_u16(), "todo": update(db, id, patch)? })
+        }
+        Operation::Complete { id } => {
+            json!({ "status": StatusCode::OK.as_u16(), "todo": complete(db, id)? })
+        }
+        Operation::Delete { id } => {
+            let action_id = delete(db, id)?;
+            json!({ "status": StatusCode::NO_CONTENT.as_u16(), "undo_action": action_id })
+        }
+    })
+}
+
+fn create(db: &Database, input: NewTodo) -> Result<Todo, Failure> {
+    let input = validate::todo(&validate::TodoFields {
+        title: &input.title,
+        deadline: input.deadline.as_deref().unwrap_or_default(),
+        location: input.location.as_deref().unwrap_or_default(),
+        ..Default::default()
+    })
+    .map_err(Failure::Invalid)?;
+
+    let todo = db
+        .add_todo(&input.title, input.deadline, input.location.as_deref())
+        .map_err(error_status)?;
+    Ok(todo)
+}
+
+fn update(db: &Database, id: i64, patch: TodoPatch) -> Result<Todo, Failure> {
+    let current = db
+        .get_todo(id)
+        .map_err(error_status)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    // Validated the same way as the edit page, which always sends every field.
+    let mut form = EditForm::from(&current);
+    if let Some(title) = patch.title {
+        form.title = title;
+    }
+    if let Some(deadline) = patch.deadline {
+        form.deadline = deadline.unwrap_or_default();
+    }
+    if let Some(location) = patch.location {
+        form.location = location.unwrap_or_default();
+    }
+    if let Some(points) = patch.points {
+        form.points = points.map(|points| points.to_string()).unwrap_or_default();
+    }
+    if let Some(goal_id) = patch.goal_id {
+        form.goal = goal_id.map(|id| id.to_string()).unwrap_or_default();
+    }
+    if let Some(start) = patch.start_date {
+        form.start = start.unwrap_or_default();
+    }
+    if let Some(habit) = patch.habit {
+        form.habit = habit.then(|| "1".to_string());
+    }
+    if let Some(floating) = patch.floating {
+        form.floating = floating.then(|| "1".to_string());
+    }
+    let input = validate::todo(&form.fields()).map_err(Failure::Invalid)?;
+
+    let version = patch.version.unwrap_or(current.version);
+    let updated = db
+        .update_todo(
+            id,
+            version,
+            &input,
+            form.goal.parse().ok(),
+            form.habit.is_some(),
+            form.floating.is_some(),
+            patch.someday,
+        )
+        .map_err(error_status)?;
+    if !updated {
+        return Err(StatusCode::CONFLICT.into());
+    }
+    let todo = db
+        .get_todo(id)
+        .map_err(error_status)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    Ok(todo)
+}
+
+fn complete(db: &Database, id: i64) -> Result<Todo, StatusCode> {
+    let todo = db
+        .get_todo(id)
+        .map_err(error_status)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    if todo.completed_at.is_some() {
+        return Err(StatusCode::CONFLICT);
+    }
+
+    db.complete_todo(id).map_err(error_status)?;
+    let todo = db
+        .get_todo(id)
+        .map_err(error_status)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    Ok(todo)
+}
+
+/// Returns the id that undoes the delete.
+fn delete(db: &Database, id: i64) -> Result<i64, StatusCode> {
+    db.get_todo(id)
+        .map_err(error_status)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    db.delete_todo(id).map_err(error_status)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cursors_round_trip() {
+        for id in [0, 1, 42, i64::MAX, -1] {
+            assert_eq!(decode_cursor(&encode_cursor(id)), Some(id));
+        }
+        assert_eq!(encode_cursor(42), "000000000000002a");
+    }
+
+    fn patch(patch: Value) -> TodoPatch {
+        serde_json::from_value(patch).unwrap()
+    }
+
+    #[test]
+    fn stale_versions_conflict() {
+        let db = Database::connect(":memory:", None).unwrap();
+        let todo = db.add_todo("Reifen wechseln", None, None).unwrap();
+        let edit =
+            |title: &str| update(&db, todo.id, patch(json!({ "title": title, "version": 1 })));
+
+        assert!(edit("Winterreifen aufziehen").is_ok());
+        let stale = edit("Sommerreifen aufziehen");
+        assert_eq!(
+            stale.err().map(|failure| failure.status()),
+            Some(StatusCode::CONFLICT)
+        );
+        assert_eq!(
+            db.get_todo(todo.id).unwrap().unwrap().title,
+            "Winterreifen aufziehen"
+        );
+    }
+
+    fn changes(changes: Value) -> Vec<Change> {
+        serde_json::from_value(changes).unwrap()
+    }
+
+    fn title(db: &Database, id: i64) -> String {
+        db.get_todo(id).unwrap().unwrap().title
+    }
+
+    #[test]
+    fn failed_batches_apply_nothing() {
+        let db = Database::connect(":memory:", None).unwrap();
+        let todo = db.add_todo("Kuchen backen", None, None).unwrap();
+        let operations = serde_json::from_value(json!([
+            { "op": "create", "title": "Mehl kaufen" },
+            { "op": "update", "id": todo.id, "title": "Torte backen" },
+            { "op": "complete", "id": 999 },
+            { "op": "create", "title": "Gäste einladen" }
+        ]))
+        .unwrap();
+
+        let (status, body) = run_batch(&db, operations).unwrap();
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        assert_eq!(body["applied"], false);
+        assert_eq!(body["failed"], 2);
+        assert_eq!(body["results"].as_array().unwrap().len(), 3);
+        let todos = db.list_todos(true).unwrap();
+        assert_eq!(todos.len(), 1);
+        assert_eq!(todos[0].title, "Kuchen backen");
+        assert_eq!(todos[0].version, todo.version);
+    }
+
+    #[test]
+    fn uploads_lose_to_newer_changes() {
+        let db = Database::connect(":memory:", None).unwrap();
+        let todo = db.add_todo("Blumen gießen", None, None).unwrap();
+        let made_offline = Utc::now();
+        assert!(update(&db, todo.id, patch(json!({ "title": "Balkon gießen" }))).is_ok());
+
+        let uploaded = changes(json!([
+            { "op": "update", "id": todo.id, "title": "Garten gießen", "at": made_offline },
+            { "op": "complete", "id": todo.id, "at": made_offline }
+        ]));
+        let results = apply(&db, uploaded, &[]).unwrap();
+        for result in &results {
+            assert_eq!(result["status"], 409);
+            assert_eq!(result["conflict"], true);
+            assert_eq!(result["todo"]["title"], "Balkon gießen");
+        }
+        let current = db.get_todo(todo.id).unwrap().unwrap();
+        assert_eq!(current.title, "Balkon gießen");
+        assert!(current.completed_at.is_none());
+    }
+
+    #[test]
+    fn newer_uploads_win() {
+        let db = Database::connect(":memory:", None).unwrap();
+        let todo = db.add_todo("Blumen gießen", None, None).unwrap();
+        assert!(update(&db, todo.id, patch(json!({ "title": "Balkon gießen" }))).is_ok());
+        let made_offline = Utc::now();
+
+        let uploaded = changes(json!([
+            { "op": "update", "id": todo.id, "title": "Garten gießen", "at": made_offline },
+            // Made later, but still judged by the todo from before the upload.
+            { "op": "update", "id": todo.id, "location": "@garten", "at": made_offline },
+            { "op": "complete", "id": todo.id }
+        ]));
+        let results = apply(&db, uploaded, &[todo.id]).unwrap();
+        let statuses: Vec<&Value> = results.iter().map(|result| &result["status"]).collect();
+        assert_eq!(statuses, [200, 200, 200]);
+        // Others changed the todo since the last sync, which the upload overwrote.
+        assert!(results.iter().all(|result| result["conflict"] == true));
+        let current = db.get_todo(todo.id).unwrap().unwrap();
+        assert_eq!(current.title, "Garten gießen");
+        assert_eq!(current.location.as_deref(), Some("@garten"));
+        assert!(current.completed_at.is_some());
+    }
+
+    #[test]
+    fn failed_uploads_undo_only_their_own_writes() {
+        // A file, so that a second connection can add a trigger failing the
+        // second change after it has written.
+        let path =
+            std::env::temp_dir().join(format!("simpletodo-upload-{}.db", std::process::id()));
+        let db = Database::connect(&path, None).unwrap();
+        let first = db.add_todo("Rasen mähen", None, None).unwrap();
+        let second = db.add_todo("Hecke schneiden", None, None).unwrap();
+        rusqlite::Connection::open(&path)
+            .unwrap()
+            .execute_batch(&format!(
+                "CREATE TRIGGER audit_fails BEFORE INSERT ON audit_log \
+                 WHEN NEW.todo_id = {} BEGIN SELECT RAISE(ABORT, 'audit log unavailable'); END",
+                second.id
+            ))
+            .unwrap();
+
+        let uploaded = changes(json!([
+            { "op": "update", "id": first.id, "title": "Rasen vertikutieren" },
+            { "op": "update", "id": second.id, "title": "Hecke stutzen" },
+            { "op": "create", "title": "Laub rechen" }
+        ]));
+        let tx = db.transaction().unwrap();
+        let results = apply(&db, uploaded, &[]).unwrap();
+        tx.commit().unwrap();
+        let statuses: Vec<&Value> = results.iter().map(|result| &result["status"]).collect();
+        // The trigger's abort counts as a violated constraint.
+        assert_eq!(statuses, [200, 422, 201]);
+        assert_eq!(title(&db, first.id), "Rasen vertikutieren");
+        assert_eq!(title(&db, second.id), "Hecke schneiden");
+        assert_eq!(db.get_todo(second.id).unwrap().unwrap().version, 1);
+        let created = results[2]["todo"]["id"].as_i64().unwrap();
+        assert_eq!(title(&db, created), "Laub rechen");
+        drop(db);
+        std::fs::remove_file(path).unwrap();
+    }
+
+    #[test]
+    fn rejects_malformed_cursors() {
+        for cursor in ["", "2a", "zz00000000000000", "000000000000002a00"] {
+            assert_eq!(decode_cursor(cursor), None, "{cursor}");
+        }
+    }
+}
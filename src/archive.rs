@@ -0,0 +1,80 @@
+//! Moves long-completed todos off the main list into `/archive`.
+
+use std::time::Duration;
+
+use axum::{extract::State, http::StatusCode, response::Html, routing::get, Router};
+use chrono::{TimeDelta, Utc};
+
+use crate::db::SharedDatabase;
+use crate::{base_path, error_status, html_escape, page_start, preferences, AppState, PAGE_END};
+
+/// How often the archive job looks for todos to archive.
+const CHECK_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+pub fn router() -> Router<AppState> {
+    Router::new().route("/archive", get(show))
+}
+
+/// Archives todos completed more than `after` ago, now and then hourly.
+pub fn spawn(db: SharedDatabase, after: TimeDelta) {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(CHECK_INTERVAL);
+        loop {
+            ticker.tick().await;
+            if db.is_read_only() {
+                continue;
+            }
+            let db = db.clone();
+            let job = tokio::task::spawn_blocking(move || {
+                db.lock().archive_completed(Utc::now() - after)
+            });
+            match job.await {
+                Ok(Ok(0)) => {}
+                Ok(Ok(count)) => println!("archived {count} completed todos"),
+                Ok(Err(err)) => eprintln!("archiving todos failed: {err:#}"),
+                Err(err) => eprintln!("archiving todos panicked: {err}"),
+            }
+        }
+    });
+}
+
+async fn show(State(state): State<AppState>) -> Result<Html<String>, StatusCode> {
+    let todos = state.db.lock().archived_todos().map_err(error_status)?;
+
+    let base = base_path();
+    let mut body = page_start();
+    body.push_str(
+        "    <h1>Archiv</h1>\n    <div class=\"subtitle\">Erledigte Todos, die nicht mehr in der Liste stehen.</div>\n    <div class=\"todo-list\">\n",
+    );
+    if todos.is_empty() {
+        body.push_str("<div class=\"subtitle\">Das Archiv ist leer.</div>\n");
+    }
+    for todo in todos {
+        let completed = todo
+            .completed_at
+            .map(|completed_at| {
+                format!(
+                    " · erledigt am {}",
+                    completed_at.format(preferences::date())
+                )
+            })
+            .unwrap_or_default();
+        body.push_str(&format!(
+            r#"<div class="todo">
+  <div class="meta">
+    <div class="title"><a href="{base}/todo/{id}">{title}</a></div>
+    <div class="time">Erstellt am {created}{completed}</div>
+  </div>
+</div>
+"#,
+            id = todo.id,
+            title = html_escape(&todo.title),
+            created = todo.created_at.format(preferences::date_time()),
+        ));
+    }
+    body.push_str(&format!(
+        "    </div>\n    <a class=\"back\" href=\"{base}/\">Zurück zur Liste</a>\n"
+    ));
+    body.push_str(PAGE_END);
+    Ok(Html(body))
+}
@@ -0,0 +1,147 @@
+//! Audit trail: who changed which todo when. Changes are recorded by the
+//! database itself; this module tells it who is asking and shows the log.
+
+use std::future::Future;
+
+use axum::{
+    extract::{Query, Request, State},
+    http::StatusCode,
+    middleware::Next,
+    response::{Html, Response},
+    routing::get,
+    Router,
+};
+use chrono::{Local, NaiveDate, Utc};
+use serde::Deserialize;
+
+use crate::db::AuditFilter;
+use crate::{base_path, error_status, html_escape, page_start, preferences, AppState, PAGE_END};
+
+/// Entries shown on the admin page, at most.
+const PAGE_SIZE: u32 = 200;
+
+tokio::task_local! {
+    static ACTOR: &'static str;
+}
+
+/// Who caused the changes made by the current task: `web`, `api` or `hook`
+/// for requests, `sync` for the sync jobs and `system` for everything else.
+pub fn actor() -> &'static str {
+    ACTOR.try_with(|actor| *actor).unwrap_or("system")
+}
+
+/// Runs `future` with its changes recorded as made by `actor`.
+pub async fn scope<F: Future>(actor: &'static str, future: F) -> F::Output {
+    ACTOR.scope(actor, future).await
+}
+
+/// Tells the audit log which way a request came in.
+pub async fn track(request: Request, next: Next) -> Response {
+    let path = request.uri().path();
+    let path = path.strip_prefix(base_path()).unwrap_or(path);
+    let actor = if path.starts_with("/api/") {
+        "api"
+    } else if path.starts_with("/hooks/") {
+        "hook"
+    } else {
+        "web"
+    };
+    scope(actor, next.run(request)).await
+}
+
+/// Filters of the admin page and the API endpoint; empty fields match
+/// everything.
+#[derive(Deserialize)]
+pub struct AuditQuery {
+    pub todo_id: Option<i64>,
+    #[serde(default)]
+    pub action: String,
+    #[serde(default)]
+    pub actor: String,
+    /// First day included, local time.
+    pub since: Option<NaiveDate>,
+    pub limit: Option<u32>,
+}
+
+impl AuditQuery {
+    pub fn filter(&self) -> AuditFilter {
+        let since = self.since.and_then(|since| {
+            since
+                .and_time(Default::default())
+                .and_local_timezone(Local)
+                .earliest()
+        });
+        AuditFilter {
+            todo_id: self.todo_id,
+            action: Some(self.action.clone()).filter(|action| !action.is_empty()),
+            actor: Some(self.actor.clone()).filter(|actor| !actor.is_empty()),
+            since: since.map(|since| since.with_timezone(&Utc)),
+            limit: self.limit,
+        }
+    }
+}
+
+pub fn router() -> Router<AppState> {
+    Router::new().route("/admin/audit", get(show))
+}
+
+async fn show(
+    State(state): State<AppState>,
+    Query(query): Query<AuditQuery>,
+) -> Result<Html<String>, StatusCode> {
+    let base = base_path();
+    let mut filter = query.filter();
+    filter.limit = Some(filter.limit.unwrap_or(PAGE_SIZE).min(PAGE_SIZE));
+    let entries = state.db.lock().audit_log(&filter).map_err(error_status)?;
+
+    let mut body = page_start();
+    body.push_str(&format!(
+        r#"    <h1>Protokoll</h1>
+    <div class="subtitle">Jede Änderung an einem Todo, neueste zuerst. Einträge lassen sich weder ändern noch löschen.</div>
+    <form method="get" action="{base}/admin/audit">
+      <input type="number" name="todo_id" placeholder="Todo" value="{todo_id}" />
+      <input type="text" name="action" placeholder="Aktion" value="{action}" />
+      <input type="text" name="actor" placeholder="Von" value="{actor}" />
+      <input type="date" name="since" title="Seit" value="{since}" />
+      <button type="submit">Filtern</button>
+    </form>
+"#,
+        todo_id = query.todo_id.map(|id| id.to_string()).unwrap_or_default(),
+        action = html_escape(&query.action),
+        actor = html_escape(&query.actor),
+        since = query.since.map(|since| since.to_string()).unwrap_or_default(),
+    ));
+    if entries.is_empty() {
+        body.push_str("    <div class=\"subtitle\">Keine Einträge.</div>\n");
+    } else {
+        body.push_str("    <div class=\"todo-list\">\n");
+        for entry in &entries {
+            let todo = match entry.todo_id {
+                Some(id) => format!("<a href=\"{base}/todo/{id}\">Todo {id}</a>"),
+                None => String::new(),
+            };
+            body.push_str(&format!(
+                r#"<div class="todo">
+  <div class="meta">
+    <div class="title">{action} {todo}</div>
+    <div class="time">{at} · {actor} · <code>{changes}</code></div>
+  </div>
+</div>
+"#,
+                action = html_escape(&entry.action),
+                at = entry
+                    .at
+                    .with_timezone(&Local)
+                    .format(preferences::date_time_seconds()),
+                actor = html_escape(&entry.actor),
+                changes = html_escape(&entry.changes.to_string()),
+            ));
+        }
+        body.push_str("    </div>\n");
+    }
+    body.push_str(&format!(
+        "    <a class=\"back\" href=\"{base}/admin\">Zurück zur Verwaltung</a>\n"
+    ));
+    body.push_str(PAGE_END);
+    Ok(Html(body))
+}
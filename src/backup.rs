@@ -0,0 +1,254 @@
+//! Scheduled copies of the database, keeping only the newest few, and named
+//! snapshots that are kept until deleted and can be restored.
+
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+
+use crate::db::SharedDatabase;
+
+const PREFIX: &str = "todo-";
+const SUFFIX: &str = ".db";
+/// Subdirectory of the backup directory that holds named snapshots.
+const SNAPSHOTS: &str = "snapshots";
+/// Prefix of the snapshot taken before each restore.
+const PRE_RESTORE: &str = "vor-wiederherstellung-";
+/// Prefix of the snapshot taken before migrating to a new schema.
+const PRE_MIGRATION: &str = "vor-migration-";
+const MAX_NAME_CHARS: usize = 64;
+
+/// Writes timestamped snapshots into `dir` and remembers the last one for the
+/// admin page.
+#[derive(Clone)]
+pub struct Backups {
+    db: SharedDatabase,
+    dir: PathBuf,
+    keep: usize,
+    key: Option<String>,
+    last: Arc<Mutex<Option<Snapshot>>>,
+}
+
+#[derive(Clone)]
+pub struct Snapshot {
+    pub path: PathBuf,
+    pub finished_at: DateTime<Utc>,
+    pub size: u64,
+}
+
+impl Backups {
+    /// `key` unlocks an encrypted database; snapshots are encrypted with it too.
+    pub fn new(db: SharedDatabase, dir: PathBuf, keep: usize, key: Option<String>) -> Self {
+        Self {
+            db,
+            dir,
+            keep,
+            key,
+            last: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    pub fn dir(&self) -> &PathBuf {
+        &self.dir
+    }
+
+    /// Writes a snapshot, then deletes all but the newest `keep` ones. Blocks
+    /// every other database access while the copy runs.
+    pub fn run(&self) -> Result<Snapshot> {
+        std::fs::create_dir_all(&self.dir)
+            .with_context(|| format!("creating {}", self.dir.display()))?;
+        let finished_at = Utc::now();
+        let name = format!("{PREFIX}{}{SUFFIX}", finished_at.format("%Y%m%d-%H%M%S"));
+        let path = self.dir.join(&name);
+        // Only complete copies get a name that pruning and restores look for.
+        let partial = self.dir.join(format!("{name}.partial"));
+
+        let result = self.db.lock().backup_to(&partial, self.key.as_deref());
+        if let Err(err) = result {
+            let _ = std::fs::remove_file(&partial);
+            return Err(err);
+        }
+        std::fs::rename(&partial, &path)?;
+
+        let snapshot = Snapshot {
+            size: std::fs::metadata(&path)?.len(),
+            path,
+            finished_at,
+        };
+        *self.last.lock().unwrap_or_else(PoisonError::into_inner) = Some(snapshot.clone());
+        self.prune()?;
+        Ok(snapshot)
+    }
+
+    pub fn last(&self) -> Option<Snapshot> {
+        self.last
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .clone()
+    }
+
+    /// Snapshot files in the backup directory, oldest first.
+    pub fn list(&self) -> Result<Vec<PathBuf>> {
+        let entries = match std::fs::read_dir(&self.dir) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(err.into()),
+        };
+        let mut snapshots = Vec::new();
+        for entry in entries {
+            let path = entry?.path();
+            let is_snapshot = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with(PREFIX) && name.ends_with(SUFFIX));
+            if is_snapshot {
+                snapshots.push(path);
+            }
+        }
+        // The timestamp in the name sorts chronologically.
+        snapshots.sort();
+        Ok(snapshots)
+    }
+
+    fn prune(&self) -> Result<()> {
+        let snapshots = self.list()?;
+        let excess = snapshots.len().saturating_sub(self.keep);
+        for path in &snapshots[..excess] {
+            std::fs::remove_file(path)
+                .with_context(|| format!("removing old backup {}", path.display()))?;
+        }
+        Ok(())
+    }
+
+    /// Copies the database into the snapshot `name`, replacing an older one
+    /// of that name.
+    pub fn snapshot(&self, name: &str) -> Result<Snapshot> {
+        let dir = self.dir.join(SNAPSHOTS);
+        std::fs::create_dir_all(&dir).with_context(|| format!("creating {}", dir.display()))?;
+        let path = dir.join(format!("{}{SUFFIX}", valid_name(name)?));
+        let partial = dir.join(format!("{name}{SUFFIX}.partial"));
+        let result = self.db.lock().backup_to(&partial, self.key.as_deref());
+        if let Err(err) = result {
+            let _ = std::fs::remove_file(&partial);
+            return Err(err);
+        }
+        std::fs::rename(&partial, &path)?;
+        Ok(Snapshot {
+            size: std::fs::metadata(&path)?.len(),
+            path,
+            finished_at: Utc::now(),
+        })
+    }
+
+    /// Named snapshots with their names, newest first.
+    pub fn snapshots(&self) -> Result<Vec<(String, Snapshot)>> {
+        let entries = match std::fs::read_dir(self.dir.join(SNAPSHOTS)) {
+            Ok(entries) => entries,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+            Err(err) => return Err(err.into()),
+        };
+        let mut snapshots = Vec::new();
+        for entry in entries {
+            let entry = entry?;
+            let path = entry.path();
+            let Some(name) = path
+                .file_name()
+                .and_then(|name| name.to_str())
+                .and_then(|name| name.strip_suffix(SUFFIX))
+            else {
+                continue;
+            };
+            let metadata = entry.metadata()?;
+            snapshots.push((
+                name.to_string(),
+                Snapshot {
+                    finished_at: metadata.modified()?.into(),
+                    size: metadata.len(),
+                    path,
+                },
+            ));
+        }
+        snapshots.sort_by_key(|(_, snapshot)| std::cmp::Reverse(snapshot.finished_at));
+        Ok(snapshots)
+    }
+
+    /// Replaces the database with the snapshot `name`, after saving the
+    /// current state as a snapshot of its own; returns that one's name.
+    pub fn restore(&self, name: &str) -> Result<String> {
+        let path = self
+            .dir
+            .join(SNAPSHOTS)
+            .join(format!("{}{SUFFIX}", valid_name(name)?));
+        if !path.is_file() {
+            anyhow::bail!("there is no snapshot named {name}");
+        }
+        let before = format!("{PRE_RESTORE}{}", Utc::now().format("%Y%m%d-%H%M%S-%3f"));
+        self.snapshot(&before)?;
+        let mut db = self.db.lock();
+        db.restore_from(&path, self.key.as_deref())?;
+        // The snapshot may write dates differently.
+        crate::preferences::load(&db)?;
+        Ok(before)
+    }
+
+    /// Saves the database as a snapshot before its schema is migrated from
+    /// version `from` to `to`; returns the snapshot's name.
+    pub fn before_migration(&self, from: usize, to: usize) -> Result<String> {
+        let name = format!(
+            "{PRE_MIGRATION}{from}-{to}-{}",
+            Utc::now().format("%Y%m%d-%H%M%S")
+        );
+        self.snapshot(&name)?;
+        Ok(name)
+    }
+
+    pub fn delete_snapshot(&self, name: &str) -> Result<()> {
+        let path = self
+            .dir
+            .join(SNAPSHOTS)
+            .join(format!("{}{SUFFIX}", valid_name(name)?));
+        std::fs::remove_file(&path).with_context(|| format!("removing {}", path.display()))
+    }
+
+    /// Writes a snapshot every `interval`, starting one interval from now.
+    pub fn spawn(&self, interval: Duration) {
+        let backups = self.clone();
+        tokio::spawn(async move {
+            let mut ticker =
+                tokio::time::interval_at(tokio::time::Instant::now() + interval, interval);
+            loop {
+                ticker.tick().await;
+                let job = backups.clone();
+                match tokio::task::spawn_blocking(move || job.run()).await {
+                    Ok(Ok(snapshot)) => println!(
+                        "backup written to {} ({} bytes)",
+                        snapshot.path.display(),
+                        snapshot.size
+                    ),
+                    Ok(Err(err)) => eprintln!("backup failed: {err:#}"),
+                    Err(err) => eprintln!("backup panicked: {err}"),
+                }
+            }
+        });
+    }
+}
+
+/// Snapshot names become file names, so only letters, digits, `-` and `_`.
+pub fn is_valid_name(name: &str) -> bool {
+    !name.is_empty()
+        && name.chars().count() <= MAX_NAME_CHARS
+        && name
+            .chars()
+            .all(|c| c.is_alphanumeric() || c == '-' || c == '_')
+}
+
+fn valid_name(name: &str) -> Result<&str> {
+    if !is_valid_name(name) {
+        anyhow::bail!(
+            "a snapshot name has up to {MAX_NAME_CHARS} letters, digits, dashes and underscores"
+        );
+    }
+    Ok(name)
+}
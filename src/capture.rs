@@ -0,0 +1,274 @@
+//! Quick capture of web pages: a bookmarklet or browser extension sends the
+//! page's title, address and selected text to `/capture`, which files a todo
+//! with a note holding the address and the quote. Other sites may only post
+//! to it, from scripts or forms, if `--capture-origins` lets them; without
+//! that, the bookmarklet opens the prefilled form instead.
+
+use axum::{
+    extract::{FromRequest, Query, Request, State},
+    http::{header, HeaderMap, HeaderValue, Method, StatusCode},
+    middleware::{self, Next},
+    response::{Html, IntoResponse, Redirect, Response},
+    routing::get,
+    Form, Json, Router,
+};
+use serde::Deserialize;
+use tower_http::cors::{AllowOrigin, CorsLayer};
+
+use crate::validate::{self, Invalid, NoteInput};
+use crate::{
+    base_path, error_notice, error_status, html_escape, invalid_attr, page_start, public_url,
+    AppState, PAGE_END,
+};
+
+/// A page as the bookmarklet sees it; every field may be missing.
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct Capture {
+    title: String,
+    url: String,
+    selection: String,
+}
+
+/// `origins` may post to `/capture` from their pages; `*` allows every site.
+pub fn router(origins: &[String]) -> anyhow::Result<Router<AppState>> {
+    let origins = Origins::parse(origins)?;
+    let router = Router::new()
+        .route("/capture", get(show).post(capture))
+        .route_layer(middleware::from_fn_with_state(
+            origins.clone(),
+            check_origin,
+        ));
+    let allow_origin = if origins.any {
+        AllowOrigin::any()
+    } else if !origins.list.is_empty() {
+        AllowOrigin::list(origins.list)
+    } else {
+        return Ok(router);
+    };
+    let cors = CorsLayer::new()
+        .allow_origin(allow_origin)
+        .allow_methods([Method::POST])
+        .allow_headers([header::CONTENT_TYPE]);
+    Ok(router.layer(cors))
+}
+
+/// The sites allowed to post besides the app's own pages.
+#[derive(Clone)]
+struct Origins {
+    any: bool,
+    list: Vec<HeaderValue>,
+}
+
+impl Origins {
+    fn parse(origins: &[String]) -> anyhow::Result<Self> {
+        let list = origins
+            .iter()
+            .filter(|origin| *origin != "*")
+            .map(|origin| {
+                HeaderValue::from_str(origin.trim_end_matches('/'))
+                    .map_err(|_| anyhow::anyhow!("invalid capture origin {origin:?}"))
+            })
+            .collect::<anyhow::Result<_>>()?;
+        Ok(Self {
+            any: origins.iter().any(|origin| origin == "*"),
+            list,
+        })
+    }
+
+    /// Whether a post comes from the app's own pages, an allowed site or no
+    /// browser at all. CORS doesn't stop forms and other simple requests,
+    /// so those from other sites are turned away here.
+    fn trust(&self, headers: &HeaderMap) -> bool {
+        let header = |name| headers.get(name).and_then(|value| value.to_str().ok());
+        let fetch_site = header("sec-fetch-site");
+        if fetch_site == Some("same-origin") {
+            return true;
+        }
+        match header("origin") {
+            Some(origin) => {
+                self.any
+                    || self.list.iter().any(|allowed| allowed == origin)
+                    || is_own(origin, header("host"))
+            }
+            // Not from a browser, or from one too old to send an origin with
+            // every post; most of those still say whether another site did.
+            None => !matches!(fetch_site, Some("cross-site" | "same-site")),
+        }
+    }
+}
+
+/// Whether `origin` is the app's: the host the request went to, or that of
+/// `--public-url`.
+fn is_own(origin: &str, host: Option<&str>) -> bool {
+    let authority = |url: &'static str| {
+        url.split_once("://")
+            .map(|(_, rest)| rest.split('/').next().unwrap_or(rest))
+    };
+    let Some((_, origin)) = origin.split_once("://") else {
+        return false;
+    };
+    Some(origin) == host || Some(origin) == authority(public_url())
+}
+
+async fn check_origin(State(origins): State<Origins>, request: Request, next: Next) -> Response {
+    if request.method() == Method::POST && !origins.trust(request.headers()) {
+        return StatusCode::FORBIDDEN.into_response();
+    }
+    next.run(request).await
+}
+
+/// The form, prefilled from the query, for when a script may not post.
+async fn show(Query(page): Query<Capture>) -> Html<String> {
+    Html(render(&page, None))
+}
+
+/// Files the page as a todo. JSON bodies get the todo back as JSON, forms
+/// are sent on to it.
+async fn capture(State(state): State<AppState>, request: Request) -> Result<Response, StatusCode> {
+    let json = request
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("application/json"));
+    let page = if json {
+        let Json(page) = Json::<Capture>::from_request(request, &state)
+            .await
+            .map_err(|_| StatusCode::BAD_REQUEST)?;
+        page
+    } else {
+        let Form(page) = Form::<Capture>::from_request(request, &state)
+            .await
+            .map_err(|_| StatusCode::BAD_REQUEST)?;
+        page
+    };
+
+    let input = match note(&page) {
+        Ok(input) => input,
+        Err(invalid) if json => return Ok(invalid.into_response()),
+        Err(invalid) => {
+            let body = render(&page, Some(&invalid));
+            return Ok((StatusCode::UNPROCESSABLE_ENTITY, Html(body)).into_response());
+        }
+    };
+    let todo = state
+        .db
+        .lock()
+        .add_todo_with_note(&input.title, &input.body)
+        .map_err(error_status)?;
+    if json {
+        return Ok((StatusCode::CREATED, Json(todo)).into_response());
+    }
+    Ok(Redirect::to(&format!("{}/todo/{}", base_path(), todo.id)).into_response())
+}
+
+/// The page's title on one line, or its address if it has none, shortened
+/// to fit; the note gets the address and the selection as a quote.
+fn note(page: &Capture) -> Result<NoteInput, Invalid> {
+    let url = page.url.trim();
+    let mut title = page.title.split_whitespace().collect::<Vec<_>>().join(" ");
+    if title.is_empty() {
+        title = url.to_string();
+    }
+    if title.chars().count() > validate::MAX_TITLE_CHARS {
+        title = title.chars().take(validate::MAX_TITLE_CHARS - 1).collect();
+        title.push('…');
+    }
+
+    let mut body = url.to_string();
+    let selection = page.selection.trim();
+    if !selection.is_empty() {
+        if !body.is_empty() {
+            body.push_str("\n\n");
+        }
+        for line in selection.lines() {
+            body.push_str(&format!("> {}\n", line.trim_end()));
+        }
+    }
+    validate::note(&title, &body)
+}
+
+fn render(page: &Capture, invalid: Option<&Invalid>) -> String {
+    let base = base_path();
+    let mut body = page_start();
+    body.push_str(
+        "    <h1>Seite erfassen</h1>\n    <div class=\"subtitle\">Legt ein Todo an und merkt sich Adresse und markierten Text in einer Notiz dazu.</div>\n",
+    );
+    if let Some(invalid) = invalid {
+        body.push_str(&error_notice(invalid));
+    }
+    body.push_str(&format!(
+        r#"    <form class="stacked" method="post" action="{base}/capture">
+      <input type="text" name="title" placeholder="Titel" value="{title}"{title_invalid} />
+      <input type="url" name="url" placeholder="Adresse" value="{url}" />
+      <textarea name="selection" rows="4" placeholder="Markierter Text (optional)"{body_invalid}>{selection}</textarea>
+      <button type="submit">Als Todo anlegen</button>
+    </form>
+    <p>Zum Erfassen mit einem Klick <a href="{bookmarklet}">Todo erfassen</a> in die Lesezeichenleiste ziehen.</p>
+    <a class="back" href="{base}/">Zurück zur Liste</a>
+"#,
+        title = html_escape(&page.title),
+        title_invalid = invalid_attr(invalid, "title"),
+        url = html_escape(&page.url),
+        selection = html_escape(&page.selection),
+        body_invalid = invalid_attr(invalid, "body"),
+        bookmarklet = html_escape(&bookmarklet()),
+    ));
+    body.push_str(PAGE_END);
+    body
+}
+
+/// Posts the current page as JSON, and opens the form instead if the site
+/// or `--capture-origins` doesn't allow that.
+fn bookmarklet() -> String {
+    let capture = format!("{}/capture", public_url());
+    format!(
+        "javascript:(()=>{{const p={{title:document.title,url:location.href,selection:String(getSelection())}};\
+fetch('{capture}',{{method:'POST',headers:{{'Content-Type':'application/json'}},body:JSON.stringify(p)}})\
+.then(r=>{{if(!r.ok)throw r;alert('Als Todo erfasst')}})\
+.catch(()=>open('{capture}?'+new URLSearchParams(p)))}})()"
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn headers(pairs: &[(&'static str, &'static str)]) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        for (name, value) in pairs {
+            headers.insert(*name, HeaderValue::from_static(value));
+        }
+        headers
+    }
+
+    #[test]
+    fn turns_away_other_sites() {
+        let origins = Origins::parse(&["https://github.com/".to_string()]).unwrap();
+        let host = ("host", "todo.example.org");
+        // A form on another site, posting in the user's browser.
+        let form = headers(&[
+            host,
+            ("origin", "https://evil.example"),
+            ("sec-fetch-site", "cross-site"),
+        ]);
+        assert!(!origins.trust(&form));
+        assert!(!origins.trust(&headers(&[host, ("sec-fetch-site", "cross-site")])));
+        assert!(!origins.trust(&headers(&[host, ("origin", "null")])));
+
+        assert!(origins.trust(&headers(&[host, ("origin", "https://github.com")])));
+        assert!(origins.trust(&headers(&[host, ("origin", "https://todo.example.org")])));
+        // Behind a proxy that changes the host, the browser still tells.
+        let proxied = headers(&[
+            ("host", "127.0.0.1:5876"),
+            ("origin", "https://todo.example.org"),
+            ("sec-fetch-site", "same-origin"),
+        ]);
+        assert!(origins.trust(&proxied));
+        // curl and other clients that aren't browsers.
+        assert!(origins.trust(&headers(&[host])));
+
+        let any = Origins::parse(&["*".to_string()]).unwrap();
+        assert!(any.trust(&form));
+    }
+}
@@ -0,0 +1,269 @@
+//! Typed client for a simpletodo server's JSON API (`/api/v1`).
+//!
+//! ```no_run
+//! # async fn example() -> Result<(), simpletodo::client::Error> {
+//! use simpletodo::client::{Client, NewTodo};
+//!
+//! let client = Client::new("http://localhost:5876");
+//! let todo = client.add_todo(&NewTodo::new("Milch kaufen")).await?;
+//! client.complete_todo(todo.id).await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use std::fmt;
+
+use chrono::NaiveDate;
+use reqwest::{RequestBuilder, Response, StatusCode};
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+
+use crate::models::Todo;
+
+/// Why a request failed.
+#[derive(Debug)]
+pub enum Error {
+    /// The server couldn't be reached or answered with something unreadable.
+    Http(reqwest::Error),
+    NotFound,
+    /// The todo changed in the meantime, or is already completed.
+    Conflict,
+    /// The submitted fields were rejected.
+    Invalid(Vec<FieldError>),
+    /// Any other unsuccessful answer.
+    Status(StatusCode),
+}
+
+pub type Result<T, E = Error> = std::result::Result<T, E>;
+
+/// A rejected field, with a message meant for the person who typed it.
+#[derive(Debug, Clone, Deserialize)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+#[derive(Deserialize)]
+struct ValidationErrors {
+    errors: Vec<FieldError>,
+}
+
+impl fmt::Display for Error {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Error::Http(err) => write!(f, "{err}"),
+            Error::NotFound => write!(f, "not found"),
+            Error::Conflict => write!(f, "conflicting change"),
+            Error::Invalid(errors) => {
+                let messages: Vec<&str> =
+                    errors.iter().map(|error| error.message.as_str()).collect();
+                write!(f, "{}", messages.join(" "))
+            }
+            Error::Status(status) => write!(f, "server answered {status}"),
+        }
+    }
+}
+
+impl std::error::Error for Error {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            // Its message is already part of ours.
+            Error::Http(err) => err.source(),
+            _ => None,
+        }
+    }
+}
+
+impl From<reqwest::Error> for Error {
+    fn from(err: reqwest::Error) -> Self {
+        Error::Http(err)
+    }
+}
+
+/// A todo to create.
+#[derive(Debug, Clone, Serialize)]
+pub struct NewTodo {
+    pub title: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deadline: Option<NaiveDate>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub location: Option<String>,
+}
+
+impl NewTodo {
+    pub fn new(title: impl Into<String>) -> Self {
+        Self {
+            title: title.into(),
+            deadline: None,
+            location: None,
+        }
+    }
+}
+
+/// Fields to change with [`Client::update_todo`]: `None` keeps a field,
+/// `Some(None)` clears it.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct TodoPatch {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub title: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deadline: Option<Option<NaiveDate>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub location: Option<Option<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub points: Option<Option<u32>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub goal_id: Option<Option<i64>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub start_date: Option<Option<NaiveDate>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub habit: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub floating: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub someday: Option<bool>,
+    /// Fail with [`Error::Conflict`] if the todo is no longer at this version.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<i64>,
+}
+
+/// Filters for [`Client::list_todos`] and [`Client::list_page`]; the default
+/// lists every todo that isn't archived.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ListQuery {
+    /// `Some(false)` for open todos only, `Some(true)` for completed ones.
+    #[serde(
+        rename = "status",
+        serialize_with = "status",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub completed: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deadline_from: Option<NaiveDate>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub deadline_to: Option<NaiveDate>,
+    /// Text the title contains.
+    #[serde(rename = "q", skip_serializing_if = "Option::is_none")]
+    pub text: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub location: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub goal_id: Option<i64>,
+    /// `Some(true)` for todos put off to someday only, `Some(false)` for
+    /// active ones.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub someday: Option<bool>,
+}
+
+fn status<S: serde::Serializer>(
+    completed: &Option<bool>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    match completed {
+        Some(true) => serializer.serialize_str("done"),
+        _ => serializer.serialize_str("open"),
+    }
+}
+
+/// One page of [`Client::list_page`].
+#[derive(Debug, Deserialize)]
+pub struct Page {
+    pub todos: Vec<Todo>,
+    /// Pass to the next call to continue; `None` on the last page.
+    pub next_cursor: Option<String>,
+}
+
+pub struct Client {
+    base: String,
+    http: reqwest::Client,
+}
+
+impl Client {
+    /// `server` is the address the web UI is served at, e.g.
+    /// `https://todo.example.org` or `http://localhost:5876/todo`.
+    pub fn new(server: &str) -> Self {
+        Self::with_http_client(server, reqwest::Client::new())
+    }
+
+    /// Like [`Client::new`], with a client configured by the caller (timeouts,
+    /// proxies, …).
+    pub fn with_http_client(server: &str, http: reqwest::Client) -> Self {
+        Self {
+            base: format!("{}/api/v1", server.trim_end_matches('/')),
+            http,
+        }
+    }
+
+    pub async fn list_todos(&self, query: &ListQuery) -> Result<Vec<Todo>> {
+        let request = self.http.get(format!("{}/todos", self.base)).query(query);
+        json(send(request).await?).await
+    }
+
+    /// Up to `limit` todos, continuing after `cursor` (from the previous
+    /// page's [`Page::next_cursor`]).
+    pub async fn list_page(
+        &self,
+        query: &ListQuery,
+        limit: u32,
+        cursor: Option<&str>,
+    ) -> Result<Page> {
+        let mut request = self
+            .http
+            .get(format!("{}/todos", self.base))
+            .query(query)
+            .query(&[("limit", limit)]);
+        if let Some(cursor) = cursor {
+            request = request.query(&[("cursor", cursor)]);
+        }
+        json(send(request).await?).await
+    }
+
+    pub async fn get_todo(&self, id: i64) -> Result<Todo> {
+        let request = self.http.get(format!("{}/todos/{id}", self.base));
+        json(send(request).await?).await
+    }
+
+    pub async fn add_todo(&self, todo: &NewTodo) -> Result<Todo> {
+        let request = self.http.post(format!("{}/todos", self.base)).json(todo);
+        json(send(request).await?).await
+    }
+
+    pub async fn update_todo(&self, id: i64, patch: &TodoPatch) -> Result<Todo> {
+        let request = self
+            .http
+            .patch(format!("{}/todos/{id}", self.base))
+            .json(patch);
+        json(send(request).await?).await
+    }
+
+    pub async fn complete_todo(&self, id: i64) -> Result<Todo> {
+        let request = self.http.post(format!("{}/todos/{id}/complete", self.base));
+        json(send(request).await?).await
+    }
+
+    pub async fn delete_todo(&self, id: i64) -> Result<()> {
+        let request = self.http.delete(format!("{}/todos/{id}", self.base));
+        send(request).await?;
+        Ok(())
+    }
+}
+
+/// Sends `request` and turns unsuccessful answers into [`Error`]s.
+async fn send(request: RequestBuilder) -> Result<Response> {
+    let response = request.send().await?;
+    match response.status() {
+        status if status.is_success() => Ok(response),
+        StatusCode::NOT_FOUND => Err(Error::NotFound),
+        StatusCode::CONFLICT => Err(Error::Conflict),
+        StatusCode::UNPROCESSABLE_ENTITY => match response.json::<ValidationErrors>().await {
+            Ok(body) => Err(Error::Invalid(body.errors)),
+            // Not a validation error but a body the server couldn't parse.
+            Err(_) => Err(Error::Status(StatusCode::UNPROCESSABLE_ENTITY)),
+        },
+        status => Err(Error::Status(status)),
+    }
+}
+
+async fn json<T: DeserializeOwned>(response: Response) -> Result<T> {
+    Ok(response.json().await?)
+}
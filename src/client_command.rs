@@ -0,0 +1,148 @@
+//! The `client` subcommand: the todo list of a running server on the
+//! command line.
+
+use anyhow::Result;
+use chrono::Local;
+use clap::Subcommand;
+use simpletodo::client::{self, Client, ListQuery, NewTodo};
+
+use crate::models::Todo;
+use crate::preferences;
+
+#[derive(Subcommand)]
+pub enum ClientCommand {
+    /// List todos
+    List {
+        /// Only show open todos
+        #[arg(long)]
+        open: bool,
+    },
+    /// Add a new todo
+    Add {
+        /// Title of the todo
+        #[arg(required = true)]
+        title: Vec<String>,
+    },
+    /// Mark a todo as done
+    Done { id: i64 },
+    /// Show a single todo
+    Show { id: i64 },
+}
+
+pub async fn run(server: &str, command: ClientCommand) -> Result<()> {
+    let client = Client::new(server);
+
+    match command {
+        ClientCommand::List { open } => {
+            let query = ListQuery {
+                completed: open.then_some(false),
+                ..Default::default()
+            };
+            let todos = client
+                .list_todos(&query)
+                .await
+                .map_err(|err| describe(server, None, err))?;
+            print_table(&todos);
+        }
+        ClientCommand::Add { title } => {
+            let todo = client
+                .add_todo(&NewTodo::new(title.join(" ")))
+                .await
+                .map_err(|err| describe(server, None, err))?;
+            println!("added todo {}", todo.id);
+        }
+        ClientCommand::Done { id } => {
+            client
+                .complete_todo(id)
+                .await
+                .map_err(|err| describe(server, Some(id), err))?;
+            println!("completed todo {id}");
+        }
+        ClientCommand::Show { id } => {
+            let todo = client
+                .get_todo(id)
+                .await
+                .map_err(|err| describe(server, Some(id), err))?;
+            print_todo(&todo);
+        }
+    }
+
+    Ok(())
+}
+
+/// Phrases a failed request for the command line, naming the todo `id` it
+/// was about.
+fn describe(server: &str, id: Option<i64>, err: client::Error) -> anyhow::Error {
+    match (err, id) {
+        (client::Error::NotFound, Some(id)) => anyhow::anyhow!("todo {id} not found"),
+        (client::Error::Conflict, Some(id)) => anyhow::anyhow!("todo {id} is already completed"),
+        (err @ client::Error::Http(_), _) => {
+            anyhow::Error::new(err).context(format!("connecting to {server}"))
+        }
+        (err, _) => err.into(),
+    }
+}
+
+fn status_label(todo: &Todo) -> &'static str {
+    if todo.completed_at.is_some() {
+        "done"
+    } else {
+        "open"
+    }
+}
+
+fn print_table(todos: &[Todo]) {
+    if todos.is_empty() {
+        println!("no todos");
+        return;
+    }
+
+    let id_width = todos
+        .iter()
+        .map(|todo| todo.id.to_string().len())
+        .max()
+        .unwrap_or(0)
+        .max("ID".len());
+
+    println!(
+        "{:>id_width$}  {:<6}  {:<16}  TITLE",
+        "ID", "STATUS", "CREATED"
+    );
+    for todo in todos {
+        println!(
+            "{:>id_width$}  {:<6}  {:<16}  {}",
+            todo.id,
+            status_label(todo),
+            todo.created_at
+                .with_timezone(&Local)
+                .format(preferences::date_time()),
+            todo.title
+        );
+    }
+}
+
+fn print_todo(todo: &Todo) {
+    println!("id:        {}", todo.id);
+    println!("title:     {}", todo.title);
+    println!("status:    {}", status_label(todo));
+    println!(
+        "created:   {}",
+        todo.created_at
+            .with_timezone(&Local)
+            .format(preferences::date_time())
+    );
+    if let Some(deadline) = todo.deadline {
+        println!("deadline:  {}", deadline.format(preferences::date()));
+    }
+    if let Some(location) = &todo.location {
+        println!("location:  {location}");
+    }
+    if let Some(completed_at) = todo.completed_at {
+        println!(
+            "completed: {}",
+            completed_at
+                .with_timezone(&Local)
+                .format(preferences::date_time())
+        );
+    }
+}
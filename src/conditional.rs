@@ -0,0 +1,132 @@
+//! Cached index page and its validators for conditional GETs (`ETag` /
+//! `Last-Modified`).
+
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+use axum::body::Bytes;
+use axum::http::{header, HeaderMap, HeaderValue, StatusCode};
+use axum::response::{IntoResponse, Response};
+use chrono::{DateTime, TimeDelta, Utc};
+use sha2::{Digest, Sha256};
+
+const HTTP_DATE: &str = "%a, %d %b %Y %H:%M:%S GMT";
+
+/// Writes by other processes (e.g. `simpletodo tui` on the same file) don't
+/// bump the connection's generation, so a cached page is re-rendered after
+/// this long regardless.
+const MAX_AGE: Duration = Duration::from_secs(30);
+
+/// The last rendered page, reused while the database generation is unchanged.
+#[derive(Clone)]
+pub struct PageCache(Arc<Mutex<Cached>>);
+
+struct Cached {
+    page: Option<Page>,
+    modified_at: DateTime<Utc>,
+}
+
+struct Page {
+    generation: u64,
+    rendered_at: Instant,
+    body: Bytes,
+    etag: String,
+}
+
+#[derive(Clone)]
+pub struct Validators {
+    etag: String,
+    last_modified: DateTime<Utc>,
+}
+
+impl PageCache {
+    pub fn new() -> Self {
+        Self(Arc::new(Mutex::new(Cached {
+            page: None,
+            modified_at: Utc::now(),
+        })))
+    }
+
+    pub fn get(&self, generation: u64) -> Option<(Bytes, Validators)> {
+        let cached = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        let page = cached.page.as_ref()?;
+        if page.generation != generation || page.rendered_at.elapsed() >= MAX_AGE {
+            return None;
+        }
+        Some((
+            page.body.clone(),
+            Validators {
+                etag: page.etag.clone(),
+                last_modified: cached.modified_at,
+            },
+        ))
+    }
+
+    pub fn store(&self, generation: u64, body: String) -> (Bytes, Validators) {
+        let digest = Sha256::digest(body.as_bytes());
+        let etag = format!("\"{}\"", hex::encode(&digest[..12]));
+
+        let mut cached = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        let previous = cached.page.as_ref().map(|page| page.etag.as_str());
+        if previous.is_some_and(|previous| previous != etag) {
+            // HTTP dates have second precision; never hand out the same
+            // Last-Modified for two different pages.
+            cached.modified_at = Utc::now().max(cached.modified_at + TimeDelta::seconds(1));
+        }
+
+        let body = Bytes::from(body);
+        cached.page = Some(Page {
+            generation,
+            rendered_at: Instant::now(),
+            body: body.clone(),
+            etag: etag.clone(),
+        });
+        (
+            body,
+            Validators {
+                etag,
+                last_modified: cached.modified_at,
+            },
+        )
+    }
+}
+
+impl Validators {
+    /// Whether the client's cached copy is still current.
+    pub fn matches(&self, headers: &HeaderMap) -> bool {
+        if let Some(if_none_match) = headers
+            .get(header::IF_NONE_MATCH)
+            .and_then(|value| value.to_str().ok())
+        {
+            return if_none_match.split(',').any(|tag| {
+                let tag = tag.trim();
+                tag == "*" || tag.trim_start_matches("W/") == self.etag
+            });
+        }
+
+        headers
+            .get(header::IF_MODIFIED_SINCE)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| DateTime::parse_from_rfc2822(value).ok())
+            .is_some_and(|since| self.last_modified.timestamp() <= since.timestamp())
+    }
+
+    pub fn headers(&self) -> [(header::HeaderName, HeaderValue); 3] {
+        [
+            (
+                header::ETAG,
+                HeaderValue::from_str(&self.etag).expect("etag is ascii"),
+            ),
+            (
+                header::LAST_MODIFIED,
+                HeaderValue::from_str(&self.last_modified.format(HTTP_DATE).to_string())
+                    .expect("http date is ascii"),
+            ),
+            (header::CACHE_CONTROL, HeaderValue::from_static("no-cache")),
+        ]
+    }
+
+    pub fn not_modified(&self) -> Response {
+        (StatusCode::NOT_MODIFIED, self.headers()).into_response()
+    }
+}
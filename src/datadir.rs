@@ -0,0 +1,44 @@
+//! Where simpletodo keeps its files unless told otherwise.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::{Context, Result};
+
+/// Database file name, in the data directory or (for setups predating it) in
+/// the working directory.
+const DATABASE: &str = "todo.db";
+
+/// `$XDG_DATA_HOME/simpletodo` on Linux, `~/Library/Application
+/// Support/simpletodo` on macOS and `%APPDATA%\simpletodo` on Windows.
+pub struct DataDir(PathBuf);
+
+impl DataDir {
+    /// `dir` if given, else the platform data directory. A `todo.db` left in
+    /// the working directory by older versions keeps being used.
+    pub fn resolve(dir: Option<PathBuf>) -> Self {
+        if let Some(dir) = dir {
+            return Self(dir);
+        }
+        if Path::new(DATABASE).exists() {
+            return Self(PathBuf::from("."));
+        }
+        match dirs::data_dir() {
+            Some(data) => Self(data.join("simpletodo")),
+            None => Self(PathBuf::from(".")),
+        }
+    }
+
+    pub fn database(&self) -> Result<PathBuf> {
+        create(&self.0)?;
+        Ok(self.0.join(DATABASE))
+    }
+
+    /// Created by the backup job when it first runs.
+    pub fn backups(&self) -> PathBuf {
+        self.0.join("backups")
+    }
+}
+
+fn create(dir: &Path) -> Result<()> {
+    std::fs::create_dir_all(dir).with_context(|| format!("creating {}", dir.display()))
+}
@@ -1,25 +1,525 @@
-use std::path::Path;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::time::Duration;
 
 use anyhow::Context;
-use chrono::{DateTime, Utc};
-use rusqlite::{params, Connection};
+use chrono::{DateTime, Local, NaiveDate, Utc};
+use rusqlite::backup::Backup;
+use rusqlite::hooks::Action;
+use rusqlite::types::Value;
+use rusqlite::{
+    params, params_from_iter, Connection, OpenFlags, OptionalExtension, Row, Transaction,
+    TransactionBehavior,
+};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Map};
 
-use crate::models::Todo;
+use crate::aging::AgeThresholds;
+use crate::audit;
+use crate::models::{
+    AuditEntry, Goal, MailQueueStatus, Note, OAuthToken, Pomodoro, PushSubscription, QueuedMail,
+    Relation, RelationKind, Reminder, Status, Todo, Tombstone,
+};
+use crate::preferences::{DateFormat, Preferences};
+use crate::reminders;
+use crate::retention::{self, RetentionPolicy};
+use crate::validate::TodoInput;
+
+/// Schema changes on top of the base tables, applied in order and tracked in
+/// `PRAGMA user_version`, each with the step that undoes it. Only ever
+/// append to this list.
+const MIGRATIONS: &[(&str, &str)] = &[
+    (
+        "ALTER TABLE todos ADD COLUMN version INTEGER NOT NULL DEFAULT 1",
+        "ALTER TABLE todos DROP COLUMN version",
+    ),
+    (
+        "ALTER TABLE todos ADD COLUMN deadline TEXT",
+        "ALTER TABLE todos DROP COLUMN deadline",
+    ),
+    (
+        "CREATE TABLE feature_flags (name TEXT PRIMARY KEY, enabled INTEGER NOT NULL)",
+        "DROP TABLE feature_flags",
+    ),
+    (
+        "CREATE TABLE goals (id INTEGER PRIMARY KEY AUTOINCREMENT, title TEXT NOT NULL, created_at TEXT NOT NULL)",
+        "DROP TABLE goals",
+    ),
+    (
+        "ALTER TABLE todos ADD COLUMN goal_id INTEGER REFERENCES goals(id) ON DELETE SET NULL",
+        "ALTER TABLE todos DROP COLUMN goal_id",
+    ),
+    (
+        "CREATE TABLE notes (id INTEGER PRIMARY KEY AUTOINCREMENT, title TEXT NOT NULL, body TEXT NOT NULL, created_at TEXT NOT NULL, todo_id INTEGER REFERENCES todos(id) ON DELETE SET NULL)",
+        "DROP TABLE notes",
+    ),
+    (
+        "ALTER TABLE todos ADD COLUMN location TEXT",
+        "ALTER TABLE todos DROP COLUMN location",
+    ),
+    (
+        "ALTER TABLE todos ADD COLUMN points INTEGER",
+        "ALTER TABLE todos DROP COLUMN points",
+    ),
+    (
+        "CREATE TABLE todo_relations (todo_id INTEGER NOT NULL REFERENCES todos(id) ON DELETE CASCADE, related_id INTEGER NOT NULL REFERENCES todos(id) ON DELETE CASCADE, kind TEXT NOT NULL, PRIMARY KEY (todo_id, related_id, kind))",
+        "DROP TABLE todo_relations",
+    ),
+    (
+        "ALTER TABLE todos ADD COLUMN archived_at TEXT",
+        "ALTER TABLE todos DROP COLUMN archived_at",
+    ),
+    (
+        "CREATE TABLE reminders (id INTEGER PRIMARY KEY AUTOINCREMENT, todo_id INTEGER NOT NULL REFERENCES todos(id) ON DELETE CASCADE, remind_at TEXT NOT NULL)",
+        "DROP TABLE reminders",
+    ),
+    (
+        "CREATE TABLE notification_jobs (key TEXT NOT NULL, channel TEXT NOT NULL, attempts INTEGER NOT NULL, sent_at TEXT, last_error TEXT, PRIMARY KEY (key, channel))",
+        "DROP TABLE notification_jobs",
+    ),
+    (
+        "CREATE TABLE settings (name TEXT PRIMARY KEY, value TEXT NOT NULL)",
+        "DROP TABLE settings",
+    ),
+    (
+        "CREATE TABLE push_subscriptions (endpoint TEXT PRIMARY KEY, p256dh TEXT NOT NULL, auth TEXT NOT NULL, created_at TEXT NOT NULL)",
+        "DROP TABLE push_subscriptions",
+    ),
+    (
+        "ALTER TABLE reminders ADD COLUMN offset_minutes INTEGER",
+        "ALTER TABLE reminders DROP COLUMN offset_minutes",
+    ),
+    (
+        "CREATE TABLE statuses (id INTEGER PRIMARY KEY AUTOINCREMENT, name TEXT NOT NULL UNIQUE, position INTEGER NOT NULL, done INTEGER NOT NULL)",
+        "DROP TABLE statuses",
+    ),
+    (
+        "INSERT INTO statuses (name, position, done) VALUES ('Backlog', 1, 0), ('In Arbeit', 2, 0), ('Wartet', 3, 0), ('Erledigt', 4, 1)",
+        "DELETE FROM statuses",
+    ),
+    (
+        "ALTER TABLE todos ADD COLUMN status_id INTEGER REFERENCES statuses(id) ON DELETE SET NULL",
+        "ALTER TABLE todos DROP COLUMN status_id",
+    ),
+    // Fills in the column added above; nothing to undo before it is dropped.
+    (
+        "UPDATE todos SET status_id = (SELECT id FROM statuses WHERE done = (todos.completed_at IS NOT NULL) ORDER BY position LIMIT 1)",
+        "",
+    ),
+    (
+        "ALTER TABLE goals ADD COLUMN color TEXT",
+        "ALTER TABLE goals DROP COLUMN color",
+    ),
+    (
+        "ALTER TABLE goals ADD COLUMN icon TEXT",
+        "ALTER TABLE goals DROP COLUMN icon",
+    ),
+    (
+        "ALTER TABLE todos ADD COLUMN start_date TEXT",
+        "ALTER TABLE todos DROP COLUMN start_date",
+    ),
+    (
+        "CREATE TABLE focus (todo_id INTEGER NOT NULL REFERENCES todos(id) ON DELETE CASCADE, day TEXT NOT NULL, PRIMARY KEY (todo_id, day))",
+        "DROP TABLE focus",
+    ),
+    (
+        "CREATE TABLE pomodoros (id INTEGER PRIMARY KEY AUTOINCREMENT, todo_id INTEGER NOT NULL REFERENCES todos(id) ON DELETE CASCADE, started_at TEXT NOT NULL, paused_at TEXT, paused_seconds INTEGER NOT NULL DEFAULT 0, finished_at TEXT)",
+        "DROP TABLE pomodoros",
+    ),
+    (
+        "ALTER TABLE todos ADD COLUMN habit INTEGER NOT NULL DEFAULT 0",
+        "ALTER TABLE todos DROP COLUMN habit",
+    ),
+    (
+        "CREATE TABLE habit_days (todo_id INTEGER NOT NULL REFERENCES todos(id) ON DELETE CASCADE, day TEXT NOT NULL, PRIMARY KEY (todo_id, day))",
+        "DROP TABLE habit_days",
+    ),
+    (
+        "CREATE TABLE share_links (todo_id INTEGER PRIMARY KEY REFERENCES todos(id) ON DELETE CASCADE, token TEXT NOT NULL UNIQUE, created_at TEXT NOT NULL)",
+        "DROP TABLE share_links",
+    ),
+    (
+        "CREATE TABLE audit_log (id INTEGER PRIMARY KEY AUTOINCREMENT, at TEXT NOT NULL, actor TEXT NOT NULL, action TEXT NOT NULL, todo_id INTEGER, changes TEXT NOT NULL)",
+        "DROP TABLE audit_log",
+    ),
+    (
+        "CREATE INDEX audit_log_todo ON audit_log (todo_id)",
+        "DROP INDEX audit_log_todo",
+    ),
+    (
+        "CREATE TRIGGER audit_log_no_update BEFORE UPDATE ON audit_log BEGIN SELECT RAISE(ABORT, 'audit_log is append-only'); END",
+        "DROP TRIGGER audit_log_no_update",
+    ),
+    (
+        "CREATE TRIGGER audit_log_no_delete BEFORE DELETE ON audit_log BEGIN SELECT RAISE(ABORT, 'audit_log is append-only'); END",
+        "DROP TRIGGER audit_log_no_delete",
+    ),
+    (
+        "CREATE TABLE tombstones (id INTEGER PRIMARY KEY AUTOINCREMENT, kind TEXT NOT NULL, object_id INTEGER NOT NULL, title TEXT NOT NULL, deleted_at TEXT NOT NULL, rows TEXT NOT NULL)",
+        "DROP TABLE tombstones",
+    ),
+    (
+        "ALTER TABLE todos ADD COLUMN floating INTEGER NOT NULL DEFAULT 0",
+        "ALTER TABLE todos DROP COLUMN floating",
+    ),
+    (
+        "CREATE TABLE postponements (todo_id INTEGER NOT NULL REFERENCES todos(id) ON DELETE CASCADE, day TEXT NOT NULL, deadline TEXT NOT NULL, PRIMARY KEY (todo_id, day))",
+        "DROP TABLE postponements",
+    ),
+    (
+        "ALTER TABLE todos ADD COLUMN someday_at TEXT",
+        "ALTER TABLE todos DROP COLUMN someday_at",
+    ),
+    // Deleting is allowed for entries older than the retention job's cutoff.
+    (
+        "DROP TRIGGER audit_log_no_delete",
+        "CREATE TRIGGER audit_log_no_delete BEFORE DELETE ON audit_log BEGIN SELECT RAISE(ABORT, 'audit_log is append-only'); END",
+    ),
+    (
+        "CREATE TRIGGER audit_log_no_delete BEFORE DELETE ON audit_log WHEN OLD.at >= (SELECT COALESCE(MAX(value), '') FROM settings WHERE name = 'audit_purged_before') BEGIN SELECT RAISE(ABORT, 'audit_log is append-only'); END",
+        "DROP TRIGGER audit_log_no_delete",
+    ),
+    (
+        "CREATE TABLE mail_queue (id INTEGER PRIMARY KEY AUTOINCREMENT, recipient TEXT NOT NULL, subject TEXT NOT NULL, body TEXT NOT NULL, created_at TEXT NOT NULL, attempts INTEGER NOT NULL DEFAULT 0, next_attempt_at TEXT NOT NULL, sent_at TEXT, last_error TEXT)",
+        "DROP TABLE mail_queue",
+    ),
+    // Retention may blank the changes of entries about todos it anonymizes
+    // or purges; nothing else about an entry can change.
+    (
+        "DROP TRIGGER audit_log_no_update",
+        "CREATE TRIGGER audit_log_no_update BEFORE UPDATE ON audit_log BEGIN SELECT RAISE(ABORT, 'audit_log is append-only'); END",
+    ),
+    (
+        "CREATE TRIGGER audit_log_no_update BEFORE UPDATE ON audit_log WHEN NEW.id IS NOT OLD.id OR NEW.at IS NOT OLD.at OR NEW.actor IS NOT OLD.actor OR NEW.action IS NOT OLD.action OR NEW.todo_id IS NOT OLD.todo_id OR NEW.changes != '{}' BEGIN SELECT RAISE(ABORT, 'audit_log is append-only'); END",
+        "DROP TRIGGER audit_log_no_update",
+    ),
+];
+
+/// The schema version [`Database::migrate`] brings databases to.
+pub const SCHEMA_VERSION: usize = MIGRATIONS.len();
+
+/// Columns read by `todo_from_row`, in order.
+const TODO_COLUMNS: &str =
+    "id, title, created_at, completed_at, version, deadline, goal_id, location, points, status_id, start_date, habit, floating, someday_at IS NOT NULL";
+
+pub struct Health {
+    /// Findings of `PRAGMA integrity_check`; empty when the file is intact.
+    pub problems: Vec<String>,
+    pub schema_version: usize,
+    pub row_counts: Vec<(&'static str, i64)>,
+    pub file_size: u64,
+}
+
+/// Conditions for [`Database::find_todos`]; `None` matches everything.
+#[derive(Default)]
+pub struct TodoFilter {
+    pub completed: Option<bool>,
+    /// Deadline range, inclusive; todos without a deadline never match.
+    pub deadline_from: Option<NaiveDate>,
+    pub deadline_to: Option<NaiveDate>,
+    /// Part of the title, case-insensitive for ASCII letters.
+    pub text: Option<String>,
+    pub location: Option<String>,
+    pub goal_id: Option<i64>,
+    /// `Some(true)` for todos put off to someday only, `Some(false)` for
+    /// active ones only.
+    pub someday: Option<bool>,
+    /// Only todos older than this id, for paging.
+    pub after: Option<i64>,
+    pub limit: Option<u32>,
+}
+
+/// Conditions for [`Database::audit_log`]; `None` matches everything.
+#[derive(Default)]
+pub struct AuditFilter {
+    pub todo_id: Option<i64>,
+    pub action: Option<String>,
+    pub actor: Option<String>,
+    pub since: Option<DateTime<Utc>>,
+    pub limit: Option<u32>,
+}
+
+/// Why a change to a single todo was not made.
+#[derive(Debug)]
+pub enum TodoError {
+    NotFound(i64),
+    AlreadyCompleted(i64),
+    NotCompleted(i64),
+}
+
+impl fmt::Display for TodoError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            TodoError::NotFound(id) => write!(f, "todo {id} not found"),
+            TodoError::AlreadyCompleted(id) => write!(f, "todo {id} is already completed"),
+            TodoError::NotCompleted(id) => write!(f, "todo {id} is not completed"),
+        }
+    }
+}
+
+impl std::error::Error for TodoError {}
+
+/// Setting with the default reminder offsets, comma-separated minutes.
+const REMINDER_OFFSETS_SETTING: &str = "reminder_offsets";
+
+/// Setting with the last day whose leftover focus todos were carried over
+/// or dropped, so the question is asked once.
+const FOCUS_REVIEWED_SETTING: &str = "focus_reviewed";
+
+/// Setting that makes every overdue todo move to today, not only floating
+/// ones; `1` when on.
+const AUTO_POSTPONE_SETTING: &str = "auto_postpone";
+
+/// Setting with the days deleted todos, notes and goals stay in the trash.
+const TRASH_RETENTION_SETTING: &str = "trash_retention";
+const DEFAULT_TRASH_RETENTION_DAYS: u32 = 30;
+
+/// Setting with the [`RetentionPolicy`], as JSON.
+const RETENTION_SETTING: &str = "retention";
+
+/// Setting with the retention job's last cutoff for the audit log; the log
+/// refuses to delete newer entries. Named in the `audit_log_no_delete`
+/// trigger as well.
+const AUDIT_PURGED_BEFORE_SETTING: &str = "audit_purged_before";
+
+/// Setting with the days after which open todos count as aging and as
+/// stale, comma-separated.
+const AGE_THRESHOLDS_SETTING: &str = "age_thresholds";
+const WEEK_START_SETTING: &str = "week_start";
+const DATE_FORMAT_SETTING: &str = "date_format";
+
+/// Title that anonymized todos are left with.
+const ANONYMIZED_TITLE: &str = "Erledigtes Todo";
+
+/// How long a statement waits for another writer before giving up.
+const BUSY_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// Tables created by [`Database::migrate`], as reported by [`Database::health`].
+const TABLES: &[&str] = &[
+    "todos",
+    "todo_commits",
+    "sync_links",
+    "oauth_tokens",
+    "feature_flags",
+    "goals",
+    "notes",
+    "todo_relations",
+    "reminders",
+    "notification_jobs",
+    "settings",
+    "push_subscriptions",
+    "statuses",
+    "focus",
+    "pomodoros",
+    "habit_days",
+    "share_links",
+    "audit_log",
+    "tombstones",
+    "postponements",
+    "mail_queue",
+];
+
+/// Rows deleted along with a todo: table and the column pointing to it.
+const TODO_DEPENDENTS: &[(&str, &str)] = &[
+    ("todo_commits", "todo_id"),
+    ("todo_relations", "todo_id"),
+    ("todo_relations", "related_id"),
+    ("reminders", "todo_id"),
+    ("focus", "todo_id"),
+    ("pomodoros", "todo_id"),
+    ("habit_days", "todo_id"),
+    ("share_links", "todo_id"),
+    ("postponements", "todo_id"),
+];
+
+/// Rows that lose their link to a deleted todo: table, the column set to
+/// `NULL` and the columns that identify a row.
+const TODO_LINKS: &[(&str, &str, &[&str])] = &[
+    ("notes", "todo_id", &["id"]),
+    (
+        "sync_links",
+        "todo_id",
+        &["provider", "remote_list", "remote_id"],
+    ),
+];
+
+/// What a tombstone puts back: whole rows, inserted in order, and links
+/// that were cleared by `ON DELETE SET NULL`.
+#[derive(Serialize, Deserialize, Default)]
+struct Snapshot {
+    rows: Vec<SnapshotRow>,
+    links: Vec<SnapshotLink>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SnapshotRow {
+    table: String,
+    row: Map<String, serde_json::Value>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct SnapshotLink {
+    table: String,
+    column: String,
+    value: i64,
+    key: Map<String, serde_json::Value>,
+}
 
 pub struct Database {
     conn: Connection,
+    generation: Arc<AtomicU64>,
+}
+
+/// One long-lived connection shared by the server's handlers and background
+/// jobs, so statements stay in the connection's prepared statement cache.
+#[derive(Clone)]
+pub struct SharedDatabase {
+    db: Arc<Mutex<Database>>,
+    generation: Arc<AtomicU64>,
+    read_only: Arc<AtomicBool>,
+}
+
+impl SharedDatabase {
+    pub fn new(db: Database) -> Self {
+        Self {
+            generation: db.generation.clone(),
+            db: Arc::new(Mutex::new(db)),
+            read_only: Arc::new(AtomicBool::new(false)),
+        }
+    }
+
+    pub fn lock(&self) -> MutexGuard<'_, Database> {
+        // A panicking handler leaves the connection itself intact.
+        self.db.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Counts rows written through this connection; readable without the lock.
+    pub fn generation(&self) -> u64 {
+        self.generation.load(Ordering::Acquire)
+    }
+
+    /// Makes SQLite refuse every write on this connection (`PRAGMA
+    /// query_only`), including background sync and maintenance.
+    pub fn set_read_only(&self, read_only: bool) -> anyhow::Result<()> {
+        let db = self.lock();
+        db.conn.pragma_update(None, "query_only", read_only)?;
+        self.read_only.store(read_only, Ordering::Release);
+        Ok(())
+    }
+
+    pub fn is_read_only(&self) -> bool {
+        self.read_only.load(Ordering::Acquire)
+    }
+}
+
+/// Writes kept or undone together, from [`Database::savepoint`]. Dropping
+/// it without [`Savepoint::release`] rolls them back.
+pub struct Savepoint<'a> {
+    conn: &'a Connection,
+    /// Inside a transaction, which goes on after a rollback.
+    nested: bool,
+    released: bool,
+}
+
+impl Savepoint<'_> {
+    pub fn release(mut self) -> anyhow::Result<()> {
+        self.conn.execute_batch(if self.nested {
+            "RELEASE nested"
+        } else {
+            "COMMIT"
+        })?;
+        self.released = true;
+        Ok(())
+    }
+}
+
+impl Drop for Savepoint<'_> {
+    fn drop(&mut self) {
+        if !self.released {
+            let _ = self.conn.execute_batch(if self.nested {
+                "ROLLBACK TO nested; RELEASE nested"
+            } else {
+                "ROLLBACK"
+            });
+        }
+    }
 }
 
 impl Database {
-    pub fn connect<P: AsRef<Path>>(path: P) -> anyhow::Result<Self> {
-        let conn = Connection::open(path.as_ref())
-            .with_context(|| format!("opening database at {}", path.as_ref().display()))?;
-        let db = Self { conn };
+    /// Opens (and migrates) the database. `key` unlocks a SQLCipher-encrypted
+    /// file and requires the `sqlcipher` build feature.
+    pub fn connect<P: AsRef<Path>>(path: P, key: Option<&str>) -> anyhow::Result<Self> {
+        let db = Self::open(path, key)?;
         db.migrate()?;
         Ok(db)
     }
 
-    fn migrate(&self) -> anyhow::Result<()> {
+    /// Opens the database as it is, for a look at
+    /// [`Database::pending_migrations`] before [`Database::migrate`].
+    pub fn open<P: AsRef<Path>>(path: P, key: Option<&str>) -> anyhow::Result<Self> {
+        let conn = Connection::open(path.as_ref())
+            .with_context(|| format!("opening database at {}", path.as_ref().display()))?;
+        if let Some(key) = key {
+            if !cfg!(feature = "sqlcipher") {
+                // Plain SQLite silently ignores PRAGMA key.
+                anyhow::bail!("a database key was given, but simpletodo was built without the sqlcipher feature");
+            }
+            conn.pragma_update(None, "key", key)?;
+            conn.query_row("SELECT COUNT(*) FROM sqlite_master", [], |row| {
+                row.get::<_, i64>(0)
+            })
+            .context("unlocking the database failed, is the key correct?")?;
+        }
+        conn.set_prepared_statement_cache_capacity(64);
+        // SQLite retries with backoff while another process holds the lock.
+        conn.busy_timeout(BUSY_TIMEOUT)?;
+        // Off by default in SQLite; without it the cascades below never run.
+        conn.pragma_update(None, "foreign_keys", true)?;
+        let generation = Arc::new(AtomicU64::new(0));
+        let counter = generation.clone();
+        conn.update_hook(Some(move |_: Action, _: &str, _: &str, _: i64| {
+            counter.fetch_add(1, Ordering::Release);
+        }));
+        Ok(Self { conn, generation })
+    }
+
+    /// True for a database without any tables yet, which has nothing to
+    /// migrate or lose.
+    pub fn is_new(&self) -> anyhow::Result<bool> {
+        let tables: i64 = self
+            .conn
+            .query_row("SELECT COUNT(*) FROM sqlite_master", [], |row| row.get(0))?;
+        Ok(tables == 0)
+    }
+
+    /// The schema version the database is at, and the migrations that
+    /// [`Database::migrate`] would apply, with the version each leads to.
+    pub fn pending_migrations(&self) -> anyhow::Result<(usize, Vec<(usize, &'static str)>)> {
+        let applied: usize = self
+            .conn
+            .query_row("PRAGMA user_version", [], |row| row.get(0))?;
+        let pending = (1..)
+            .zip(MIGRATIONS.iter().map(|(up, _)| *up))
+            .skip(applied)
+            .collect();
+        Ok((applied, pending))
+    }
+
+    /// Creates missing tables and applies the pending migrations. A schema
+    /// newer than this build knows is left as it is.
+    pub fn migrate(&self) -> anyhow::Result<()> {
+        let applied: usize = self
+            .conn
+            .query_row("PRAGMA user_version", [], |row| row.get(0))?;
+        self.migrate_to(SCHEMA_VERSION.max(applied))
+    }
+
+    /// Brings the schema to `version`, applying migrations or undoing them.
+    /// Undoing drops the tables and columns they added, with their data.
+    pub fn migrate_to(&self, version: usize) -> anyhow::Result<()> {
         self.conn.execute_batch(
             r#"
             CREATE TABLE IF NOT EXISTS todos (
@@ -28,47 +528,148 @@ impl Database {
                 created_at TEXT NOT NULL,
                 completed_at TEXT
             );
+            CREATE TABLE IF NOT EXISTS todo_commits (
+                todo_id INTEGER NOT NULL REFERENCES todos(id) ON DELETE CASCADE,
+                repository TEXT NOT NULL,
+                commit_id TEXT NOT NULL,
+                url TEXT,
+                created_at TEXT NOT NULL
+            );
+            CREATE TABLE IF NOT EXISTS sync_links (
+                provider TEXT NOT NULL,
+                remote_list TEXT NOT NULL,
+                remote_id TEXT NOT NULL,
+                todo_id INTEGER REFERENCES todos(id) ON DELETE SET NULL,
+                PRIMARY KEY (provider, remote_list, remote_id)
+            );
+            CREATE TABLE IF NOT EXISTS oauth_tokens (
+                provider TEXT PRIMARY KEY,
+                access_token TEXT NOT NULL,
+                refresh_token TEXT,
+                expires_at TEXT NOT NULL
+            );
+            "#,
+        )?;
+
+        let applied: usize = self
+            .conn
+            .query_row("PRAGMA user_version", [], |row| row.get(0))?;
+        if version < applied && applied > SCHEMA_VERSION {
+            anyhow::bail!(
+                "the database has schema version {applied}, newer than this build's {SCHEMA_VERSION}; undo the migrations with the build that applied them"
+            );
+        }
+        for (index, (up, _)) in MIGRATIONS.iter().enumerate().take(version).skip(applied) {
+            self.conn.execute_batch(&format!(
+                "BEGIN; {up}; PRAGMA user_version = {}; COMMIT;",
+                index + 1
+            ))?;
+        }
+        for (index, (_, down)) in MIGRATIONS
+            .iter()
+            .enumerate()
+            .take(applied)
+            .skip(version)
+            .rev()
+        {
+            self.conn.execute_batch(&format!(
+                "BEGIN; {down}; PRAGMA user_version = {index}; COMMIT;"
+            ))?;
+        }
+
+        // Rows left behind by deletes from before foreign keys were enforced.
+        self.conn.execute_batch(
+            r#"
+            DELETE FROM todo_commits WHERE todo_id NOT IN (SELECT id FROM todos);
+            UPDATE sync_links SET todo_id = NULL
+                WHERE todo_id IS NOT NULL AND todo_id NOT IN (SELECT id FROM todos);
             "#,
         )?;
         Ok(())
     }
 
-    pub fn add_todo(&self, title: &str) -> anyhow::Result<Todo> {
+    /// Groups the following calls into one transaction, committed explicitly.
+    ///
+    /// The write lock is taken up front: a deferred transaction that later
+    /// upgrades to writing fails with `SQLITE_BUSY` right away instead of
+    /// waiting for the busy timeout.
+    pub fn transaction(&self) -> anyhow::Result<Transaction<'_>> {
+        Ok(Transaction::new_unchecked(
+            &self.conn,
+            TransactionBehavior::Immediate,
+        )?)
+    }
+
+    /// Like [`Self::transaction`], but also usable inside one, e.g. a
+    /// batch's: there it only sets a savepoint, so a failure undoes the
+    /// writes since then and the transaction goes on.
+    pub fn savepoint(&self) -> anyhow::Result<Savepoint<'_>> {
+        let nested = !self.conn.is_autocommit();
+        self.conn.execute_batch(if nested {
+            "SAVEPOINT nested"
+        } else {
+            "BEGIN IMMEDIATE"
+        })?;
+        Ok(Savepoint {
+            conn: &self.conn,
+            nested,
+            released: false,
+        })
+    }
+
+    pub fn add_todo(
+        &self,
+        title: &str,
+        deadline: Option<NaiveDate>,
+        location: Option<&str>,
+    ) -> anyhow::Result<Todo> {
         let now = Utc::now();
-        self.conn.execute(
-            "INSERT INTO todos (title, created_at) VALUES (?1, ?2)",
-            params![title, now.to_rfc3339()],
-        )?;
+        let status_id = self.first_status(false)?;
+        let savepoint = self.savepoint()?;
+        self.conn
+            .prepare_cached(
+                "INSERT INTO todos (title, created_at, deadline, location, status_id) VALUES (?1, ?2, ?3, ?4, ?5)",
+            )?
+            .execute(params![title, now.to_rfc3339(), deadline, location, status_id])?;
         let id = self.conn.last_insert_rowid();
+        self.audit(
+            "create",
+            Some(id),
+            json!({ "title": title, "deadline": deadline, "location": location }),
+        )?;
+        self.reschedule_reminders(id, None, deadline)?;
+        savepoint.release()?;
         Ok(Todo {
             id,
             title: title.to_string(),
             created_at: now,
             completed_at: None,
+            deadline,
+            version: 1,
+            goal_id: None,
+            location: location.map(str::to_string),
+            points: None,
+            status_id,
+            start_date: None,
+            habit: false,
+            floating: false,
+            someday: false,
         })
     }
 
+    /// Todos that are neither archived nor put off to someday, newest first.
     pub fn list_todos(&self, include_completed: bool) -> anyhow::Result<Vec<Todo>> {
         let mut stmt = if include_completed {
-            self.conn.prepare(
-                "SELECT id, title, created_at, completed_at FROM todos ORDER BY id DESC",
-            )?
+            self.conn.prepare_cached(&format!(
+                "SELECT {TODO_COLUMNS} FROM todos WHERE archived_at IS NULL AND someday_at IS NULL ORDER BY id DESC"
+            ))?
         } else {
-            self.conn.prepare(
-                "SELECT id, title, created_at, completed_at FROM todos WHERE completed_at IS NULL ORDER BY id DESC",
-            )?
+            self.conn.prepare_cached(&format!(
+                "SELECT {TODO_COLUMNS} FROM todos WHERE completed_at IS NULL AND archived_at IS NULL AND someday_at IS NULL ORDER BY id DESC"
+            ))?
         };
 
-        let rows = stmt.query_map([], |row| {
-            let created_at: String = row.get(2)?;
-            let completed_at: Option<String> = row.get(3)?;
-            Ok(Todo {
-                id: row.get(0)?,
-                title: row.get(1)?,
-                created_at: parse_datetime(&created_at),
-                completed_at: completed_at.map(|value| parse_datetime(&value)),
-            })
-        })?;
+        let rows = stmt.query_map([], todo_from_row)?;
 
         let mut todos = Vec::new();
         for todo in rows {
@@ -77,31 +678,2264 @@ impl Database {
         Ok(todos)
     }
 
+    /// Todos that aren't archived and match `filter`, newest first.
+    pub fn find_todos(&self, filter: &TodoFilter) -> anyhow::Result<Vec<Todo>> {
+        // Only fixed SQL goes into the statement; every value is bound.
+        let mut conditions = vec!["archived_at IS NULL"];
+        let mut values: Vec<Value> = Vec::new();
+        match filter.completed {
+            Some(true) => conditions.push("completed_at IS NOT NULL"),
+            Some(false) => conditions.push("completed_at IS NULL"),
+            None => {}
+        }
+        if let Some(from) = filter.deadline_from {
+            conditions.push("deadline >= ?");
+            values.push(from.to_string().into());
+        }
+        if let Some(to) = filter.deadline_to {
+            conditions.push("deadline <= ?");
+            values.push(to.to_string().into());
+        }
+        if let Some(text) = &filter.text {
+            conditions.push("title LIKE ? ESCAPE '\\'");
+            let escaped = text
+                .replace('\\', "\\\\")
+                .replace('%', "\\%")
+                .replace('_', "\\_");
+            values.push(format!("%{escaped}%").into());
+        }
+        if let Some(location) = &filter.location {
+            conditions.push("location = ?");
+            values.push(location.clone().into());
+        }
+        if let Some(goal_id) = filter.goal_id {
+            conditions.push("goal_id = ?");
+            values.push(goal_id.into());
+        }
+        match filter.someday {
+            Some(true) => conditions.push("someday_at IS NOT NULL"),
+            Some(false) => conditions.push("someday_at IS NULL"),
+            None => {}
+        }
+        if let Some(after) = filter.after {
+            conditions.push("id < ?");
+            values.push(after.into());
+        }
+
+        let mut sql = format!(
+            "SELECT {TODO_COLUMNS} FROM todos WHERE {} ORDER BY id DESC",
+            conditions.join(" AND ")
+        );
+        if let Some(limit) = filter.limit {
+            sql.push_str(" LIMIT ?");
+            values.push(limit.into());
+        }
+        let mut stmt = self.conn.prepare_cached(&sql)?;
+        let todos = stmt
+            .query_map(params_from_iter(values), todo_from_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(todos)
+    }
+
+    /// Archives todos completed before `before`; returns how many.
+    pub fn archive_completed(&self, before: DateTime<Utc>) -> anyhow::Result<usize> {
+        let archived = self
+            .conn
+            .prepare_cached(
+                "UPDATE todos SET archived_at = ?1 WHERE archived_at IS NULL AND habit = 0 AND completed_at < ?2 RETURNING id",
+            )?
+            .query_map(params![Utc::now().to_rfc3339(), before.to_rfc3339()], |row| row.get(0))?
+            .collect::<rusqlite::Result<Vec<i64>>>()?;
+        for id in &archived {
+            self.audit("archive", Some(*id), json!({}))?;
+        }
+        Ok(archived.len())
+    }
+
+    /// Open todos put off to someday, the most recently put off first.
+    pub fn someday_todos(&self) -> anyhow::Result<Vec<Todo>> {
+        let mut stmt = self.conn.prepare_cached(&format!(
+            "SELECT {TODO_COLUMNS} FROM todos WHERE someday_at IS NOT NULL AND completed_at IS NULL AND archived_at IS NULL ORDER BY someday_at DESC, id DESC"
+        ))?;
+        let todos = stmt
+            .query_map([], todo_from_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(todos)
+    }
+
+    /// Puts a todo off to someday, out of the list and every deadline view,
+    /// or makes it active again.
+    pub fn set_someday(&self, id: i64, someday: bool) -> anyhow::Result<()> {
+        let previous = self.get_todo(id)?.ok_or(TodoError::NotFound(id))?;
+        if previous.someday == someday {
+            return Ok(());
+        }
+        let someday_at = someday.then(|| Utc::now().to_rfc3339());
+        self.conn
+            .prepare_cached(
+                "UPDATE todos SET someday_at = ?1, version = version + 1 WHERE id = ?2",
+            )?
+            .execute(params![someday_at, id])?;
+        self.audit(
+            "update",
+            Some(id),
+            json!({ "someday": [previous.someday, someday] }),
+        )
+    }
+
+    /// Archived todos, most recently completed first.
+    pub fn archived_todos(&self) -> anyhow::Result<Vec<Todo>> {
+        let mut stmt = self.conn.prepare_cached(&format!(
+            "SELECT {TODO_COLUMNS} FROM todos WHERE archived_at IS NOT NULL ORDER BY completed_at DESC"
+        ))?;
+        let todos = stmt
+            .query_map([], todo_from_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(todos)
+    }
+
+    /// Completion time and points of every todo completed since `since`.
+    pub fn completed_since(
+        &self,
+        since: DateTime<Utc>,
+    ) -> anyhow::Result<Vec<(DateTime<Utc>, Option<u32>)>> {
+        let mut stmt = self.conn.prepare_cached(
+            "SELECT completed_at, points FROM todos WHERE completed_at >= ?1 ORDER BY completed_at",
+        )?;
+        let completed = stmt
+            .query_map(params![since.to_rfc3339()], |row| {
+                let completed_at: String = row.get(0)?;
+                Ok((parse_datetime(&completed_at), row.get(1)?))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(completed)
+    }
+
+    /// Every location in use, for suggestions and the filter.
+    pub fn locations(&self) -> anyhow::Result<Vec<String>> {
+        let mut stmt = self.conn.prepare_cached(
+            "SELECT DISTINCT location FROM todos WHERE location IS NOT NULL ORDER BY location",
+        )?;
+        let locations = stmt
+            .query_map([], |row| row.get(0))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(locations)
+    }
+
+    pub fn get_todo(&self, id: i64) -> anyhow::Result<Option<Todo>> {
+        let todo = self
+            .conn
+            .prepare_cached(&format!("SELECT {TODO_COLUMNS} FROM todos WHERE id = ?1"))?
+            .query_row(params![id], todo_from_row)
+            .optional()?;
+        Ok(todo)
+    }
+
+    /// Returns `false` if the todo is gone or was edited since `version` was read.
+    /// `someday` also puts it off or makes it active again, as part of the
+    /// same edit; `None` leaves that as it is.
+    #[allow(clippy::too_many_arguments)]
+    pub fn update_todo(
+        &self,
+        id: i64,
+        version: i64,
+        input: &TodoInput,
+        goal_id: Option<i64>,
+        habit: bool,
+        floating: bool,
+        someday: Option<bool>,
+    ) -> anyhow::Result<bool> {
+        let Some(previous) = self.get_todo(id)? else {
+            return Ok(false);
+        };
+        let savepoint = self.savepoint()?;
+        let updated = self
+            .conn
+            .prepare_cached(
+                "UPDATE todos SET title = ?1, deadline = ?2, location = ?3, points = ?4, goal_id = ?5, start_date = ?6, habit = ?7, floating = ?8, \
+                 someday_at = CASE ?11 WHEN 0 THEN NULL WHEN 1 THEN COALESCE(someday_at, ?12) ELSE someday_at END, \
+                 version = version + 1 WHERE id = ?9 AND version = ?10",
+            )?
+            .execute(params![
+                input.title,
+                input.deadline,
+                input.location,
+                input.points,
+                goal_id,
+                input.start,
+                habit,
+                floating,
+                id,
+                version,
+                someday,
+                Utc::now().to_rfc3339()
+            ])?;
+        if updated > 0 {
+            let mut changes = Map::new();
+            changed(&mut changes, "title", &previous.title, &input.title);
+            changed(&mut changes, "deadline", previous.deadline, input.deadline);
+            changed(
+                &mut changes,
+                "location",
+                &previous.location,
+                &input.location,
+            );
+            changed(&mut changes, "points", previous.points, input.points);
+            changed(&mut changes, "goal_id", previous.goal_id, goal_id);
+            changed(&mut changes, "start_date", previous.start_date, input.start);
+            changed(&mut changes, "habit", previous.habit, habit);
+            changed(&mut changes, "floating", previous.floating, floating);
+            if let Some(someday) = someday {
+                changed(&mut changes, "someday", previous.someday, someday);
+            }
+            self.audit("update", Some(id), changes.into())?;
+            self.reschedule_reminders(id, previous.deadline, input.deadline)?;
+        }
+        savepoint.release()?;
+        Ok(updated > 0)
+    }
+
+    /// Links a todo to `goal_id` without counting as an edit.
+    pub fn set_goal(&self, id: i64, goal_id: i64) -> anyhow::Result<()> {
+        let previous = self.get_todo(id)?.ok_or(TodoError::NotFound(id))?;
+        self.conn
+            .prepare_cached("UPDATE todos SET goal_id = ?1 WHERE id = ?2")?
+            .execute(params![goal_id, id])?;
+        let mut changes = Map::new();
+        changed(&mut changes, "goal_id", previous.goal_id, Some(goal_id));
+        self.audit("update", Some(id), changes.into())
+    }
+
     pub fn complete_todo(&self, id: i64) -> anyhow::Result<()> {
         let now = Utc::now().to_rfc3339();
-        let updated = self.conn.execute(
-            "UPDATE todos SET completed_at = ?1 WHERE id = ?2 AND completed_at IS NULL",
-            params![now, id],
-        )?;
+        let savepoint = self.savepoint()?;
+        let updated = self
+            .conn
+            .prepare_cached(
+                "UPDATE todos SET completed_at = ?1, status_id = (SELECT id FROM statuses WHERE done = 1 ORDER BY position LIMIT 1) WHERE id = ?2 AND completed_at IS NULL",
+            )?
+            .execute(params![now, id])?;
+        if updated == 0 {
+            return Err(match self.get_todo(id)? {
+                Some(_) => TodoError::AlreadyCompleted(id),
+                None => TodoError::NotFound(id),
+            }
+            .into());
+        }
+        self.audit("complete", Some(id), json!({}))?;
+        self.record_habit_day(id, true)?;
+        savepoint.release()
+    }
+
+    pub fn reopen_todo(&self, id: i64) -> anyhow::Result<()> {
+        let savepoint = self.savepoint()?;
+        let updated = self
+            .conn
+            .prepare_cached(
+                "UPDATE todos SET completed_at = NULL, archived_at = NULL, status_id = (SELECT id FROM statuses WHERE done = 0 ORDER BY position LIMIT 1) WHERE id = ?1 AND completed_at IS NOT NULL",
+            )?
+            .execute(params![id])?;
+        if updated == 0 {
+            return Err(match self.get_todo(id)? {
+                Some(_) => TodoError::NotCompleted(id),
+                None => TodoError::NotFound(id),
+            }
+            .into());
+        }
+        self.audit("reopen", Some(id), json!({}))?;
+        self.record_habit_day(id, false)?;
+        savepoint.release()
+    }
+
+    /// Moves a todo to `status_id`, completing it if that status means done
+    /// and reopening it otherwise.
+    pub fn set_status(&self, id: i64, status_id: i64) -> anyhow::Result<()> {
+        let done: bool = self
+            .conn
+            .prepare_cached("SELECT done FROM statuses WHERE id = ?1")?
+            .query_row(params![status_id], |row| row.get(0))
+            .optional()?
+            .context("no such status")?;
+        let previous = self.get_todo(id)?.ok_or(TodoError::NotFound(id))?;
+        let updated = if done {
+            self.conn
+                .prepare_cached(
+                    "UPDATE todos SET status_id = ?1, completed_at = COALESCE(completed_at, ?2) WHERE id = ?3",
+                )?
+                .execute(params![status_id, Utc::now().to_rfc3339(), id])?
+        } else {
+            self.conn
+                .prepare_cached(
+                    "UPDATE todos SET status_id = ?1, completed_at = NULL, archived_at = NULL WHERE id = ?2",
+                )?
+                .execute(params![status_id, id])?
+        };
         if updated == 0 {
-            anyhow::bail!("todo {id} not found or already completed");
+            return Err(TodoError::NotFound(id).into());
+        }
+        let mut changes = Map::new();
+        changed(
+            &mut changes,
+            "status_id",
+            previous.status_id,
+            Some(status_id),
+        );
+        self.audit("status", Some(id), changes.into())?;
+        self.record_habit_day(id, done)
+    }
+
+    /// Checks a habit off for today, or takes that back when it is reopened;
+    /// other todos are left alone.
+    fn record_habit_day(&self, id: i64, done: bool) -> anyhow::Result<()> {
+        let today = Local::now().date_naive();
+        if done {
+            self.conn
+                .prepare_cached(
+                    "INSERT OR IGNORE INTO habit_days (todo_id, day) SELECT id, ?2 FROM todos WHERE id = ?1 AND habit = 1",
+                )?
+                .execute(params![id, today])?;
+        } else {
+            self.conn
+                .prepare_cached("DELETE FROM habit_days WHERE todo_id = ?1 AND day = ?2")?
+                .execute(params![id, today])?;
         }
         Ok(())
     }
 
-    pub fn delete_todo(&self, id: i64) -> anyhow::Result<()> {
+    /// Days a habit was checked off, oldest first.
+    pub fn habit_days(&self, todo_id: i64) -> anyhow::Result<Vec<NaiveDate>> {
+        let days = self
+            .conn
+            .prepare_cached("SELECT day FROM habit_days WHERE todo_id = ?1 ORDER BY day")?
+            .query_map(params![todo_id], |row| row.get(0))?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(days)
+    }
+
+    /// Reopens habits checked off before `since`, the start of the day, so
+    /// each one is open again every morning.
+    pub fn reopen_habits(&self, since: DateTime<Utc>) -> anyhow::Result<usize> {
+        let reopened = self
+            .conn
+            .prepare_cached(
+                "UPDATE todos SET completed_at = NULL, archived_at = NULL, status_id = (SELECT id FROM statuses WHERE done = 0 ORDER BY position LIMIT 1) \
+                 WHERE habit = 1 AND completed_at < ?1 RETURNING id",
+            )?
+            .query_map(params![since.to_rfc3339()], |row| row.get(0))?
+            .collect::<rusqlite::Result<Vec<i64>>>()?;
+        for id in &reopened {
+            self.audit("reopen", Some(*id), json!({}))?;
+        }
+        Ok(reopened.len())
+    }
+
+    /// Moves the deadline of overdue open todos to `today`, each counting as
+    /// an edit and recorded as a postponement; only floating todos unless
+    /// `all`. Returns how many were moved.
+    pub fn postpone_overdue(&self, today: NaiveDate, all: bool) -> anyhow::Result<usize> {
+        let tx = self.transaction()?;
+        let overdue = self
+            .conn
+            .prepare_cached(
+                "SELECT id, deadline FROM todos \
+                 WHERE deadline < ?1 AND completed_at IS NULL AND archived_at IS NULL AND someday_at IS NULL AND (floating = 1 OR ?2)",
+            )?
+            .query_map(params![today, all], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<rusqlite::Result<Vec<(i64, NaiveDate)>>>()?;
+        for (id, previous) in &overdue {
+            self.conn
+                .prepare_cached(
+                    "UPDATE todos SET deadline = ?1, version = version + 1 WHERE id = ?2",
+                )?
+                .execute(params![today, id])?;
+            self.conn
+                .prepare_cached(
+                    "INSERT OR IGNORE INTO postponements (todo_id, day, deadline) VALUES (?1, ?2, ?3)",
+                )?
+                .execute(params![id, today, previous])?;
+            self.audit(
+                "postpone",
+                Some(*id),
+                json!({ "deadline": [previous, today] }),
+            )?;
+            self.reschedule_reminders(*id, Some(*previous), Some(today))?;
+        }
+        tx.commit()?;
+        Ok(overdue.len())
+    }
+
+    /// How often a todo's deadline was moved to the next day.
+    pub fn postponements(&self, todo_id: i64) -> anyhow::Result<usize> {
+        let count = self
+            .conn
+            .prepare_cached("SELECT COUNT(*) FROM postponements WHERE todo_id = ?1")?
+            .query_row(params![todo_id], |row| row.get(0))?;
+        Ok(count)
+    }
+
+    /// Whether every overdue todo moves to today, not only floating ones.
+    pub fn auto_postpone(&self) -> anyhow::Result<bool> {
+        Ok(self.setting(AUTO_POSTPONE_SETTING)?.as_deref() == Some("1"))
+    }
+
+    pub fn set_auto_postpone(&self, enabled: bool) -> anyhow::Result<()> {
+        self.set_setting(AUTO_POSTPONE_SETTING, if enabled { "1" } else { "0" })
+    }
+
+    /// When open todos start to look aging and stale; the defaults until
+    /// set.
+    pub fn age_thresholds(&self) -> anyhow::Result<AgeThresholds> {
+        let Some(value) = self.setting(AGE_THRESHOLDS_SETTING)? else {
+            return Ok(AgeThresholds::default());
+        };
+        let thresholds = value
+            .split_once(',')
+            .and_then(|(aging, stale)| {
+                Some(AgeThresholds {
+                    aging: aging.parse().ok()?,
+                    stale: stale.parse().ok()?,
+                })
+            })
+            .unwrap_or_default();
+        Ok(thresholds)
+    }
+
+    pub fn set_age_thresholds(&self, thresholds: AgeThresholds) -> anyhow::Result<()> {
+        self.set_setting(
+            AGE_THRESHOLDS_SETTING,
+            &format!("{},{}", thresholds.aging, thresholds.stale),
+        )
+    }
+
+    pub fn preferences(&self) -> anyhow::Result<Preferences> {
+        let mut preferences = Preferences::default();
+        if let Some(week_start) = self.setting(WEEK_START_SETTING)? {
+            preferences.week_start = week_start.parse().unwrap_or(preferences.week_start);
+        }
+        if let Some(date_format) = self.setting(DATE_FORMAT_SETTING)? {
+            preferences.date_format =
+                DateFormat::from_name(&date_format).unwrap_or(preferences.date_format);
+        }
+        Ok(preferences)
+    }
+
+    pub fn set_preferences(&self, preferences: Preferences) -> anyhow::Result<()> {
+        self.set_setting(WEEK_START_SETTING, &preferences.week_start.to_string())?;
+        self.set_setting(DATE_FORMAT_SETTING, preferences.date_format.name())
+    }
+
+    /// Workflow statuses in board order.
+    pub fn statuses(&self) -> anyhow::Result<Vec<Status>> {
+        let mut stmt = self
+            .conn
+            .prepare_cached("SELECT id, name, done FROM statuses ORDER BY position, id")?;
+        let statuses = stmt
+            .query_map([], |row| {
+                Ok(Status {
+                    id: row.get(0)?,
+                    name: row.get(1)?,
+                    done: row.get(2)?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(statuses)
+    }
+
+    /// Adds a status after the existing ones.
+    pub fn add_status(&self, name: &str, done: bool) -> anyhow::Result<i64> {
+        self.conn
+            .prepare_cached(
+                "INSERT INTO statuses (name, position, done) VALUES (?1, (SELECT COALESCE(MAX(position), 0) + 1 FROM statuses), ?2)",
+            )?
+            .execute(params![name, done])?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    /// Deletes a status; its todos move to the first remaining status that
+    /// is done, or open, alike.
+    pub fn delete_status(&self, id: i64) -> anyhow::Result<()> {
+        let tx = self.transaction()?;
+        let moved = self
+            .conn
+            .prepare_cached(
+                "UPDATE todos SET status_id = (SELECT other.id FROM statuses other, statuses deleted \
+                 WHERE deleted.id = ?1 AND other.id != ?1 AND other.done = deleted.done ORDER BY other.position LIMIT 1) \
+                 WHERE status_id = ?1 RETURNING id, status_id",
+            )?
+            .query_map(params![id], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<rusqlite::Result<Vec<(i64, Option<i64>)>>>()?;
+        for (todo_id, status_id) in moved {
+            self.audit(
+                "status",
+                Some(todo_id),
+                json!({ "status_id": [id, status_id] }),
+            )?;
+        }
         let deleted = self
             .conn
-            .execute("DELETE FROM todos WHERE id = ?1", params![id])?;
+            .prepare_cached("DELETE FROM statuses WHERE id = ?1")?
+            .execute(params![id])?;
         if deleted == 0 {
-            anyhow::bail!("todo {id} not found");
+            return Err(rusqlite::Error::QueryReturnedNoRows.into());
         }
+        tx.commit()?;
         Ok(())
     }
-}
 
-fn parse_datetime(value: &str) -> DateTime<Utc> {
-    DateTime::parse_from_rfc3339(value)
-        .map(|dt| dt.with_timezone(&Utc))
-        .unwrap_or_else(|_| Utc::now())
+    /// The status new (`done` false) or completed todos get.
+    fn first_status(&self, done: bool) -> anyhow::Result<Option<i64>> {
+        Ok(self
+            .conn
+            .prepare_cached("SELECT id FROM statuses WHERE done = ?1 ORDER BY position LIMIT 1")?
+            .query_row(params![done], |row| row.get(0))
+            .optional()?)
+    }
+
+    pub fn record_commit(
+        &self,
+        todo_id: i64,
+        repository: &str,
+        commit_id: &str,
+        url: Option<&str>,
+    ) -> anyhow::Result<()> {
+        self.conn
+            .prepare_cached(
+                "INSERT INTO todo_commits (todo_id, repository, commit_id, url, created_at) VALUES (?1, ?2, ?3, ?4, ?5)",
+            )?
+            .execute(params![todo_id, repository, commit_id, url, Utc::now().to_rfc3339()])?;
+        Ok(())
+    }
+
+    /// Completes a todo and records the commit that closed it, atomically.
+    pub fn complete_by_commit(
+        &self,
+        todo_id: i64,
+        repository: &str,
+        commit_id: &str,
+        url: Option<&str>,
+    ) -> anyhow::Result<()> {
+        let tx = self.transaction()?;
+        self.complete_todo(todo_id)?;
+        self.record_commit(todo_id, repository, commit_id, url)?;
+        tx.commit()?;
+        Ok(())
+    }
+
+    /// Maps remote ids of `provider`/`remote_list` to their todo, `None` once the todo is gone.
+    pub fn sync_links(
+        &self,
+        provider: &str,
+        remote_list: &str,
+    ) -> anyhow::Result<HashMap<String, Option<i64>>> {
+        let mut stmt = self.conn.prepare_cached(
+            "SELECT l.remote_id, t.id FROM sync_links l LEFT JOIN todos t ON t.id = l.todo_id WHERE l.provider = ?1 AND l.remote_list = ?2",
+        )?;
+        let rows = stmt.query_map(params![provider, remote_list], |row| {
+            Ok((row.get(0)?, row.get(1)?))
+        })?;
+
+        let mut links = HashMap::new();
+        for link in rows {
+            let (remote_id, todo_id) = link?;
+            links.insert(remote_id, todo_id);
+        }
+        Ok(links)
+    }
+
+    pub fn link_remote(
+        &self,
+        provider: &str,
+        remote_list: &str,
+        remote_id: &str,
+        todo_id: i64,
+    ) -> anyhow::Result<()> {
+        self.conn
+            .prepare_cached(
+                "INSERT OR REPLACE INTO sync_links (provider, remote_list, remote_id, todo_id) VALUES (?1, ?2, ?3, ?4)",
+            )?
+            .execute(params![provider, remote_list, remote_id, todo_id])?;
+        Ok(())
+    }
+
+    /// Creates a todo for a remote item and links the two in one transaction,
+    /// so a failed link can't cause the item to be imported again.
+    pub fn import_remote(
+        &self,
+        provider: &str,
+        remote_list: &str,
+        remote_id: &str,
+        title: &str,
+    ) -> anyhow::Result<Todo> {
+        let tx = self.transaction()?;
+        let todo = self.add_todo(title, None, None)?;
+        self.link_remote(provider, remote_list, remote_id, todo.id)?;
+        tx.commit()?;
+        Ok(todo)
+    }
+
+    /// Open todos that have not been mirrored to `provider`/`remote_list` yet,
+    /// leaving out those the lists hide: archived or put off to someday.
+    pub fn unlinked_todos(&self, provider: &str, remote_list: &str) -> anyhow::Result<Vec<Todo>> {
+        let mut stmt = self.conn.prepare_cached(&format!(
+            "SELECT {TODO_COLUMNS} FROM todos WHERE completed_at IS NULL AND archived_at IS NULL AND someday_at IS NULL AND id NOT IN (SELECT todo_id FROM sync_links WHERE provider = ?1 AND remote_list = ?2 AND todo_id IS NOT NULL) ORDER BY id",
+        ))?;
+        let rows = stmt.query_map(params![provider, remote_list], todo_from_row)?;
+
+        let mut todos = Vec::new();
+        for todo in rows {
+            todos.push(todo?);
+        }
+        Ok(todos)
+    }
+
+    pub fn oauth_token(&self, provider: &str) -> anyhow::Result<Option<OAuthToken>> {
+        let token = self
+            .conn
+            .prepare_cached(
+                "SELECT access_token, refresh_token, expires_at FROM oauth_tokens WHERE provider = ?1",
+            )?
+            .query_row(params![provider], |row| {
+                let expires_at: String = row.get(2)?;
+                Ok(OAuthToken {
+                    access_token: row.get(0)?,
+                    refresh_token: row.get(1)?,
+                    expires_at: parse_datetime(&expires_at),
+                })
+            })
+            .optional()?;
+        Ok(token)
+    }
+
+    pub fn save_oauth_token(&self, provider: &str, token: &OAuthToken) -> anyhow::Result<()> {
+        self.conn
+            .prepare_cached(
+                "INSERT OR REPLACE INTO oauth_tokens (provider, access_token, refresh_token, expires_at) VALUES (?1, ?2, ?3, ?4)",
+            )?
+            .execute(params![
+                provider,
+                token.access_token,
+                token.refresh_token,
+                token.expires_at.to_rfc3339()
+            ])?;
+        Ok(())
+    }
+
+    pub fn add_goal(&self, title: &str) -> anyhow::Result<i64> {
+        self.conn
+            .prepare_cached("INSERT INTO goals (title, created_at) VALUES (?1, ?2)")?
+            .execute(params![title, Utc::now().to_rfc3339()])?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    /// All goals with how many of their linked todos are done, oldest first.
+    pub fn list_goals(&self) -> anyhow::Result<Vec<Goal>> {
+        let mut stmt = self.conn.prepare_cached(
+            "SELECT goals.id, goals.title, goals.created_at, COUNT(todos.completed_at), COUNT(todos.id), goals.color, goals.icon FROM goals LEFT JOIN todos ON todos.goal_id = goals.id GROUP BY goals.id ORDER BY goals.id",
+        )?;
+        let goals = stmt
+            .query_map([], |row| {
+                let created_at: String = row.get(2)?;
+                Ok(Goal {
+                    id: row.get(0)?,
+                    title: row.get(1)?,
+                    created_at: parse_datetime(&created_at),
+                    todos_done: row.get(3)?,
+                    todos_total: row.get(4)?,
+                    color: row.get(5)?,
+                    icon: row.get(6)?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(goals)
+    }
+
+    pub fn update_goal(
+        &self,
+        id: i64,
+        title: &str,
+        color: Option<&str>,
+        icon: Option<&str>,
+    ) -> anyhow::Result<()> {
+        let updated = self
+            .conn
+            .prepare_cached("UPDATE goals SET title = ?1, color = ?2, icon = ?3 WHERE id = ?4")?
+            .execute(params![title, color, icon, id])?;
+        if updated == 0 {
+            return Err(rusqlite::Error::QueryReturnedNoRows.into());
+        }
+        Ok(())
+    }
+
+    /// Todos linked to `goal_id`, open ones first.
+    pub fn goal_todos(&self, goal_id: i64) -> anyhow::Result<Vec<Todo>> {
+        let mut stmt = self.conn.prepare_cached(&format!(
+            "SELECT {TODO_COLUMNS} FROM todos WHERE goal_id = ?1 ORDER BY completed_at IS NOT NULL, id"
+        ))?;
+        let todos = stmt
+            .query_map(params![goal_id], todo_from_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(todos)
+    }
+
+    /// Deletes a goal, keeping a tombstone that also relinks its todos;
+    /// returns the tombstone's id.
+    pub fn delete_goal(&self, id: i64) -> anyhow::Result<i64> {
+        let savepoint = self.savepoint()?;
+        let mut snapshot = Snapshot::default();
+        self.snapshot_rows(&mut snapshot, "goals", "id", id)?;
+        let title = snapshot_title(&snapshot).ok_or(rusqlite::Error::QueryReturnedNoRows)?;
+        self.snapshot_links(&mut snapshot, "todos", "goal_id", &["id"], id)?;
+        let tombstone = self.add_tombstone("goal", id, &title, &snapshot)?;
+        self.conn
+            .prepare_cached("DELETE FROM goals WHERE id = ?1")?
+            .execute(params![id])?;
+        savepoint.release()?;
+        Ok(tombstone)
+    }
+
+    pub fn add_note(&self, title: &str, body: &str) -> anyhow::Result<i64> {
+        self.conn
+            .prepare_cached("INSERT INTO notes (title, body, created_at) VALUES (?1, ?2, ?3)")?
+            .execute(params![title, body, Utc::now().to_rfc3339()])?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    /// All notes, newest first.
+    pub fn list_notes(&self) -> anyhow::Result<Vec<Note>> {
+        let mut stmt = self.conn.prepare_cached(
+            "SELECT id, title, body, created_at, todo_id FROM notes ORDER BY id DESC",
+        )?;
+        let notes = stmt
+            .query_map([], |row| {
+                let created_at: String = row.get(3)?;
+                Ok(Note {
+                    id: row.get(0)?,
+                    title: row.get(1)?,
+                    body: row.get(2)?,
+                    created_at: parse_datetime(&created_at),
+                    todo_id: row.get(4)?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(notes)
+    }
+
+    /// Creates a todo from the note's title and links the note to it; the
+    /// note itself is kept for its text.
+    pub fn convert_note(&self, id: i64) -> anyhow::Result<Todo> {
+        let tx = self.transaction()?;
+        let title: String = self
+            .conn
+            .prepare_cached("SELECT title FROM notes WHERE id = ?1 AND todo_id IS NULL")?
+            .query_row(params![id], |row| row.get(0))?;
+        let todo = self.add_todo(&title, None, None)?;
+        self.conn
+            .prepare_cached("UPDATE notes SET todo_id = ?1 WHERE id = ?2")?
+            .execute(params![todo.id, id])?;
+        tx.commit()?;
+        Ok(todo)
+    }
+
+    /// Adds a todo together with a note linked to it, as if the note had
+    /// been converted: how captured web pages keep their address and quote.
+    pub fn add_todo_with_note(&self, title: &str, body: &str) -> anyhow::Result<Todo> {
+        let tx = self.transaction()?;
+        let todo = self.add_todo(title, None, None)?;
+        self.conn
+            .prepare_cached(
+                "INSERT INTO notes (title, body, created_at, todo_id) VALUES (?1, ?2, ?3, ?4)",
+            )?
+            .execute(params![title, body, Utc::now().to_rfc3339(), todo.id])?;
+        tx.commit()?;
+        Ok(todo)
+    }
+
+    /// Deletes a note, keeping a tombstone; returns the tombstone's id.
+    pub fn delete_note(&self, id: i64) -> anyhow::Result<i64> {
+        let savepoint = self.savepoint()?;
+        let mut snapshot = Snapshot::default();
+        self.snapshot_rows(&mut snapshot, "notes", "id", id)?;
+        let title = snapshot_title(&snapshot).ok_or(rusqlite::Error::QueryReturnedNoRows)?;
+        let tombstone = self.add_tombstone("note", id, &title, &snapshot)?;
+        self.conn
+            .prepare_cached("DELETE FROM notes WHERE id = ?1")?
+            .execute(params![id])?;
+        savepoint.release()?;
+        Ok(tombstone)
+    }
+
+    /// Records that `todo_id` relates to `related_id`; adding a relation
+    /// twice is a no-op.
+    pub fn add_relation(
+        &self,
+        todo_id: i64,
+        related_id: i64,
+        kind: RelationKind,
+    ) -> anyhow::Result<()> {
+        self.conn
+            .prepare_cached(
+                "INSERT OR IGNORE INTO todo_relations (todo_id, related_id, kind) VALUES (?1, ?2, ?3)",
+            )?
+            .execute(params![todo_id, related_id, kind.as_str()])?;
+        Ok(())
+    }
+
+    /// Relations in both directions: those added on `todo_id` first, then
+    /// those other todos have to it.
+    pub fn relations(&self, todo_id: i64) -> anyhow::Result<Vec<Relation>> {
+        let mut stmt = self.conn.prepare_cached(&format!(
+            "SELECT {TODO_COLUMNS}, kind, 1 FROM todo_relations JOIN todos ON todos.id = related_id WHERE todo_id = ?1 \
+             UNION ALL \
+             SELECT {TODO_COLUMNS}, kind, 0 FROM todo_relations JOIN todos ON todos.id = todo_id WHERE related_id = ?1 \
+             ORDER BY 16 DESC, 1"
+        ))?;
+        let relations = stmt
+            .query_map(params![todo_id], |row| {
+                let kind: String = row.get(14)?;
+                Ok((kind, row.get(15)?, todo_from_row(row)?))
+            })?
+            .collect::<rusqlite::Result<Vec<(String, bool, Todo)>>>()?
+            .into_iter()
+            // Kinds this version doesn't know are left alone.
+            .filter_map(|(kind, outgoing, other)| {
+                Some(Relation {
+                    kind: RelationKind::parse(&kind)?,
+                    outgoing,
+                    other,
+                })
+            })
+            .collect();
+        Ok(relations)
+    }
+
+    /// Every relation of `kind` as `(todo_id, related_id)`, read from the todo
+    /// it was added on.
+    pub fn relation_pairs(&self, kind: RelationKind) -> anyhow::Result<Vec<(i64, i64)>> {
+        let pairs = self
+            .conn
+            .prepare_cached(
+                "SELECT todo_id, related_id FROM todo_relations WHERE kind = ?1 ORDER BY todo_id, related_id",
+            )?
+            .query_map(params![kind.as_str()], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(pairs)
+    }
+
+    pub fn delete_relation(
+        &self,
+        todo_id: i64,
+        related_id: i64,
+        kind: RelationKind,
+    ) -> anyhow::Result<()> {
+        let deleted = self
+            .conn
+            .prepare_cached(
+                "DELETE FROM todo_relations WHERE todo_id = ?1 AND related_id = ?2 AND kind = ?3",
+            )?
+            .execute(params![todo_id, related_id, kind.as_str()])?;
+        if deleted == 0 {
+            return Err(rusqlite::Error::QueryReturnedNoRows.into());
+        }
+        Ok(())
+    }
+
+    /// Adds a reminder at `remind_at`; `offset_minutes` marks one that keeps
+    /// that distance to the deadline when the deadline moves.
+    pub fn add_reminder(
+        &self,
+        todo_id: i64,
+        remind_at: DateTime<Utc>,
+        offset_minutes: Option<i64>,
+    ) -> anyhow::Result<i64> {
+        self.conn
+            .prepare_cached(
+                "INSERT INTO reminders (todo_id, remind_at, offset_minutes) VALUES (?1, ?2, ?3)",
+            )?
+            .execute(params![todo_id, remind_at.to_rfc3339(), offset_minutes])?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    /// Reminders of one todo, earliest first.
+    pub fn reminders(&self, todo_id: i64) -> anyhow::Result<Vec<Reminder>> {
+        let mut stmt = self.conn.prepare_cached(
+            "SELECT id, todo_id, remind_at, offset_minutes FROM reminders WHERE todo_id = ?1 ORDER BY remind_at",
+        )?;
+        let reminders = stmt
+            .query_map(params![todo_id], reminder_from_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(reminders)
+    }
+
+    /// The token of the todo's public link, if it has one.
+    pub fn share_token(&self, todo_id: i64) -> anyhow::Result<Option<String>> {
+        let token = self
+            .conn
+            .prepare_cached("SELECT token FROM share_links WHERE todo_id = ?1")?
+            .query_row(params![todo_id], |row| row.get(0))
+            .optional()?;
+        Ok(token)
+    }
+
+    /// Gives the todo a public link with `token`, unless it has one already.
+    pub fn add_share_token(&self, todo_id: i64, token: &str) -> anyhow::Result<()> {
+        self.conn
+            .prepare_cached(
+                "INSERT OR IGNORE INTO share_links (todo_id, token, created_at) VALUES (?1, ?2, ?3)",
+            )?
+            .execute(params![todo_id, token, Utc::now().to_rfc3339()])?;
+        Ok(())
+    }
+
+    pub fn delete_share_token(&self, todo_id: i64) -> anyhow::Result<()> {
+        self.conn
+            .prepare_cached("DELETE FROM share_links WHERE todo_id = ?1")?
+            .execute(params![todo_id])?;
+        Ok(())
+    }
+
+    /// The todo a public link leads to.
+    pub fn shared_todo(&self, token: &str) -> anyhow::Result<Option<Todo>> {
+        let todo = self
+            .conn
+            .prepare_cached(&format!(
+                "SELECT {TODO_COLUMNS} FROM todos WHERE id = (SELECT todo_id FROM share_links WHERE token = ?1)"
+            ))?
+            .query_row(params![token], todo_from_row)
+            .optional()?;
+        Ok(todo)
+    }
+
+    /// The pomodoros of a todo, oldest first.
+    pub fn pomodoros(&self, todo_id: i64) -> anyhow::Result<Vec<Pomodoro>> {
+        let pomodoros = self
+            .conn
+            .prepare_cached(
+                "SELECT id, todo_id, started_at, paused_at, paused_seconds, finished_at FROM pomodoros WHERE todo_id = ?1 ORDER BY started_at",
+            )?
+            .query_map(params![todo_id], pomodoro_from_row)?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(pomodoros)
+    }
+
+    /// Pomodoros of any todo finished at or after `since`.
+    pub fn pomodoros_finished_since(&self, since: DateTime<Utc>) -> anyhow::Result<Vec<Pomodoro>> {
+        let pomodoros = self
+            .conn
+            .prepare_cached(
+                "SELECT id, todo_id, started_at, paused_at, paused_seconds, finished_at FROM pomodoros WHERE finished_at >= ?1 ORDER BY finished_at",
+            )?
+            .query_map(params![since.to_rfc3339()], pomodoro_from_row)?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(pomodoros)
+    }
+
+    pub fn add_pomodoro(&self, todo_id: i64, started_at: DateTime<Utc>) -> anyhow::Result<()> {
+        self.conn
+            .prepare_cached("INSERT INTO pomodoros (todo_id, started_at) VALUES (?1, ?2)")?
+            .execute(params![todo_id, started_at.to_rfc3339()])?;
+        Ok(())
+    }
+
+    /// Stores a pomodoro's pause and finish times.
+    pub fn update_pomodoro(&self, pomodoro: &Pomodoro) -> anyhow::Result<()> {
+        self.conn
+            .prepare_cached(
+                "UPDATE pomodoros SET paused_at = ?1, paused_seconds = ?2, finished_at = ?3 WHERE id = ?4",
+            )?
+            .execute(params![
+                pomodoro.paused_at.map(|at| at.to_rfc3339()),
+                pomodoro.paused_seconds,
+                pomodoro.finished_at.map(|at| at.to_rfc3339()),
+                pomodoro.id
+            ])?;
+        Ok(())
+    }
+
+    pub fn delete_reminder(&self, todo_id: i64, id: i64) -> anyhow::Result<()> {
+        let deleted = self
+            .conn
+            .prepare_cached("DELETE FROM reminders WHERE id = ?1 AND todo_id = ?2")?
+            .execute(params![id, todo_id])?;
+        if deleted == 0 {
+            return Err(rusqlite::Error::QueryReturnedNoRows.into());
+        }
+        Ok(())
+    }
+
+    /// Reminder offsets given to todos that get a deadline, in minutes.
+    pub fn default_reminder_offsets(&self) -> anyhow::Result<Vec<i64>> {
+        let offsets = self
+            .setting(REMINDER_OFFSETS_SETTING)?
+            .unwrap_or_default()
+            .split(',')
+            .filter_map(|offset| offset.parse().ok())
+            .collect();
+        Ok(offsets)
+    }
+
+    pub fn set_default_reminder_offsets(&self, offsets: &[i64]) -> anyhow::Result<()> {
+        let offsets: Vec<String> = offsets.iter().map(i64::to_string).collect();
+        self.set_setting(REMINDER_OFFSETS_SETTING, &offsets.join(","))
+    }
+
+    /// The todos picked for `day`, open ones first, each group in the order
+    /// they were picked.
+    pub fn focus(&self, day: NaiveDate) -> anyhow::Result<Vec<Todo>> {
+        let todos = self
+            .conn
+            .prepare_cached(&format!(
+                "SELECT {TODO_COLUMNS} FROM focus JOIN todos ON todos.id = todo_id \
+                 WHERE day = ?1 AND archived_at IS NULL ORDER BY completed_at IS NOT NULL, focus.rowid"
+            ))?
+            .query_map(params![day], todo_from_row)?
+            .collect::<rusqlite::Result<_>>()?;
+        Ok(todos)
+    }
+
+    /// Picks a todo for `day`; picking it twice is a no-op.
+    pub fn add_focus(&self, todo_id: i64, day: NaiveDate) -> anyhow::Result<()> {
+        self.conn
+            .prepare_cached("INSERT OR IGNORE INTO focus (todo_id, day) VALUES (?1, ?2)")?
+            .execute(params![todo_id, day])?;
+        Ok(())
+    }
+
+    pub fn remove_focus(&self, todo_id: i64, day: NaiveDate) -> anyhow::Result<()> {
+        self.conn
+            .prepare_cached("DELETE FROM focus WHERE todo_id = ?1 AND day = ?2")?
+            .execute(params![todo_id, day])?;
+        Ok(())
+    }
+
+    /// The latest day before `day` that had todos picked, unless its leftovers
+    /// were already dealt with on `day`.
+    pub fn focus_to_review(&self, day: NaiveDate) -> anyhow::Result<Option<NaiveDate>> {
+        if self.setting(FOCUS_REVIEWED_SETTING)? == Some(day.to_string()) {
+            return Ok(None);
+        }
+        let previous = self
+            .conn
+            .prepare_cached("SELECT MAX(day) FROM focus WHERE day < ?1")?
+            .query_row(params![day], |row| row.get(0))?;
+        Ok(previous)
+    }
+
+    /// Records that the leftovers were carried over to or dropped on `day`.
+    pub fn set_focus_reviewed(&self, day: NaiveDate) -> anyhow::Result<()> {
+        self.set_setting(FOCUS_REVIEWED_SETTING, &day.to_string())
+    }
+
+    /// Keeps the reminders relative to a todo's deadline in step with it: a
+    /// new deadline gets the default offsets (those still ahead), a moved one
+    /// takes its reminders along, and a removed one takes them away.
+    fn reschedule_reminders(
+        &self,
+        todo_id: i64,
+        previous: Option<NaiveDate>,
+        deadline: Option<NaiveDate>,
+    ) -> anyhow::Result<()> {
+        match (previous, deadline) {
+            (_, None) => {
+                self.conn
+                    .prepare_cached(
+                        "DELETE FROM reminders WHERE todo_id = ?1 AND offset_minutes IS NOT NULL",
+                    )?
+                    .execute(params![todo_id])?;
+            }
+            (None, Some(deadline)) => {
+                let now = Utc::now();
+                for offset in self.default_reminder_offsets()? {
+                    let remind_at = reminders::before_deadline(deadline, offset);
+                    if remind_at > now {
+                        self.add_reminder(todo_id, remind_at, Some(offset))?;
+                    }
+                }
+            }
+            (Some(previous), Some(deadline)) if previous != deadline => {
+                let reminders = self.reminders(todo_id)?;
+                for reminder in reminders {
+                    let Some(offset) = reminder.offset_minutes else {
+                        continue;
+                    };
+                    self.conn
+                        .prepare_cached("UPDATE reminders SET remind_at = ?1 WHERE id = ?2")?
+                        .execute(params![
+                            reminders::before_deadline(deadline, offset).to_rfc3339(),
+                            reminder.id
+                        ])?;
+                }
+            }
+            (Some(_), Some(_)) => {}
+        }
+        Ok(())
+    }
+
+    /// Open todos due on `date`.
+    pub fn todos_due_on(&self, date: NaiveDate) -> anyhow::Result<Vec<Todo>> {
+        let mut stmt = self.conn.prepare_cached(&format!(
+            "SELECT {TODO_COLUMNS} FROM todos WHERE deadline = ?1 AND completed_at IS NULL AND archived_at IS NULL AND someday_at IS NULL ORDER BY id"
+        ))?;
+        let todos = stmt
+            .query_map(params![date], todo_from_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(todos)
+    }
+
+    /// Open todos whose deadline was before `date`, oldest deadline first.
+    pub fn overdue_todos(&self, date: NaiveDate) -> anyhow::Result<Vec<Todo>> {
+        let mut stmt = self.conn.prepare_cached(&format!(
+            "SELECT {TODO_COLUMNS} FROM todos WHERE deadline < ?1 AND completed_at IS NULL AND archived_at IS NULL AND someday_at IS NULL ORDER BY deadline, id"
+        ))?;
+        let todos = stmt
+            .query_map(params![date], todo_from_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(todos)
+    }
+
+    /// Todos completed from `since` up to (excluding) `until`, in order.
+    pub fn completed_between(
+        &self,
+        since: DateTime<Utc>,
+        until: DateTime<Utc>,
+    ) -> anyhow::Result<Vec<Todo>> {
+        let mut stmt = self.conn.prepare_cached(&format!(
+            "SELECT {TODO_COLUMNS} FROM todos WHERE completed_at >= ?1 AND completed_at < ?2 ORDER BY completed_at"
+        ))?;
+        let todos = stmt
+            .query_map(
+                params![since.to_rfc3339(), until.to_rfc3339()],
+                todo_from_row,
+            )?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(todos)
+    }
+
+    /// Reminders of open todos that came due between `since` and `until`,
+    /// with their todos.
+    pub fn due_reminders(
+        &self,
+        since: DateTime<Utc>,
+        until: DateTime<Utc>,
+    ) -> anyhow::Result<Vec<(Reminder, Todo)>> {
+        let mut stmt = self.conn.prepare_cached(&format!(
+            "SELECT {TODO_COLUMNS}, reminder_id, remind_at, offset_minutes FROM todos \
+             JOIN (SELECT id AS reminder_id, todo_id, remind_at, offset_minutes FROM reminders) ON id = todo_id \
+             WHERE remind_at > ?1 AND remind_at <= ?2 AND completed_at IS NULL AND archived_at IS NULL AND someday_at IS NULL \
+             ORDER BY remind_at"
+        ))?;
+        let due = stmt
+            .query_map(params![since.to_rfc3339(), until.to_rfc3339()], |row| {
+                let todo = todo_from_row(row)?;
+                let remind_at: String = row.get(15)?;
+                let reminder = Reminder {
+                    id: row.get(14)?,
+                    todo_id: todo.id,
+                    remind_at: parse_datetime(&remind_at),
+                    offset_minutes: row.get(16)?,
+                };
+                Ok((reminder, todo))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(due)
+    }
+
+    /// Whether the notification `key` still has to go out on `channel`: it
+    /// wasn't sent yet and has failed fewer than `max_attempts` times.
+    pub fn notification_pending(
+        &self,
+        key: &str,
+        channel: &str,
+        max_attempts: u32,
+    ) -> anyhow::Result<bool> {
+        let job: Option<(u32, Option<String>)> = self
+            .conn
+            .prepare_cached(
+                "SELECT attempts, sent_at FROM notification_jobs WHERE key = ?1 AND channel = ?2",
+            )?
+            .query_row(params![key, channel], |row| Ok((row.get(0)?, row.get(1)?)))
+            .optional()?;
+        Ok(match job {
+            None => true,
+            Some((attempts, sent_at)) => sent_at.is_none() && attempts < max_attempts,
+        })
+    }
+
+    /// Records an attempt to send notification `key` on `channel`, with the
+    /// error if it failed.
+    pub fn record_notification(
+        &self,
+        key: &str,
+        channel: &str,
+        error: Option<&str>,
+    ) -> anyhow::Result<()> {
+        let sent_at = error.is_none().then(|| Utc::now().to_rfc3339());
+        self.conn
+            .prepare_cached(
+                "INSERT INTO notification_jobs (key, channel, attempts, sent_at, last_error) VALUES (?1, ?2, 1, ?3, ?4) \
+                 ON CONFLICT (key, channel) DO UPDATE SET attempts = attempts + 1, sent_at = ?3, last_error = ?4",
+            )?
+            .execute(params![key, channel, sent_at, error])?;
+        Ok(())
+    }
+
+    /// Adds a mail to the queue, due right away.
+    pub fn queue_mail(&self, recipient: &str, subject: &str, body: &str) -> anyhow::Result<()> {
+        let now = Utc::now().to_rfc3339();
+        self.conn
+            .prepare_cached(
+                "INSERT INTO mail_queue (recipient, subject, body, created_at, next_attempt_at) VALUES (?1, ?2, ?3, ?4, ?4)",
+            )?
+            .execute(params![recipient, subject, body, now])?;
+        Ok(())
+    }
+
+    /// Unsent mail whose next attempt is due and that has failed fewer than
+    /// `max_attempts` times, oldest first.
+    pub fn due_mail(
+        &self,
+        now: DateTime<Utc>,
+        max_attempts: u32,
+    ) -> anyhow::Result<Vec<QueuedMail>> {
+        let mut stmt = self.conn.prepare_cached(
+            "SELECT id, recipient, subject, body, attempts FROM mail_queue \
+             WHERE sent_at IS NULL AND attempts < ?1 AND next_attempt_at <= ?2 ORDER BY id",
+        )?;
+        let mail = stmt
+            .query_map(params![max_attempts, now.to_rfc3339()], |row| {
+                Ok(QueuedMail {
+                    id: row.get(0)?,
+                    recipient: row.get(1)?,
+                    subject: row.get(2)?,
+                    body: row.get(3)?,
+                    attempts: row.get(4)?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(mail)
+    }
+
+    /// Records an attempt to send queued mail `id`: sent without `error`,
+    /// otherwise tried again from `retry_at`.
+    pub fn record_mail_attempt(
+        &self,
+        id: i64,
+        error: Option<&str>,
+        retry_at: DateTime<Utc>,
+    ) -> anyhow::Result<()> {
+        let sent_at = error.is_none().then(|| Utc::now().to_rfc3339());
+        self.conn
+            .prepare_cached(
+                "UPDATE mail_queue SET attempts = attempts + 1, sent_at = ?2, last_error = ?3, next_attempt_at = ?4 WHERE id = ?1",
+            )?
+            .execute(params![id, sent_at, error, retry_at.to_rfc3339()])?;
+        Ok(())
+    }
+
+    /// Deletes mail sent before `before`; returns how many.
+    pub fn purge_sent_mail(&self, before: DateTime<Utc>) -> anyhow::Result<usize> {
+        let purged = self
+            .conn
+            .prepare_cached("DELETE FROM mail_queue WHERE sent_at < ?1")?
+            .execute(params![before.to_rfc3339()])?;
+        Ok(purged)
+    }
+
+    /// How the queue is doing, for the admin page.
+    pub fn mail_queue_status(&self, max_attempts: u32) -> anyhow::Result<MailQueueStatus> {
+        let (waiting, given_up): (u32, u32) = self.conn.query_row(
+            "SELECT COALESCE(SUM(attempts < ?1), 0), COALESCE(SUM(attempts >= ?1), 0) FROM mail_queue WHERE sent_at IS NULL",
+            params![max_attempts],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+        let last_error = self
+            .conn
+            .query_row(
+                "SELECT last_error FROM mail_queue WHERE sent_at IS NULL AND last_error IS NOT NULL ORDER BY id DESC LIMIT 1",
+                [],
+                |row| row.get(0),
+            )
+            .optional()?;
+        Ok(MailQueueStatus {
+            waiting,
+            given_up,
+            last_error,
+        })
+    }
+
+    pub fn setting(&self, name: &str) -> anyhow::Result<Option<String>> {
+        let value = self
+            .conn
+            .prepare_cached("SELECT value FROM settings WHERE name = ?1")?
+            .query_row(params![name], |row| row.get(0))
+            .optional()?;
+        Ok(value)
+    }
+
+    pub fn set_setting(&self, name: &str, value: &str) -> anyhow::Result<()> {
+        self.conn
+            .prepare_cached("INSERT OR REPLACE INTO settings (name, value) VALUES (?1, ?2)")?
+            .execute(params![name, value])?;
+        Ok(())
+    }
+
+    /// Stores a browser's push subscription, replacing an older one for the
+    /// same endpoint.
+    pub fn add_push_subscription(&self, subscription: &PushSubscription) -> anyhow::Result<()> {
+        self.conn
+            .prepare_cached(
+                "INSERT OR REPLACE INTO push_subscriptions (endpoint, p256dh, auth, created_at) VALUES (?1, ?2, ?3, ?4)",
+            )?
+            .execute(params![
+                subscription.endpoint,
+                subscription.p256dh,
+                subscription.auth,
+                Utc::now().to_rfc3339()
+            ])?;
+        Ok(())
+    }
+
+    pub fn push_subscriptions(&self) -> anyhow::Result<Vec<PushSubscription>> {
+        let mut stmt = self
+            .conn
+            .prepare_cached("SELECT endpoint, p256dh, auth FROM push_subscriptions")?;
+        let subscriptions = stmt
+            .query_map([], |row| {
+                Ok(PushSubscription {
+                    endpoint: row.get(0)?,
+                    p256dh: row.get(1)?,
+                    auth: row.get(2)?,
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(subscriptions)
+    }
+
+    /// Forgets a subscription; unknown endpoints are ignored.
+    pub fn delete_push_subscription(&self, endpoint: &str) -> anyhow::Result<()> {
+        self.conn
+            .prepare_cached("DELETE FROM push_subscriptions WHERE endpoint = ?1")?
+            .execute(params![endpoint])?;
+        Ok(())
+    }
+
+    /// Feature flags that were switched at some point, by name.
+    pub fn feature_flags(&self) -> anyhow::Result<Vec<(String, bool)>> {
+        let mut stmt = self
+            .conn
+            .prepare_cached("SELECT name, enabled FROM feature_flags")?;
+        let flags = stmt
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(flags)
+    }
+
+    pub fn set_feature_flag(&self, name: &str, enabled: bool) -> anyhow::Result<()> {
+        self.conn
+            .prepare_cached("INSERT OR REPLACE INTO feature_flags (name, enabled) VALUES (?1, ?2)")?
+            .execute(params![name, enabled])?;
+        Ok(())
+    }
+
+    /// Rebuilds the file and refreshes the query planner's statistics.
+    pub fn maintain(&self) -> anyhow::Result<()> {
+        self.conn
+            .execute_batch("VACUUM; ANALYZE; PRAGMA optimize;")?;
+        Ok(())
+    }
+
+    /// Copies the database into a new file at `path` with SQLite's online
+    /// backup API. An encrypted database needs its `key` for the copy.
+    pub fn backup_to(&self, path: &Path, key: Option<&str>) -> anyhow::Result<()> {
+        let mut copy = Connection::open(path)
+            .with_context(|| format!("creating backup at {}", path.display()))?;
+        if let Some(key) = key {
+            copy.pragma_update(None, "key", key)?;
+        }
+        Backup::new(&self.conn, &mut copy)?.run_to_completion(1024, Duration::ZERO, None)?;
+        Ok(())
+    }
+
+    /// Replaces the whole database with the copy at `path`, e.g. one made by
+    /// [`Database::backup_to`], and brings it up to the current schema.
+    pub fn restore_from(&mut self, path: &Path, key: Option<&str>) -> anyhow::Result<()> {
+        let copy = Connection::open_with_flags(path, OpenFlags::SQLITE_OPEN_READ_ONLY)
+            .with_context(|| format!("opening {}", path.display()))?;
+        if let Some(key) = key {
+            copy.pragma_update(None, "key", key)?;
+        }
+        Backup::new(&copy, &mut self.conn)?.run_to_completion(1024, Duration::ZERO, None)?;
+        self.migrate()?;
+        // The copy bypasses the update hook; pages cached by generation must
+        // still see the change.
+        self.generation.fetch_add(1, Ordering::AcqRel);
+        Ok(())
+    }
+
+    /// Switches to WAL mode with automatic checkpoints off, so the WAL only
+    /// restarts when [`Database::checkpoint`] says so. Returns the path of
+    /// the WAL file.
+    pub fn enable_wal(&self) -> anyhow::Result<PathBuf> {
+        let mode: String =
+            self.conn
+                .pragma_update_and_check(None, "journal_mode", "wal", |row| row.get(0))?;
+        if !mode.eq_ignore_ascii_case("wal") {
+            anyhow::bail!("the database can't switch to WAL mode (it stays in {mode} mode)");
+        }
+        self.conn.pragma_update(None, "wal_autocheckpoint", 0)?;
+        let path = self
+            .conn
+            .path()
+            .filter(|path| !path.is_empty())
+            .context("an in-memory database has no WAL file")?;
+        Ok(PathBuf::from(format!("{path}-wal")))
+    }
+
+    /// Copies everything in the WAL into the database file and empties the
+    /// WAL.
+    pub fn checkpoint(&self) -> anyhow::Result<()> {
+        let busy: i64 = self
+            .conn
+            .query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |row| row.get(0))?;
+        if busy != 0 {
+            anyhow::bail!("the checkpoint was blocked by another connection");
+        }
+        Ok(())
+    }
+
+    /// Runs `PRAGMA integrity_check` and gathers basic statistics.
+    pub fn health(&self) -> anyhow::Result<Health> {
+        let mut stmt = self.conn.prepare("PRAGMA integrity_check")?;
+        let problems = stmt
+            .query_map([], |row| row.get::<_, String>(0))?
+            .filter(|line| !matches!(line.as_deref(), Ok("ok")))
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        let mut row_counts = Vec::new();
+        for table in TABLES {
+            let count =
+                self.conn
+                    .query_row(&format!("SELECT COUNT(*) FROM {table}"), [], |row| {
+                        row.get(0)
+                    })?;
+            row_counts.push((*table, count));
+        }
+
+        Ok(Health {
+            problems,
+            schema_version: self
+                .conn
+                .query_row("PRAGMA user_version", [], |row| row.get(0))?,
+            row_counts,
+            file_size: self.file_size()?,
+        })
+    }
+
+    pub fn file_size(&self) -> anyhow::Result<u64> {
+        let size = self.conn.query_row(
+            "SELECT page_count * page_size FROM pragma_page_count(), pragma_page_size()",
+            [],
+            |row| row.get(0),
+        )?;
+        Ok(size)
+    }
+
+    /// Deletes a todo, keeping a tombstone to restore it from; returns the
+    /// tombstone's id.
+    pub fn delete_todo(&self, id: i64) -> anyhow::Result<i64> {
+        let savepoint = self.savepoint()?;
+        let mut snapshot = Snapshot::default();
+        self.snapshot_rows(&mut snapshot, "todos", "id", id)?;
+        let title = snapshot_title(&snapshot).ok_or(TodoError::NotFound(id))?;
+        for (table, column) in TODO_DEPENDENTS {
+            self.snapshot_rows(&mut snapshot, table, column, id)?;
+        }
+        for (table, column, key) in TODO_LINKS {
+            self.snapshot_links(&mut snapshot, table, column, key, id)?;
+        }
+        let tombstone = self.add_tombstone("todo", id, &title, &snapshot)?;
+        self.conn
+            .prepare_cached("DELETE FROM todos WHERE id = ?1")?
+            .execute(params![id])?;
+        self.audit("delete", Some(id), json!({ "title": title }))?;
+        savepoint.release()?;
+        Ok(tombstone)
+    }
+
+    /// Adds the rows of `table` whose `column` is `value` to `snapshot`.
+    fn snapshot_rows(
+        &self,
+        snapshot: &mut Snapshot,
+        table: &str,
+        column: &str,
+        value: i64,
+    ) -> anyhow::Result<()> {
+        let mut stmt = self
+            .conn
+            .prepare_cached(&format!("SELECT * FROM {table} WHERE {column} = ?1"))?;
+        let names: Vec<String> = stmt.column_names().into_iter().map(String::from).collect();
+        let mut rows = stmt.query(params![value])?;
+        while let Some(row) = rows.next()? {
+            let mut values = Map::new();
+            for (index, name) in names.iter().enumerate() {
+                values.insert(name.clone(), json_value(row.get(index)?));
+            }
+            snapshot.rows.push(SnapshotRow {
+                table: table.to_string(),
+                row: values,
+            });
+        }
+        Ok(())
+    }
+
+    /// Adds the rows of `table` whose `column` is `value`, identified by
+    /// the `key` columns, to the links of `snapshot`.
+    fn snapshot_links(
+        &self,
+        snapshot: &mut Snapshot,
+        table: &str,
+        column: &str,
+        key: &[&str],
+        value: i64,
+    ) -> anyhow::Result<()> {
+        let mut stmt = self.conn.prepare_cached(&format!(
+            "SELECT {} FROM {table} WHERE {column} = ?1",
+            key.join(", ")
+        ))?;
+        let mut rows = stmt.query(params![value])?;
+        while let Some(row) = rows.next()? {
+            let mut values = Map::new();
+            for (index, name) in key.iter().enumerate() {
+                values.insert(name.to_string(), json_value(row.get(index)?));
+            }
+            snapshot.links.push(SnapshotLink {
+                table: table.to_string(),
+                column: column.to_string(),
+                value,
+                key: values,
+            });
+        }
+        Ok(())
+    }
+
+    fn add_tombstone(
+        &self,
+        kind: &str,
+        object_id: i64,
+        title: &str,
+        snapshot: &Snapshot,
+    ) -> anyhow::Result<i64> {
+        self.conn
+            .prepare_cached(
+                "INSERT INTO tombstones (kind, object_id, title, deleted_at, rows) VALUES (?1, ?2, ?3, ?4, ?5)",
+            )?
+            .execute(params![
+                kind,
+                object_id,
+                title,
+                Utc::now().to_rfc3339(),
+                serde_json::to_string(snapshot)?
+            ])?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    /// The tombstone `id` if it was made at or after `since`.
+    pub fn tombstone(&self, id: i64, since: DateTime<Utc>) -> anyhow::Result<Option<Tombstone>> {
+        let tombstone = self
+            .conn
+            .prepare_cached(
+                "SELECT id, kind, object_id, title, deleted_at FROM tombstones WHERE id = ?1 AND deleted_at >= ?2",
+            )?
+            .query_row(params![id, since.to_rfc3339()], tombstone_from_row)
+            .optional()?;
+        Ok(tombstone)
+    }
+
+    /// Puts back what the tombstone `id` holds and removes it. Rows that
+    /// depend on something deleted since are left out.
+    pub fn restore(&self, id: i64, since: DateTime<Utc>) -> anyhow::Result<Option<Tombstone>> {
+        let Some(tombstone) = self.tombstone(id, since)? else {
+            return Ok(None);
+        };
+        let rows: String = self
+            .conn
+            .prepare_cached("SELECT rows FROM tombstones WHERE id = ?1")?
+            .query_row(params![id], |row| row.get(0))?;
+        let snapshot: Snapshot = serde_json::from_str(&rows)?;
+
+        let tx = self.transaction()?;
+        for (index, row) in snapshot.rows.iter().enumerate() {
+            // Only ever the tables listed here, since the names go into SQL.
+            anyhow::ensure!(
+                TABLES.contains(&row.table.as_str()),
+                "unknown table {}",
+                row.table
+            );
+            let columns: Vec<&str> = row.row.keys().map(String::as_str).collect();
+            let sql = format!(
+                "INSERT INTO {} ({}) VALUES ({})",
+                row.table,
+                columns.join(", "),
+                vec!["?"; columns.len()].join(", ")
+            );
+            let values = row.row.values().map(sql_value);
+            match self
+                .conn
+                .prepare_cached(&sql)?
+                .execute(params_from_iter(values))
+            {
+                Ok(_) => {}
+                // The first row is the deleted item itself, which must come back.
+                Err(rusqlite::Error::SqliteFailure(failure, _))
+                    if index > 0 && failure.code == rusqlite::ErrorCode::ConstraintViolation => {}
+                Err(err) => return Err(err.into()),
+            }
+        }
+        for link in &snapshot.links {
+            anyhow::ensure!(
+                TABLES.contains(&link.table.as_str()),
+                "unknown table {}",
+                link.table
+            );
+            let conditions: Vec<String> = link
+                .key
+                .keys()
+                .map(|column| format!("{column} = ?"))
+                .collect();
+            let sql = format!(
+                "UPDATE {table} SET {column} = ? WHERE {conditions} AND {column} IS NULL",
+                table = link.table,
+                column = link.column,
+                conditions = conditions.join(" AND "),
+            );
+            let values =
+                std::iter::once(Value::from(link.value)).chain(link.key.values().map(sql_value));
+            self.conn
+                .prepare_cached(&sql)?
+                .execute(params_from_iter(values))?;
+        }
+        self.conn
+            .prepare_cached("DELETE FROM tombstones WHERE id = ?1")?
+            .execute(params![id])?;
+        if tombstone.kind == "todo" {
+            self.audit(
+                "restore",
+                Some(tombstone.object_id),
+                json!({ "title": tombstone.title }),
+            )?;
+        }
+        tx.commit()?;
+        Ok(Some(tombstone))
+    }
+
+    /// How long completed todos, audit log entries and pomodoros are kept;
+    /// everything for good until set.
+    pub fn retention_policy(&self) -> anyhow::Result<RetentionPolicy> {
+        let policy = self
+            .setting(RETENTION_SETTING)?
+            .and_then(|policy| serde_json::from_str(&policy).ok())
+            .unwrap_or_default();
+        Ok(policy)
+    }
+
+    pub fn set_retention_policy(&self, policy: &RetentionPolicy) -> anyhow::Result<()> {
+        self.set_setting(RETENTION_SETTING, &serde_json::to_string(policy)?)
+    }
+
+    /// Deletes or anonymizes what `policy` keeps no longer as of `now`;
+    /// returns how many rows were affected.
+    pub fn apply_retention(
+        &self,
+        policy: &RetentionPolicy,
+        now: DateTime<Utc>,
+    ) -> anyhow::Result<usize> {
+        let tx = self.transaction()?;
+        let mut affected = 0;
+        if let Some(before) = retention::cutoff(now, policy.todos) {
+            // Habits count as completed until they reopen the next day.
+            let due = if policy.anonymize {
+                self.conn
+                    .prepare_cached(
+                        "SELECT id FROM todos WHERE completed_at < ?1 AND habit = 0 AND (title != ?2 OR location IS NOT NULL \
+                         OR id IN (SELECT todo_id FROM audit_log WHERE changes != '{}') OR id IN (SELECT todo_id FROM notes))",
+                    )?
+                    .query_map(params![before.to_rfc3339(), ANONYMIZED_TITLE], |row| {
+                        row.get(0)
+                    })?
+                    .collect::<rusqlite::Result<Vec<i64>>>()?
+            } else {
+                self.conn
+                    .prepare_cached("SELECT id FROM todos WHERE completed_at < ?1 AND habit = 0")?
+                    .query_map(params![before.to_rfc3339()], |row| row.get(0))?
+                    .collect::<rusqlite::Result<Vec<i64>>>()?
+            };
+            for id in &due {
+                self.scrub_todo(*id)?;
+                if policy.anonymize {
+                    self.conn
+                        .prepare_cached(
+                            "UPDATE todos SET title = ?1, location = NULL, version = version + 1 WHERE id = ?2",
+                        )?
+                        .execute(params![ANONYMIZED_TITLE, id])?;
+                    self.audit("anonymize", Some(*id), json!({}))?;
+                } else {
+                    self.conn
+                        .prepare_cached("DELETE FROM todos WHERE id = ?1")?
+                        .execute(params![id])?;
+                    self.audit("purge", Some(*id), json!({}))?;
+                }
+            }
+            affected += due.len();
+        }
+        if let Some(before) = retention::cutoff(now, policy.pomodoros) {
+            affected += self
+                .conn
+                .prepare_cached("DELETE FROM pomodoros WHERE finished_at < ?1")?
+                .execute(params![before.to_rfc3339()])?;
+        }
+        if let Some(before) = retention::cutoff(now, policy.audit) {
+            let before = before.to_rfc3339();
+            self.set_setting(AUDIT_PURGED_BEFORE_SETTING, &before)?;
+            affected += self
+                .conn
+                .prepare_cached("DELETE FROM audit_log WHERE at < ?1")?
+                .execute(params![before])?;
+        }
+        tx.commit()?;
+        Ok(affected)
+    }
+
+    /// Removes the other copies of a todo's text before retention anonymizes
+    /// or purges it: the changes in its audit entries, its notes and the
+    /// tombstones of notes deleted from it.
+    fn scrub_todo(&self, id: i64) -> anyhow::Result<()> {
+        self.conn
+            .prepare_cached(
+                "UPDATE audit_log SET changes = '{}' WHERE todo_id = ?1 AND changes != '{}'",
+            )?
+            .execute(params![id])?;
+        self.conn
+            .prepare_cached("DELETE FROM notes WHERE todo_id = ?1")?
+            .execute(params![id])?;
+        self.conn
+            .prepare_cached(
+                "DELETE FROM tombstones WHERE kind = 'note' AND EXISTS \
+                 (SELECT 1 FROM json_each(rows, '$.rows') WHERE json_extract(value, '$.row.todo_id') = ?1)",
+            )?
+            .execute(params![id])?;
+        Ok(())
+    }
+
+    /// Tombstones made since `since`, the most recent first.
+    pub fn tombstones(&self, since: DateTime<Utc>) -> anyhow::Result<Vec<Tombstone>> {
+        let tombstones = self
+            .conn
+            .prepare_cached(
+                "SELECT id, kind, object_id, title, deleted_at FROM tombstones WHERE deleted_at >= ?1 ORDER BY deleted_at DESC, id DESC",
+            )?
+            .query_map(params![since.to_rfc3339()], tombstone_from_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(tombstones)
+    }
+
+    /// Days deleted items stay in the trash, 30 until set; 0 keeps them only
+    /// for the undo window.
+    pub fn trash_retention(&self) -> anyhow::Result<u32> {
+        let days = self
+            .setting(TRASH_RETENTION_SETTING)?
+            .and_then(|days| days.parse().ok())
+            .unwrap_or(DEFAULT_TRASH_RETENTION_DAYS);
+        Ok(days)
+    }
+
+    pub fn set_trash_retention(&self, days: u32) -> anyhow::Result<()> {
+        self.set_setting(TRASH_RETENTION_SETTING, &days.to_string())
+    }
+
+    /// Drops tombstones made before `before`; returns how many.
+    pub fn purge_tombstones(&self, before: DateTime<Utc>) -> anyhow::Result<usize> {
+        let purged = self
+            .conn
+            .prepare_cached("DELETE FROM tombstones WHERE deleted_at < ?1")?
+            .execute(params![before.to_rfc3339()])?;
+        Ok(purged)
+    }
+
+    /// Appends to the audit log; the log itself refuses changes to entries.
+    fn audit(
+        &self,
+        action: &str,
+        todo_id: Option<i64>,
+        changes: serde_json::Value,
+    ) -> anyhow::Result<()> {
+        self.conn
+            .prepare_cached(
+                "INSERT INTO audit_log (at, actor, action, todo_id, changes) VALUES (?1, ?2, ?3, ?4, ?5)",
+            )?
+            .execute(params![
+                Utc::now().to_rfc3339(),
+                audit::actor(),
+                action,
+                todo_id,
+                changes.to_string()
+            ])?;
+        Ok(())
+    }
+
+    /// When a todo last changed: its newest audit log entry, or its creation
+    /// once retention has removed those. `None` for a todo that doesn't exist.
+    pub fn modified_at(&self, id: i64) -> anyhow::Result<Option<DateTime<Utc>>> {
+        let at: Option<String> = self
+            .conn
+            .prepare_cached(
+                "SELECT COALESCE((SELECT MAX(at) FROM audit_log WHERE todo_id = ?1), created_at) FROM todos WHERE id = ?1",
+            )?
+            .query_row(params![id], |row| row.get(0))
+            .optional()?;
+        Ok(at.map(|at| parse_datetime(&at)))
+    }
+
+    /// The audit log, newest first.
+    pub fn audit_log(&self, filter: &AuditFilter) -> anyhow::Result<Vec<AuditEntry>> {
+        let mut conditions = vec!["1"];
+        let mut values: Vec<Value> = Vec::new();
+        if let Some(todo_id) = filter.todo_id {
+            conditions.push("todo_id = ?");
+            values.push(todo_id.into());
+        }
+        if let Some(action) = &filter.action {
+            conditions.push("action = ?");
+            values.push(action.clone().into());
+        }
+        if let Some(actor) = &filter.actor {
+            conditions.push("actor = ?");
+            values.push(actor.clone().into());
+        }
+        if let Some(since) = filter.since {
+            conditions.push("at >= ?");
+            values.push(since.to_rfc3339().into());
+        }
+
+        let mut sql = format!(
+            "SELECT id, at, actor, action, todo_id, changes FROM audit_log WHERE {} ORDER BY id DESC",
+            conditions.join(" AND ")
+        );
+        if let Some(limit) = filter.limit {
+            sql.push_str(" LIMIT ?");
+            values.push(limit.into());
+        }
+        let mut stmt = self.conn.prepare_cached(&sql)?;
+        let entries = stmt
+            .query_map(params_from_iter(values), |row| {
+                let at: String = row.get(1)?;
+                let changes: String = row.get(5)?;
+                Ok(AuditEntry {
+                    id: row.get(0)?,
+                    at: parse_datetime(&at),
+                    actor: row.get(2)?,
+                    action: row.get(3)?,
+                    todo_id: row.get(4)?,
+                    changes: serde_json::from_str(&changes).unwrap_or_default(),
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(entries)
+    }
+
+    /// The newest entry of the audit log, counting purged ones. Every change
+    /// to a todo adds an entry, so this is where a sync cursor taken now
+    /// points.
+    pub fn change_cursor(&self) -> anyhow::Result<i64> {
+        let cursor = self
+            .conn
+            .prepare_cached("SELECT seq FROM sqlite_sequence WHERE name = 'audit_log'")?
+            .query_row([], |row| row.get(0))
+            .optional()?;
+        Ok(cursor.unwrap_or(0))
+    }
+
+    /// What changed after the audit log entry `after`: the todos changed
+    /// since, and the ids of those deleted or archived since. `None` if
+    /// entries since then were purged, or if `after` lies beyond the log, as
+    /// it does after restoring a snapshot.
+    pub fn changes_since(&self, after: i64) -> anyhow::Result<Option<(Vec<Todo>, Vec<i64>)>> {
+        let cursor = self.change_cursor()?;
+        let oldest: Option<i64> =
+            self.conn
+                .query_row("SELECT MIN(id) FROM audit_log", [], |row| row.get(0))?;
+        let purged = after < cursor && oldest.is_none_or(|oldest| oldest > after + 1);
+        if after > cursor || purged {
+            return Ok(None);
+        }
+        let todos = self
+            .conn
+            .prepare_cached(&format!(
+                "SELECT {TODO_COLUMNS} FROM todos WHERE archived_at IS NULL AND id IN (SELECT todo_id FROM audit_log WHERE id > ?1) ORDER BY id"
+            ))?
+            .query_map(params![after], todo_from_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        let removed = self
+            .conn
+            .prepare_cached(
+                "SELECT DISTINCT todo_id FROM audit_log WHERE id > ?1 AND todo_id IS NOT NULL AND todo_id NOT IN (SELECT id FROM todos WHERE archived_at IS NULL) ORDER BY todo_id",
+            )?
+            .query_map(params![after], |row| row.get(0))?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(Some((todos, removed)))
+    }
+}
+
+fn tombstone_from_row(row: &Row) -> rusqlite::Result<Tombstone> {
+    let deleted_at: String = row.get(4)?;
+    Ok(Tombstone {
+        id: row.get(0)?,
+        kind: row.get(1)?,
+        object_id: row.get(2)?,
+        title: row.get(3)?,
+        deleted_at: parse_datetime(&deleted_at),
+    })
+}
+
+fn todo_from_row(row: &Row) -> rusqlite::Result<Todo> {
+    let created_at: String = row.get(2)?;
+    let completed_at: Option<String> = row.get(3)?;
+    Ok(Todo {
+        id: row.get(0)?,
+        title: row.get(1)?,
+        created_at: parse_datetime(&created_at),
+        completed_at: completed_at.map(|value| parse_datetime(&value)),
+        version: row.get(4)?,
+        deadline: row.get(5)?,
+        goal_id: row.get(6)?,
+        location: row.get(7)?,
+        points: row.get(8)?,
+        status_id: row.get(9)?,
+        start_date: row.get(10)?,
+        habit: row.get(11)?,
+        floating: row.get(12)?,
+        someday: row.get(13)?,
+    })
+}
+
+fn reminder_from_row(row: &Row) -> rusqlite::Result<Reminder> {
+    let remind_at: String = row.get(2)?;
+    Ok(Reminder {
+        id: row.get(0)?,
+        todo_id: row.get(1)?,
+        remind_at: parse_datetime(&remind_at),
+        offset_minutes: row.get(3)?,
+    })
+}
+
+fn pomodoro_from_row(row: &Row) -> rusqlite::Result<Pomodoro> {
+    let started_at: String = row.get(2)?;
+    let paused_at: Option<String> = row.get(3)?;
+    let finished_at: Option<String> = row.get(5)?;
+    Ok(Pomodoro {
+        id: row.get(0)?,
+        todo_id: row.get(1)?,
+        started_at: parse_datetime(&started_at),
+        paused_at: paused_at.map(|value| parse_datetime(&value)),
+        paused_seconds: row.get(4)?,
+        finished_at: finished_at.map(|value| parse_datetime(&value)),
+    })
+}
+
+/// Title of the item a snapshot was taken of, `None` if it didn't exist.
+fn snapshot_title(snapshot: &Snapshot) -> Option<String> {
+    let row = &snapshot.rows.first()?.row;
+    Some(row.get("title")?.as_str()?.to_string())
+}
+
+/// A column value as stored in a tombstone.
+fn json_value(value: Value) -> serde_json::Value {
+    match value {
+        Value::Null => serde_json::Value::Null,
+        Value::Integer(value) => value.into(),
+        Value::Real(value) => value.into(),
+        Value::Text(value) => value.into(),
+        Value::Blob(value) => value.into(),
+    }
+}
+
+/// Undoes [`json_value`].
+fn sql_value(value: &serde_json::Value) -> Value {
+    match value {
+        serde_json::Value::Null => Value::Null,
+        serde_json::Value::Bool(value) => Value::Integer((*value).into()),
+        serde_json::Value::Number(number) => match number.as_i64() {
+            Some(value) => Value::Integer(value),
+            None => Value::Real(number.as_f64().unwrap_or_default()),
+        },
+        serde_json::Value::String(value) => Value::Text(value.clone()),
+        serde_json::Value::Array(bytes) => Value::Blob(
+            bytes
+                .iter()
+                .map(|byte| byte.as_u64().unwrap_or_default() as u8)
+                .collect(),
+        ),
+        serde_json::Value::Object(_) => Value::Text(value.to_string()),
+    }
+}
+
+/// Adds `field` to an update's changes as `[old, new]` if it differs.
+fn changed<T: PartialEq + Serialize>(
+    changes: &mut Map<String, serde_json::Value>,
+    field: &str,
+    old: T,
+    new: T,
+) {
+    if old != new {
+        changes.insert(field.to_string(), json!([old, new]));
+    }
+}
+
+fn parse_datetime(value: &str) -> DateTime<Utc> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|dt| dt.with_timezone(&Utc))
+        .unwrap_or_else(|_| Utc::now())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn schema(db: &Database) -> Vec<(String, String)> {
+        db.conn
+            .prepare("SELECT name, sql FROM sqlite_master WHERE sql IS NOT NULL ORDER BY name")
+            .unwrap()
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))
+            .unwrap()
+            .collect::<Result<_, _>>()
+            .unwrap()
+    }
+
+    fn version(db: &Database) -> usize {
+        db.conn
+            .query_row("PRAGMA user_version", [], |row| row.get(0))
+            .unwrap()
+    }
+
+    fn count(db: &Database, sql: &str) -> i64 {
+        db.conn.query_row(sql, [], |row| row.get(0)).unwrap()
+    }
+
+    #[test]
+    fn changes_fail_together_with_their_audit_entry() {
+        let db = Database::connect(":memory:", None).unwrap();
+        let todo = db.add_todo("Steuererklärung", None, None).unwrap();
+        db.conn
+            .execute_batch(
+                "CREATE TEMP TRIGGER audit_fails BEFORE INSERT ON audit_log \
+                 BEGIN SELECT RAISE(ABORT, 'audit log unavailable'); END",
+            )
+            .unwrap();
+
+        assert!(db.add_todo("Ohne Protokoll", None, None).is_err());
+        assert_eq!(count(&db, "SELECT COUNT(*) FROM todos"), 1);
+        assert!(db.complete_todo(todo.id).is_err());
+        assert!(db
+            .get_todo(todo.id)
+            .unwrap()
+            .unwrap()
+            .completed_at
+            .is_none());
+        assert!(db.conn.is_autocommit());
+    }
+
+    fn input(title: &str) -> TodoInput {
+        TodoInput {
+            title: title.to_string(),
+            deadline: None,
+            location: None,
+            points: None,
+            start: None,
+        }
+    }
+
+    #[test]
+    fn updates_only_the_version_they_were_made_on() {
+        let db = Database::connect(":memory:", None).unwrap();
+        let todo = db.add_todo("Fenster putzen", None, None).unwrap();
+        let edit = |title, version| {
+            db.update_todo(todo.id, version, &input(title), None, false, false, None)
+                .unwrap()
+        };
+
+        assert!(edit("Fenster putzen, innen", todo.version));
+        // Another edit made on the version both started from comes too late.
+        assert!(!edit("Fenster putzen, außen", todo.version));
+        let current = db.get_todo(todo.id).unwrap().unwrap();
+        assert_eq!(current.title, "Fenster putzen, innen");
+        assert_eq!(current.version, todo.version + 1);
+        assert_eq!(
+            count(
+                &db,
+                "SELECT COUNT(*) FROM audit_log WHERE action = 'update'"
+            ),
+            1
+        );
+    }
+
+    #[test]
+    fn restores_deleted_todos_within_the_window() {
+        let db = Database::connect(":memory:", None).unwrap();
+        let todo = db
+            .add_todo_with_note("Artikel lesen", "https://example.org/artikel")
+            .unwrap();
+        let goal = db.add_goal("Weiterbildung").unwrap();
+        db.set_goal(todo.id, goal).unwrap();
+        let before = serde_json::to_value(db.get_todo(todo.id).unwrap()).unwrap();
+
+        let deleted_at = Utc::now();
+        let action = db.delete_todo(todo.id).unwrap();
+        assert!(db.get_todo(todo.id).unwrap().is_none());
+        assert_eq!(db.list_notes().unwrap()[0].todo_id, None);
+
+        let window = deleted_at - chrono::TimeDelta::minutes(1);
+        let restored = db.restore(action, window).unwrap().unwrap();
+        assert_eq!(restored.object_id, todo.id);
+        let after = serde_json::to_value(db.get_todo(todo.id).unwrap()).unwrap();
+        assert_eq!(after, before);
+        assert_eq!(db.list_notes().unwrap()[0].todo_id, Some(todo.id));
+        assert!(db.restore(action, window).unwrap().is_none());
+
+        // Past the window, the tombstone no longer restores anything.
+        let action = db.delete_todo(todo.id).unwrap();
+        let closed = Utc::now() + chrono::TimeDelta::minutes(1);
+        assert!(db.restore(action, closed).unwrap().is_none());
+        assert!(db.get_todo(todo.id).unwrap().is_none());
+        assert_eq!(db.purge_tombstones(closed).unwrap(), 1);
+        assert!(db.restore(action, window).unwrap().is_none());
+    }
+
+    #[test]
+    fn retention_removes_only_what_is_past_the_window() {
+        let db = Database::connect(":memory:", None).unwrap();
+        let now = Utc::now();
+        let todo = |title, completed: Option<DateTime<Utc>>| {
+            let todo = db.add_todo(title, None, Some("@büro")).unwrap();
+            if let Some(completed) = completed {
+                db.complete_todo(todo.id).unwrap();
+                db.conn
+                    .execute(
+                        "UPDATE todos SET completed_at = ?1 WHERE id = ?2",
+                        params![completed.to_rfc3339(), todo.id],
+                    )
+                    .unwrap();
+            }
+            todo.id
+        };
+        let old = todo(
+            "Alte Rechnung",
+            now.checked_sub_months(chrono::Months::new(3)),
+        );
+        let recent = todo("Neue Rechnung", Some(now - chrono::TimeDelta::days(1)));
+        let open = todo("Offene Rechnung", None);
+        db.conn
+            .execute(
+                "INSERT INTO notes (title, body, created_at, todo_id) VALUES ('Beleg', 'Betrag: 120 €', ?1, ?2)",
+                params![now.to_rfc3339(), old],
+            )
+            .unwrap();
+        let audited = count(&db, "SELECT COUNT(*) FROM audit_log");
+        let title = |id| db.get_todo(id).unwrap().map(|todo| todo.title);
+
+        // 0 months keeps everything.
+        let keep = RetentionPolicy::default();
+        assert_eq!(db.apply_retention(&keep, now).unwrap(), 0);
+        assert_eq!(count(&db, "SELECT COUNT(*) FROM todos"), 3);
+        assert_eq!(count(&db, "SELECT COUNT(*) FROM notes"), 1);
+        assert_eq!(count(&db, "SELECT COUNT(*) FROM audit_log"), audited);
+
+        let mut policy = RetentionPolicy {
+            todos: 2,
+            anonymize: true,
+            audit: 1,
+            pomodoros: 0,
+        };
+        assert_eq!(db.apply_retention(&policy, now).unwrap(), 1);
+        let anonymized = db.get_todo(old).unwrap().unwrap();
+        assert_eq!(anonymized.title, ANONYMIZED_TITLE);
+        assert_eq!(anonymized.location, None);
+        assert_eq!(count(&db, "SELECT COUNT(*) FROM notes"), 0);
+        assert_eq!(
+            count(
+                &db,
+                &format!(
+                    "SELECT COUNT(*) FROM audit_log WHERE todo_id = {old} AND changes != '{{}}'"
+                )
+            ),
+            0
+        );
+        assert_eq!(title(recent).as_deref(), Some("Neue Rechnung"));
+        assert_eq!(title(open).as_deref(), Some("Offene Rechnung"));
+        // Only the anonymization was added; the entries inside the window stay.
+        assert_eq!(count(&db, "SELECT COUNT(*) FROM audit_log"), audited + 1);
+        assert_eq!(
+            count(
+                &db,
+                &format!(
+                    "SELECT COUNT(*) FROM audit_log WHERE todo_id = {open} AND changes != '{{}}'"
+                )
+            ),
+            1
+        );
+        assert_eq!(db.apply_retention(&policy, now).unwrap(), 0);
+
+        policy.anonymize = false;
+        assert_eq!(db.apply_retention(&policy, now).unwrap(), 1);
+        assert_eq!(title(old), None);
+        assert_eq!(title(recent).as_deref(), Some("Neue Rechnung"));
+        assert_eq!(title(open).as_deref(), Some("Offene Rechnung"));
+    }
+
+    #[test]
+    fn exports_only_listed_todos() {
+        let db = Database::connect(":memory:", None).unwrap();
+        let open = db.add_todo("Zeitung kündigen", None, None).unwrap();
+        let someday = db.add_todo("Segeln lernen", None, None).unwrap();
+        db.set_someday(someday.id, true).unwrap();
+        let linked = db.add_todo("Paket abholen", None, None).unwrap();
+        db.link_remote("github", "owner/repo", "7", linked.id)
+            .unwrap();
+        let done = db.add_todo("Müll rausbringen", None, None).unwrap();
+        db.complete_todo(done.id).unwrap();
+
+        let unlinked = db.unlinked_todos("github", "owner/repo").unwrap();
+        let ids: Vec<i64> = unlinked.iter().map(|todo| todo.id).collect();
+        assert_eq!(ids, [open.id]);
+    }
+
+    #[test]
+    fn migrations_undo_and_redo_cleanly() {
+        let db = Database::connect(":memory:", None).unwrap();
+        assert_eq!(version(&db), SCHEMA_VERSION);
+        let migrated = schema(&db);
+        let todo = db.add_todo("Keller aufräumen", None, None).unwrap();
+
+        db.migrate_to(0).unwrap();
+        assert_eq!(version(&db), 0);
+        assert!(schema(&db).len() < migrated.len());
+
+        db.migrate_to(SCHEMA_VERSION).unwrap();
+        assert_eq!(version(&db), SCHEMA_VERSION);
+        assert_eq!(schema(&db), migrated);
+        // The tables from before the first migration keep their rows.
+        assert_eq!(
+            db.get_todo(todo.id).unwrap().unwrap().title,
+            "Keller aufräumen"
+        );
+    }
+
+    #[test]
+    fn each_migration_can_be_undone() {
+        let db = Database::connect(":memory:", None).unwrap();
+        for version in (0..SCHEMA_VERSION).rev() {
+            let before = schema(&db);
+            db.migrate_to(version).unwrap();
+            db.migrate_to(version + 1).unwrap();
+            assert_eq!(schema(&db), before, "migration {}", version + 1);
+            db.migrate_to(version).unwrap();
+        }
+    }
 }
@@ -0,0 +1,158 @@
+//! The daily digest: a morning email listing the todos due today, the
+//! overdue ones and what was completed the day before, made from the
+//! `digest` mail template.
+
+use std::time::Duration;
+
+use anyhow::Result;
+use chrono::{DateTime, Local, NaiveDate, NaiveTime, TimeDelta, TimeZone, Utc};
+
+use crate::db::SharedDatabase;
+use crate::mail::{Mailer, Template};
+use crate::models::Todo;
+use crate::notify::MAX_ATTEMPTS;
+use crate::preferences;
+
+/// How often the job checks whether today's digest is due.
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// What a digest reports on.
+struct Digest {
+    date: NaiveDate,
+    due: Vec<Todo>,
+    overdue: Vec<Todo>,
+    completed: Vec<Todo>,
+}
+
+/// Mails the digest once a day, at `at` in the server's time zone.
+/// `public_url` is where the links in it point to.
+pub fn spawn(db: SharedDatabase, mailer: Mailer, at: NaiveTime, public_url: String) {
+    let public_url = public_url.trim_end_matches('/').to_string();
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(CHECK_INTERVAL);
+        loop {
+            ticker.tick().await;
+            if db.is_read_only() {
+                continue;
+            }
+            if let Err(err) = send_due(&db, &mailer, at, &public_url) {
+                eprintln!("sending the daily digest failed: {err:#}");
+            }
+        }
+    });
+}
+
+fn send_due(db: &SharedDatabase, mailer: &Mailer, at: NaiveTime, public_url: &str) -> Result<()> {
+    let now = Local::now();
+    if now.time() < at {
+        return Ok(());
+    }
+    let today = now.date_naive();
+    // Recorded like other notifications, so a restart doesn't send it twice.
+    let key = format!("digest:{today}");
+    let digest = {
+        let db = db.lock();
+        if !db.notification_pending(&key, "email", MAX_ATTEMPTS)? {
+            return Ok(());
+        }
+        let yesterday = today.pred_opt().expect("today is not the first day");
+        Digest {
+            date: today,
+            due: db.todos_due_on(today)?,
+            overdue: db.overdue_todos(today)?,
+            completed: db.completed_between(start_of(yesterday), start_of(today))?,
+        }
+    };
+
+    // With nothing to report it is recorded all the same, so the check
+    // isn't repeated every minute. Once queued, the outbox retries it.
+    let result = if digest.is_empty() {
+        Ok(())
+    } else {
+        let date = digest.date.format(preferences::date()).to_string();
+        mailer.queue(
+            Template::Digest,
+            &[
+                ("date", &date),
+                ("todos", &digest.render(public_url)),
+                ("url", public_url),
+            ],
+        )
+    };
+    let error = result.as_ref().err().map(|err| format!("{err:#}"));
+    db.lock()
+        .record_notification(&key, "email", error.as_deref())?;
+    result
+}
+
+/// Midnight at the start of `date`, in the server's time zone.
+fn start_of(date: NaiveDate) -> DateTime<Utc> {
+    let midnight = date.and_time(NaiveTime::MIN);
+    Local
+        .from_local_datetime(&midnight)
+        .earliest()
+        // Where a DST change skips midnight, the day starts an hour later.
+        .or_else(|| {
+            Local
+                .from_local_datetime(&(midnight + TimeDelta::hours(1)))
+                .earliest()
+        })
+        .map_or_else(|| midnight.and_utc(), |start| start.with_timezone(&Utc))
+}
+
+impl Digest {
+    fn is_empty(&self) -> bool {
+        self.due.is_empty() && self.overdue.is_empty() && self.completed.is_empty()
+    }
+
+    /// The lists for the template: one section per non-empty list, each
+    /// todo with a link to its page.
+    fn render(&self, public_url: &str) -> String {
+        let mut text = String::new();
+        section(&mut text, "Heute fällig", &self.due, public_url, |_| {
+            String::new()
+        });
+        section(
+            &mut text,
+            "Überfällig",
+            &self.overdue,
+            public_url,
+            |todo| {
+                todo.deadline
+                    .map(|deadline| {
+                        format!(" (fällig am {})", deadline.format(preferences::date()))
+                    })
+                    .unwrap_or_default()
+            },
+        );
+        section(
+            &mut text,
+            "Gestern erledigt",
+            &self.completed,
+            public_url,
+            |_| String::new(),
+        );
+        text
+    }
+}
+
+fn section(
+    text: &mut String,
+    heading: &str,
+    todos: &[Todo],
+    public_url: &str,
+    detail: impl Fn(&Todo) -> String,
+) {
+    if todos.is_empty() {
+        return;
+    }
+    text.push_str(&format!("\n{heading} ({}):\n", todos.len()));
+    for todo in todos {
+        text.push_str(&format!(
+            "- {}{}\n  {public_url}/todo/{}\n",
+            todo.title,
+            detail(todo),
+            todo.id
+        ));
+    }
+}
@@ -0,0 +1,102 @@
+//! Excel export: every todo as a row of a spreadsheet, with dates as date
+//! cells, so they sort and filter like dates.
+
+use axum::{
+    extract::State,
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    routing::get,
+    Router,
+};
+use chrono::Local;
+use rust_xlsxwriter::{Format, Workbook, XlsxError};
+
+use crate::db::TodoFilter;
+use crate::models::{Goal, Status, Todo};
+use crate::{error_status, preferences, statuses, AppState};
+
+const XLSX: &str = "application/vnd.openxmlformats-officedocument.spreadsheetml.sheet";
+
+const COLUMNS: &[&str] = &[
+    "Nr.", "Titel", "Status", "Ziel", "Ort", "Punkte", "Erstellt", "Beginn", "Fällig", "Erledigt",
+];
+
+pub fn router() -> Router<AppState> {
+    Router::new().route("/export.xlsx", get(xlsx))
+}
+
+/// All todos except archived ones, newest first.
+async fn xlsx(State(state): State<AppState>) -> Result<Response, StatusCode> {
+    let (todos, statuses, goals) = {
+        let db = state.db.lock();
+        (
+            db.find_todos(&TodoFilter::default())
+                .map_err(error_status)?,
+            db.statuses().map_err(error_status)?,
+            db.list_goals().map_err(error_status)?,
+        )
+    };
+    let body = workbook(&todos, &statuses, &goals).map_err(|err| error_status(err.into()))?;
+    let disposition = format!(
+        "attachment; filename=\"todos-{}.xlsx\"",
+        Local::now().format("%Y-%m-%d")
+    );
+    Ok((
+        [
+            (header::CONTENT_TYPE, XLSX.to_string()),
+            (header::CONTENT_DISPOSITION, disposition),
+        ],
+        body,
+    )
+        .into_response())
+}
+
+fn workbook(todos: &[Todo], statuses: &[Status], goals: &[Goal]) -> Result<Vec<u8>, XlsxError> {
+    let bold = Format::new().set_bold();
+    let date = Format::new().set_num_format(preferences::excel_date());
+    let datetime = Format::new().set_num_format(preferences::excel_date_time());
+
+    let mut workbook = Workbook::new();
+    let sheet = workbook.add_worksheet();
+    sheet.set_name("Todos")?;
+    sheet.write_row_with_format(0, 0, COLUMNS.iter().copied(), &bold)?;
+    for (row, todo) in (1..).zip(todos) {
+        sheet.write_number(row, 0, todo.id as f64)?;
+        sheet.write_string(row, 1, &todo.title)?;
+        sheet.write_string(row, 2, statuses::label(todo, statuses))?;
+        if let Some(goal) = goals.iter().find(|goal| Some(goal.id) == todo.goal_id) {
+            sheet.write_string(row, 3, &goal.title)?;
+        }
+        if let Some(location) = &todo.location {
+            sheet.write_string(row, 4, location)?;
+        }
+        if let Some(points) = todo.points {
+            sheet.write_number(row, 5, points)?;
+        }
+        // Excel has no time zones; times are written as they read here.
+        let created_at = todo.created_at.with_timezone(&Local).naive_local();
+        sheet.write_datetime_with_format(row, 6, created_at, &datetime)?;
+        if let Some(start) = todo.start_date {
+            sheet.write_date_with_format(row, 7, start, &date)?;
+        }
+        if let Some(deadline) = todo.deadline {
+            sheet.write_date_with_format(row, 8, deadline, &date)?;
+        }
+        if let Some(completed_at) = todo.completed_at {
+            let completed_at = completed_at.with_timezone(&Local).naive_local();
+            sheet.write_datetime_with_format(row, 9, completed_at, &datetime)?;
+        }
+    }
+    let last_row = todos.len() as u32;
+    sheet.autofilter(0, 0, last_row, COLUMNS.len() as u16 - 1)?;
+    sheet.set_freeze_panes(1, 0)?;
+    sheet.autofit();
+    // Autofit measures dates by their serial number, not as shown.
+    for column in [6, 9] {
+        sheet.set_column_width(column, 16)?;
+    }
+    for column in [7, 8] {
+        sheet.set_column_width(column, 11)?;
+    }
+    workbook.save_to_buffer()
+}
@@ -0,0 +1,101 @@
+//! Surfaces an operator can switch off at runtime from the admin page.
+//!
+//! Every feature is on until switched off; the choice is stored in the
+//! `feature_flags` table and survives restarts.
+
+use std::collections::HashSet;
+use std::sync::{Arc, PoisonError, RwLock};
+
+use anyhow::Result;
+use axum::{
+    extract::{Request, State},
+    http::StatusCode,
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+
+use crate::db::{Database, SharedDatabase};
+use crate::{base_path, AppState};
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Feature {
+    Api,
+    Webhooks,
+}
+
+impl Feature {
+    pub const ALL: [Feature; 2] = [Feature::Api, Feature::Webhooks];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Feature::Api => "api",
+            Feature::Webhooks => "webhooks",
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            Feature::Api => "JSON-API unter /api, auch für Client und TUI",
+            Feature::Webhooks => "Push-Webhooks unter /hooks",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|feature| feature.name() == name)
+    }
+
+    fn path_prefix(self) -> &'static str {
+        match self {
+            Feature::Api => "/api/",
+            Feature::Webhooks => "/hooks/",
+        }
+    }
+}
+
+/// The switched-off features, cached so requests don't hit the database.
+#[derive(Clone)]
+pub struct Features(Arc<RwLock<HashSet<Feature>>>);
+
+impl Features {
+    pub fn load(db: &Database) -> Result<Self> {
+        let disabled = db
+            .feature_flags()?
+            .into_iter()
+            .filter(|(_, enabled)| !enabled)
+            .filter_map(|(name, _)| Feature::from_name(&name))
+            .collect();
+        Ok(Self(Arc::new(RwLock::new(disabled))))
+    }
+
+    pub fn is_enabled(&self, feature: Feature) -> bool {
+        !self
+            .0
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .contains(&feature)
+    }
+
+    pub fn set(&self, db: &SharedDatabase, feature: Feature, enabled: bool) -> Result<()> {
+        db.lock().set_feature_flag(feature.name(), enabled)?;
+        let mut disabled = self.0.write().unwrap_or_else(PoisonError::into_inner);
+        if enabled {
+            disabled.remove(&feature);
+        } else {
+            disabled.insert(feature);
+        }
+        Ok(())
+    }
+}
+
+/// Answers 404 for routes of a switched-off feature, as if they didn't exist.
+pub async fn gate(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    let path = request.uri().path();
+    let path = path.strip_prefix(base_path()).unwrap_or(path);
+    let blocked = Feature::ALL.into_iter().any(|feature| {
+        path.starts_with(feature.path_prefix()) && !state.features.is_enabled(feature)
+    });
+    if blocked {
+        return StatusCode::NOT_FOUND.into_response();
+    }
+    next.run(request).await
+}
@@ -0,0 +1,206 @@
+//! Gantt chart: open todos as bars from their start to their deadline, drawn
+//! as an SVG on the server, with arrows from each todo to those that follow it.
+//!
+//! A todo without a start date begins on the day it was created; one with a
+//! start date but no deadline takes up that single day.
+
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use axum::{extract::State, http::StatusCode, response::Html, routing::get, Router};
+use chrono::{Datelike, Local, NaiveDate};
+
+use crate::models::{Goal, RelationKind, Todo};
+use crate::{base_path, error_status, html_escape, page_start, preferences, AppState, PAGE_END};
+
+/// Width of the column holding the titles.
+const LABEL_WIDTH: i64 = 220;
+/// Width the days are spread over, unless that makes them too narrow or wide.
+const CHART_WIDTH: i64 = 720;
+const MIN_DAY_WIDTH: i64 = 4;
+const MAX_DAY_WIDTH: i64 = 32;
+const HEADER_HEIGHT: i64 = 24;
+const ROW_HEIGHT: i64 = 28;
+const BAR_HEIGHT: i64 = 16;
+/// Longer titles are cut off so they fit the label column.
+const LABEL_CHARS: usize = 28;
+const BAR_COLOR: &str = "#2563eb";
+const OVERDUE_COLOR: &str = "#dc2626";
+
+pub fn router() -> Router<AppState> {
+    Router::new().route("/gantt", get(show))
+}
+
+/// A todo's bar, from the start of `begin` to the end of `end`.
+struct Bar<'a> {
+    todo: &'a Todo,
+    begin: NaiveDate,
+    end: NaiveDate,
+}
+
+async fn show(State(state): State<AppState>) -> Result<Html<String>, StatusCode> {
+    let db = state.db.lock();
+    let todos = db.list_todos(false).map_err(error_status)?;
+    let goals = db.list_goals().map_err(error_status)?;
+    let follows = db
+        .relation_pairs(RelationKind::Follows)
+        .map_err(error_status)?;
+    Ok(Html(render(
+        &todos,
+        &goals,
+        &follows,
+        Local::now().date_naive(),
+    )))
+}
+
+fn bars(todos: &[Todo]) -> Vec<Bar<'_>> {
+    let mut bars: Vec<Bar> = todos
+        .iter()
+        .filter_map(|todo| {
+            let (begin, end) = match (todo.start_date, todo.deadline) {
+                (Some(start), Some(deadline)) => (start, deadline),
+                (Some(start), None) => (start, start),
+                (None, Some(deadline)) => (todo.created_at.date_naive().min(deadline), deadline),
+                (None, None) => return None,
+            };
+            Some(Bar { todo, begin, end })
+        })
+        .collect();
+    bars.sort_by_key(|bar| (bar.begin, bar.end, bar.todo.id));
+    bars
+}
+
+fn render(todos: &[Todo], goals: &[Goal], follows: &[(i64, i64)], today: NaiveDate) -> String {
+    let base = base_path();
+    let mut body = page_start();
+    body.push_str(
+        "    <h1>Gantt</h1>\n    <div class=\"subtitle\">Offene Todos vom Beginn (sonst der Erstellung) bis zur Deadline; Pfeile führen zu Todos, die auf ein anderes folgen.</div>\n",
+    );
+    let bars = bars(todos);
+    if bars.is_empty() {
+        body.push_str(
+            "    <div class=\"subtitle\">Keine offenen Todos mit Beginn oder Deadline.</div>\n",
+        );
+    } else {
+        body.push_str(&format!(
+            "    <div class=\"gantt\">\n{}    </div>\n",
+            chart(&bars, goals, follows, today)
+        ));
+    }
+    body.push_str(&format!(
+        "    <a class=\"back\" href=\"{base}/\">Zurück zur Liste</a>\n"
+    ));
+    body.push_str(PAGE_END);
+    body
+}
+
+fn chart(bars: &[Bar], goals: &[Goal], follows: &[(i64, i64)], today: NaiveDate) -> String {
+    let base = base_path();
+    let first = bars
+        .iter()
+        .map(|bar| bar.begin)
+        .min()
+        .unwrap_or(today)
+        .min(today);
+    let last = bars
+        .iter()
+        .map(|bar| bar.end)
+        .max()
+        .unwrap_or(today)
+        .max(today);
+    let days = (last - first).num_days() + 1;
+    let day_width = (CHART_WIDTH / days).clamp(MIN_DAY_WIDTH, MAX_DAY_WIDTH);
+    let x = |date: NaiveDate| LABEL_WIDTH + (date - first).num_days() * day_width;
+    let width = x(last) + day_width + 8;
+    let height = HEADER_HEIGHT + bars.len() as i64 * ROW_HEIGHT + 8;
+    let bottom = height - 8;
+
+    let mut svg = format!(
+        r##"<svg xmlns="http://www.w3.org/2000/svg" width="{width}" height="{height}" viewBox="0 0 {width} {height}" font-size="12" font-family="inherit">
+<defs><marker id="arrow" viewBox="0 0 8 8" refX="8" refY="4" markerWidth="8" markerHeight="8" orient="auto"><path d="M0,0 L8,4 L0,8 z" fill="#64748b" /></marker></defs>
+"##
+    );
+
+    // Weeks are labeled while there is room for it, months otherwise.
+    let weekly = day_width * 7 >= 40;
+    for date in first.iter_days().take(days as usize) {
+        let (line, label) = if weekly {
+            (
+                date.weekday() == preferences::get().week_start,
+                date.format(preferences::day()).to_string(),
+            )
+        } else {
+            (
+                date.day() == 1,
+                date.format(preferences::month()).to_string(),
+            )
+        };
+        if line {
+            let _ = writeln!(
+                svg,
+                r##"<line x1="{x}" y1="{HEADER_HEIGHT}" x2="{x}" y2="{bottom}" stroke="#e2e8f0" /><text x="{text}" y="16" fill="#64748b">{label}</text>"##,
+                x = x(date),
+                text = x(date) + 2,
+            );
+        }
+    }
+    let _ = writeln!(
+        svg,
+        r##"<line x1="{x}" y1="{HEADER_HEIGHT}" x2="{x}" y2="{bottom}" stroke="{OVERDUE_COLOR}" stroke-dasharray="4 3"><title>Heute</title></line>"##,
+        x = x(today) + day_width / 2,
+    );
+
+    let mut rows = HashMap::new();
+    for (row, bar) in bars.iter().enumerate() {
+        let top = HEADER_HEIGHT + row as i64 * ROW_HEIGHT;
+        rows.insert(bar.todo.id, (row as i64, bar));
+        let mut title: String = bar.todo.title.chars().take(LABEL_CHARS).collect();
+        if title.len() < bar.todo.title.len() {
+            title.push('…');
+        }
+        let color = if bar.todo.deadline.is_some_and(|deadline| deadline < today) {
+            OVERDUE_COLOR
+        } else {
+            goals
+                .iter()
+                .find(|goal| Some(goal.id) == bar.todo.goal_id)
+                .and_then(|goal| goal.color.as_deref())
+                .unwrap_or(BAR_COLOR)
+        };
+        let _ = writeln!(
+            svg,
+            r##"<a href="{base}/todo/{id}"><text x="8" y="{text}" fill="#0f172a">{title}</text><rect x="{left}" y="{bar_top}" width="{bar_width}" height="{BAR_HEIGHT}" rx="3" fill="{color}"><title>{full} ({begin} – {end})</title></rect></a>"##,
+            id = bar.todo.id,
+            text = top + ROW_HEIGHT / 2 + 4,
+            title = html_escape(&title),
+            left = x(bar.begin),
+            bar_top = top + (ROW_HEIGHT - BAR_HEIGHT) / 2,
+            bar_width = x(bar.end) + day_width - x(bar.begin),
+            full = html_escape(&bar.todo.title),
+            begin = bar.begin.format(preferences::date()),
+            end = bar.end.format(preferences::date()),
+        );
+    }
+
+    // From the end of each todo to the start of the one following it.
+    let middle = |row: i64| HEADER_HEIGHT + row * ROW_HEIGHT + ROW_HEIGHT / 2;
+    for (todo_id, related_id) in follows {
+        let (Some((to_row, to)), Some((from_row, from))) =
+            (rows.get(todo_id), rows.get(related_id))
+        else {
+            continue;
+        };
+        let start = x(from.end) + day_width;
+        let _ = writeln!(
+            svg,
+            r##"<path d="M{start} {from_y} H{bend} V{to_y} H{end}" fill="none" stroke="#64748b" marker-end="url(#arrow)" />"##,
+            from_y = middle(*from_row),
+            bend = start + 6,
+            to_y = middle(*to_row),
+            end = x(to.begin),
+        );
+    }
+
+    svg.push_str("</svg>\n");
+    svg
+}
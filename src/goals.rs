@@ -0,0 +1,306 @@
+//! Longer-term goals that todos can be linked to, with their progress. A
+//! goal's color and icon mark its todos on the list and the board.
+
+use axum::{
+    extract::{Form, Path, Query, State},
+    http::{header, StatusCode},
+    response::{Html, IntoResponse, Redirect, Response},
+    routing::{get, post},
+    Router,
+};
+use serde::Deserialize;
+
+use crate::db::Database;
+use crate::models::Goal;
+use crate::undo::{self, UndoQuery};
+use crate::validate::{self, FieldError, Invalid};
+use crate::{
+    base_path, error_notice, error_status, html_escape, invalid_attr, page_start, AppState, IdForm,
+    PAGE_END,
+};
+
+/// Colors offered on a goal's page: label and value.
+const COLORS: &[(&str, &str)] = &[
+    ("Rot", "#dc2626"),
+    ("Orange", "#ea580c"),
+    ("Gelb", "#ca8a04"),
+    ("Grün", "#16a34a"),
+    ("Türkis", "#0d9488"),
+    ("Blau", "#2563eb"),
+    ("Violett", "#7c3aed"),
+    ("Pink", "#db2777"),
+];
+
+#[derive(Deserialize)]
+struct GoalForm {
+    title: String,
+}
+
+#[derive(Deserialize)]
+struct EditForm {
+    title: String,
+    #[serde(default)]
+    color: String,
+    #[serde(default)]
+    icon: String,
+}
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/goals", get(show).post(add_goal))
+        .route("/goals/:id", get(show_goal).post(update_goal))
+        .route("/goals/delete", post(delete_goal))
+        .route("/goals.css", get(stylesheet))
+}
+
+/// Each goal's color for its `goal-<id>` class, linked from every page
+/// like the main stylesheet.
+async fn stylesheet(State(state): State<AppState>) -> Result<Response, StatusCode> {
+    let goals = state.db.lock().list_goals().map_err(error_status)?;
+    let mut css = String::new();
+    for goal in goals {
+        // Colors are validated as `#rrggbb`, so nothing else gets in.
+        if let Some(color) = goal.color {
+            css.push_str(&format!(
+                ".goal-{} {{ color: {color}; accent-color: {color}; }}\n",
+                goal.id
+            ));
+        }
+    }
+    Ok((
+        [
+            (header::CONTENT_TYPE, "text/css; charset=utf-8"),
+            // Colors change with the goals; always ask again.
+            (header::CACHE_CONTROL, "no-cache"),
+        ],
+        css,
+    )
+        .into_response())
+}
+
+async fn show(
+    State(state): State<AppState>,
+    Query(query): Query<UndoQuery>,
+) -> Result<Html<String>, StatusCode> {
+    let db = state.db.lock();
+    let notice = match query.undo {
+        Some(action_id) => undo::notice(&db, state.undo_window, action_id).map_err(error_status)?,
+        None => None,
+    };
+    let body = render(&db, "", None, notice.as_deref()).map_err(error_status)?;
+    Ok(Html(body))
+}
+
+async fn add_goal(
+    State(state): State<AppState>,
+    Form(form): Form<GoalForm>,
+) -> Result<Response, StatusCode> {
+    let db = state.db.lock();
+    let title = match validate::title(&form.title) {
+        Ok(title) => title,
+        Err(error) => {
+            let body = render(&db, &form.title, Some(&error), None).map_err(error_status)?;
+            return Ok((StatusCode::UNPROCESSABLE_ENTITY, Html(body)).into_response());
+        }
+    };
+    db.add_goal(&title).map_err(error_status)?;
+    Ok(Redirect::to(&format!("{}/goals", base_path())).into_response())
+}
+
+async fn show_goal(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<Html<String>, StatusCode> {
+    let goals = state.db.lock().list_goals().map_err(error_status)?;
+    let goal = goals
+        .into_iter()
+        .find(|goal| goal.id == id)
+        .ok_or(StatusCode::NOT_FOUND)?;
+    let form = EditForm {
+        title: goal.title,
+        color: goal.color.unwrap_or_default(),
+        icon: goal.icon.unwrap_or_default(),
+    };
+    Ok(Html(render_edit(id, &form, None)))
+}
+
+async fn update_goal(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    Form(form): Form<EditForm>,
+) -> Result<Response, StatusCode> {
+    let db = state.db.lock();
+    let title = validate::title(&form.title);
+    let color = validate::color(&form.color);
+    let icon = validate::icon(&form.icon);
+    let (title, color, icon) = match (title, color, icon) {
+        (Ok(title), Ok(color), Ok(icon)) => (title, color, icon),
+        (title, color, icon) => {
+            let errors = [title.err(), color.err(), icon.err()];
+            let invalid = Invalid(errors.into_iter().flatten().collect());
+            let body = render_edit(id, &form, Some(&invalid));
+            return Ok((StatusCode::UNPROCESSABLE_ENTITY, Html(body)).into_response());
+        }
+    };
+    db.update_goal(id, &title, color.as_deref(), icon.as_deref())
+        .map_err(|_| StatusCode::NOT_FOUND)?;
+    Ok(Redirect::to(&format!("{}/goals", base_path())).into_response())
+}
+
+async fn delete_goal(
+    State(state): State<AppState>,
+    Form(form): Form<IdForm>,
+) -> Result<Response, StatusCode> {
+    let action_id = state.db.lock().delete_goal(form.id).map_err(error_status)?;
+    Ok(Redirect::to(&format!("{}/goals?undo={action_id}", base_path())).into_response())
+}
+
+/// Lists every goal with a progress bar over its linked todos; `title` and
+/// `error` refill the add form after a rejected submission. `notice` is HTML
+/// shown above the form.
+fn render(
+    db: &Database,
+    title: &str,
+    error: Option<&FieldError>,
+    notice: Option<&str>,
+) -> anyhow::Result<String> {
+    let base = base_path();
+    let mut body = page_start();
+    body.push_str(
+        "    <h1>Ziele</h1>\n    <div class=\"subtitle\">Verknüpfe Todos auf ihrer Bearbeitungsseite mit einem Ziel.</div>\n",
+    );
+    if let Some(notice) = notice {
+        body.push_str(&format!("    <div class=\"notice\">{notice}</div>\n"));
+    }
+    if let Some(error) = error {
+        body.push_str(&format!(
+            "    <div class=\"notice\">{}</div>\n",
+            html_escape(&error.message)
+        ));
+    }
+    body.push_str(&format!(
+        r#"    <form method="post" action="{base}/goals">
+      <input type="text" name="title" placeholder="Neues Ziel" value="{title}" maxlength="{max}" required{invalid} />
+      <button type="submit">Hinzufügen</button>
+    </form>
+    <div class="todo-list">
+"#,
+        title = html_escape(title),
+        max = validate::MAX_TITLE_CHARS,
+        invalid = if error.is_some() {
+            r#" class="invalid" aria-invalid="true""#
+        } else {
+            ""
+        },
+    ));
+
+    for goal in db.list_goals()? {
+        let mut todos = String::new();
+        for todo in db.goal_todos(goal.id)? {
+            let done = if todo.completed_at.is_some() {
+                " ✓"
+            } else {
+                ""
+            };
+            todos.push_str(&format!(
+                "<div class=\"time\"><a href=\"{base}/todo/{id}\">{title}</a>{done}</div>\n",
+                id = todo.id,
+                title = html_escape(&todo.title),
+            ));
+        }
+        body.push_str(&format!(
+            r#"<div class="todo">
+  <div class="meta">
+    <div class="title">{title}</div>
+    <progress class="goal-{id}" max="{max}" value="{done}"></progress>
+    <div class="time">{done} von {total} Todos erledigt</div>
+{todos}  </div>
+  <div class="actions">
+    <a class="back" href="{base}/goals/{id}">Bearbeiten</a>
+    <form method="post" action="{base}/goals/delete">
+      <input type="hidden" name="id" value="{id}" />
+      <button class="delete" type="submit">Löschen</button>
+    </form>
+  </div>
+</div>
+"#,
+            id = goal.id,
+            title = badge(&goal),
+            done = goal.todos_done,
+            total = goal.todos_total,
+            // An empty bar for a goal without todos rather than max="0".
+            max = goal.todos_total.max(1),
+        ));
+    }
+
+    body.push_str(&format!(
+        "    </div>\n    <a class=\"back\" href=\"{base}/\">Zurück zur Liste</a>\n"
+    ));
+    body.push_str(PAGE_END);
+    Ok(body)
+}
+
+/// A goal's name with its icon, in its color, for the lists and the board.
+pub fn badge(goal: &Goal) -> String {
+    let icon = goal
+        .icon
+        .as_deref()
+        .map(|icon| format!("{} ", html_escape(icon)))
+        .unwrap_or_default();
+    format!(
+        "<span class=\"goal goal-{}\">{icon}{}</span>",
+        goal.id,
+        html_escape(&goal.title)
+    )
+}
+
+/// The page to rename a goal and pick its color and icon.
+fn render_edit(id: i64, form: &EditForm, invalid: Option<&Invalid>) -> String {
+    let base = base_path();
+    let mut colors = String::from("        <option value=\"\">Keine Farbe</option>\n");
+    let mut known = false;
+    for (label, value) in COLORS {
+        let selected = if form.color.eq_ignore_ascii_case(value) {
+            known = true;
+            " selected"
+        } else {
+            ""
+        };
+        colors.push_str(&format!(
+            "        <option value=\"{value}\"{selected}>{label}</option>\n"
+        ));
+    }
+    // A color set some other way, e.g. before the choice changed.
+    if !known && !form.color.is_empty() {
+        colors.push_str(&format!(
+            "        <option value=\"{color}\" selected>{color}</option>\n",
+            color = html_escape(&form.color)
+        ));
+    }
+
+    let mut body = page_start();
+    body.push_str("    <h1>Ziel bearbeiten</h1>\n");
+    if let Some(invalid) = invalid {
+        body.push_str(&error_notice(invalid));
+    }
+    body.push_str(&format!(
+        r#"    <form class="stacked" method="post" action="{base}/goals/{id}">
+      <input type="text" name="title" value="{title}" maxlength="{max}" required{title_invalid} />
+      <select name="color"{color_invalid}>
+{colors}      </select>
+      <input type="text" name="icon" placeholder="Symbol, z. B. 🏠" value="{icon}" maxlength="{max_icon}"{icon_invalid} />
+      <button type="submit">Speichern</button>
+    </form>
+    <a class="back" href="{base}/goals">Zurück zu den Zielen</a>
+"#,
+        title = html_escape(&form.title),
+        max = validate::MAX_TITLE_CHARS,
+        title_invalid = invalid_attr(invalid, "title"),
+        color_invalid = invalid_attr(invalid, "color"),
+        icon = html_escape(&form.icon),
+        max_icon = validate::MAX_ICON_CHARS,
+        icon_invalid = invalid_attr(invalid, "icon"),
+    ));
+    body.push_str(PAGE_END);
+    body
+}
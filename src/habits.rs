@@ -0,0 +1,104 @@
+//! Habits: todos that recur daily. Done one day, a habit is open again the
+//! next morning; the days it was done make up its streak.
+
+use std::collections::BTreeSet;
+use std::time::Duration;
+
+use chrono::{Days, Local, NaiveDate, TimeZone, Utc};
+
+use crate::db::SharedDatabase;
+use crate::preferences;
+
+/// How often the job looks for habits to reopen.
+const CHECK_INTERVAL: Duration = Duration::from_secs(10 * 60);
+/// Weeks shown on the calendar, including the current one.
+const WEEKS: u64 = 12;
+
+/// Reopens the habits done before today, now and then every few minutes.
+pub fn spawn(db: SharedDatabase) {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(CHECK_INTERVAL);
+        loop {
+            ticker.tick().await;
+            if db.is_read_only() {
+                continue;
+            }
+            let midnight = Local::now().date_naive().and_time(Default::default());
+            let since = Local
+                .from_local_datetime(&midnight)
+                .earliest()
+                .map_or_else(|| midnight.and_utc(), |since| since.with_timezone(&Utc));
+            let db = db.clone();
+            let job = tokio::task::spawn_blocking(move || db.lock().reopen_habits(since));
+            match job.await {
+                Ok(Ok(_)) => {}
+                Ok(Err(err)) => eprintln!("reopening habits failed: {err:#}"),
+                Err(err) => eprintln!("reopening habits panicked: {err}"),
+            }
+        }
+    });
+}
+
+/// Days in a row up to today, or up to yesterday while today is still open.
+fn current_streak(days: &BTreeSet<NaiveDate>, today: NaiveDate) -> usize {
+    let mut day = if days.contains(&today) {
+        today
+    } else {
+        match today.pred_opt() {
+            Some(yesterday) => yesterday,
+            None => return 0,
+        }
+    };
+    let mut streak = 0;
+    while days.contains(&day) {
+        streak += 1;
+        match day.pred_opt() {
+            Some(previous) => day = previous,
+            None => break,
+        }
+    }
+    streak
+}
+
+fn longest_streak(days: &BTreeSet<NaiveDate>) -> usize {
+    let mut longest = 0;
+    let mut run = 0;
+    let mut previous: Option<NaiveDate> = None;
+    for day in days {
+        run = match previous {
+            Some(previous) if previous.succ_opt() == Some(*day) => run + 1,
+            _ => 1,
+        };
+        longest = longest.max(run);
+        previous = Some(*day);
+    }
+    longest
+}
+
+/// The streaks of a habit and a calendar of the last weeks, one column per
+/// week and a filled square for each day it was done.
+pub fn section(days: &[NaiveDate], today: NaiveDate) -> String {
+    let days: BTreeSet<NaiveDate> = days.iter().copied().collect();
+    let first = preferences::week_of(today) - Days::new((WEEKS - 1) * 7);
+    let mut cells = String::new();
+    for day in first.iter_days().take_while(|day| *day <= today) {
+        let class = if days.contains(&day) {
+            " class=\"done\""
+        } else {
+            ""
+        };
+        cells.push_str(&format!(
+            "<span{class} title=\"{date}\"></span>",
+            date = day.format(preferences::date()),
+        ));
+    }
+    format!(
+        r#"    <h2>Gewohnheit</h2>
+    <div class="subtitle">Serie: {current} Tage · längste: {longest} Tage · insgesamt {total} Tage</div>
+    <div class="heatmap">{cells}</div>
+"#,
+        current = current_streak(&days, today),
+        longest = longest_streak(&days),
+        total = days.len(),
+    )
+}
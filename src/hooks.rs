@@ -0,0 +1,268 @@
+use axum::{
+    body::Bytes,
+    extract::{Query, State},
+    http::{header, HeaderMap, StatusCode},
+    response::{IntoResponse, Response},
+    routing::post,
+    Json, Router,
+};
+use hmac::{Hmac, Mac};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::validate;
+use crate::{error_status, AppState};
+
+/// Words that, directly followed by `td#<id>`, complete the referenced todo.
+const CLOSING_KEYWORDS: &[&str] = &[
+    "close", "closes", "closed", "fix", "fixes", "fixed", "resolve", "resolves", "resolved",
+];
+
+/// The subset of a push payload shared by GitHub, Gitea/Forgejo and GitLab.
+#[derive(Deserialize)]
+struct PushEvent {
+    #[serde(default)]
+    commits: Vec<Commit>,
+    repository: Option<Repository>,
+    project: Option<Repository>,
+}
+
+#[derive(Deserialize)]
+struct Repository {
+    full_name: Option<String>,
+    path_with_namespace: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Commit {
+    id: String,
+    message: String,
+    url: Option<String>,
+}
+
+/// A todo filed by a script or home automation.
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct Ingest {
+    title: String,
+    #[serde(default)]
+    deadline: Option<String>,
+    #[serde(default)]
+    location: Option<String>,
+}
+
+/// For tools that can't set an `Authorization` header.
+#[derive(Deserialize)]
+struct TokenQuery {
+    token: Option<String>,
+}
+
+#[derive(Serialize, Default)]
+struct PushResult {
+    completed: Vec<i64>,
+    skipped: Vec<i64>,
+}
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/hooks/push", post(push))
+        .route("/hooks/ingest", post(ingest))
+}
+
+/// Completes the todos that pushed commits close. Off without
+/// `--webhook-secret`, as anyone could complete todos otherwise.
+async fn push(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Json<PushResult>, StatusCode> {
+    let secret = state
+        .webhook_secret
+        .as_deref()
+        .ok_or(StatusCode::NOT_FOUND)?;
+    if !verify_signature(secret, &headers, &body) {
+        return Err(StatusCode::UNAUTHORIZED);
+    }
+
+    let event: PushEvent = serde_json::from_slice(&body).map_err(|_| StatusCode::BAD_REQUEST)?;
+    let repository = event
+        .repository
+        .or(event.project)
+        .and_then(|repo| repo.full_name.or(repo.path_with_namespace))
+        .unwrap_or_default();
+
+    let db = state.db.lock();
+    let mut result = PushResult::default();
+    for commit in &event.commits {
+        for id in referenced_todo_ids(&commit.message) {
+            let todo = db.get_todo(id).map_err(error_status)?;
+            match todo {
+                Some(todo) if todo.completed_at.is_none() => {
+                    db.complete_by_commit(id, &repository, &commit.id, commit.url.as_deref())
+                        .map_err(error_status)?;
+                    result.completed.push(id);
+                }
+                _ => result.skipped.push(id),
+            }
+        }
+    }
+
+    Ok(Json(result))
+}
+
+/// Files a todo for holders of the ingest token, given as
+/// `Authorization: Bearer <token>` or `?token=`.
+async fn ingest(
+    State(state): State<AppState>,
+    Query(query): Query<TokenQuery>,
+    headers: HeaderMap,
+    body: Bytes,
+) -> Result<Response, StatusCode> {
+    let expected = state.ingest_token.as_deref().ok_or(StatusCode::NOT_FOUND)?;
+    let token = headers
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .or(query.token.as_deref());
+    // Comparing digests keeps the time taken independent of the token.
+    if token.map(Sha256::digest) != Some(Sha256::digest(expected)) {
+        return Err(StatusCode::UNAUTHORIZED);
+    }
+
+    let input: Ingest = serde_json::from_slice(&body).map_err(|_| StatusCode::BAD_REQUEST)?;
+    let input = match validate::todo(&validate::TodoFields {
+        title: &input.title,
+        deadline: input.deadline.as_deref().unwrap_or_default(),
+        location: input.location.as_deref().unwrap_or_default(),
+        ..Default::default()
+    }) {
+        Ok(input) => input,
+        Err(invalid) => return Ok(invalid.into_response()),
+    };
+    let todo = state
+        .db
+        .lock()
+        .add_todo(&input.title, input.deadline, input.location.as_deref())
+        .map_err(error_status)?;
+    Ok((StatusCode::CREATED, Json(todo)).into_response())
+}
+
+/// Accepts GitHub (`X-Hub-Signature-256`) and Gitea (`X-Gitea-Signature`)
+/// HMAC signatures as well as GitLab's plain `X-Gitlab-Token`.
+fn verify_signature(secret: &str, headers: &HeaderMap, body: &[u8]) -> bool {
+    let header = |name: &str| headers.get(name).and_then(|value| value.to_str().ok());
+
+    if let Some(token) = header("x-gitlab-token") {
+        // Comparing digests keeps the time taken independent of the secret.
+        return Sha256::digest(token) == Sha256::digest(secret);
+    }
+
+    let signature = header("x-hub-signature-256")
+        .and_then(|value| value.strip_prefix("sha256="))
+        .or_else(|| header("x-gitea-signature"));
+    let Some(signature) = signature.and_then(|value| hex::decode(value).ok()) else {
+        return false;
+    };
+
+    let Ok(mut mac) = Hmac::<Sha256>::new_from_slice(secret.as_bytes()) else {
+        return false;
+    };
+    mac.update(body);
+    mac.verify_slice(&signature).is_ok()
+}
+
+/// Extracts todo ids from phrases like `closes td#42` or `fixes td#1, td#2`.
+fn referenced_todo_ids(message: &str) -> Vec<i64> {
+    let mut ids = Vec::new();
+    let mut after_keyword = false;
+
+    for word in message.split_whitespace() {
+        let word = word.to_lowercase();
+        let word = word.trim_matches(|c: char| matches!(c, ',' | '.' | ':' | ';' | '(' | ')'));
+
+        if let Some(id) = word
+            .strip_prefix("td#")
+            .and_then(|id| id.parse::<i64>().ok())
+        {
+            if after_keyword {
+                if !ids.contains(&id) {
+                    ids.push(id);
+                }
+                continue;
+            }
+        } else if word == "and" && after_keyword {
+            continue;
+        }
+
+        after_keyword = CLOSING_KEYWORDS.contains(&word);
+    }
+
+    ids
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::http::HeaderValue;
+
+    use super::*;
+
+    const SECRET: &str = "It's a Secret to Everybody";
+    const BODY: &[u8] = b"Hello, World!";
+    /// GitHub's example for validating webhook deliveries.
+    const SIGNATURE: &str = "757107ea0eb2509fc211221cce984b8a37570b6d7586c22c46f4379c8b043e17";
+
+    fn headers(name: &'static str, value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(name, HeaderValue::from_str(value).unwrap());
+        headers
+    }
+
+    #[test]
+    fn accepts_github_and_gitea_signatures() {
+        let github = headers("x-hub-signature-256", &format!("sha256={SIGNATURE}"));
+        assert!(verify_signature(SECRET, &github, BODY));
+        let gitea = headers("x-gitea-signature", SIGNATURE);
+        assert!(verify_signature(SECRET, &gitea, BODY));
+    }
+
+    #[test]
+    fn rejects_bad_signatures() {
+        let github = headers("x-hub-signature-256", &format!("sha256={SIGNATURE}"));
+        assert!(!verify_signature("another secret", &github, BODY));
+        assert!(!verify_signature(SECRET, &github, b"Hello, World?"));
+        // GitHub's header needs its prefix.
+        let unprefixed = headers("x-hub-signature-256", SIGNATURE);
+        assert!(!verify_signature(SECRET, &unprefixed, BODY));
+        let garbled = headers("x-gitea-signature", "not hex");
+        assert!(!verify_signature(SECRET, &garbled, BODY));
+        assert!(!verify_signature(SECRET, &HeaderMap::new(), BODY));
+    }
+
+    #[test]
+    fn compares_gitlab_tokens() {
+        assert!(verify_signature(
+            SECRET,
+            &headers("x-gitlab-token", SECRET),
+            BODY
+        ));
+        assert!(!verify_signature(
+            SECRET,
+            &headers("x-gitlab-token", "guess"),
+            BODY
+        ));
+        // A wrong token isn't saved by a signature next to it.
+        let mut both = headers("x-gitlab-token", "guess");
+        both.insert("x-gitea-signature", HeaderValue::from_static(SIGNATURE));
+        assert!(!verify_signature(SECRET, &both, BODY));
+    }
+
+    #[test]
+    fn finds_referenced_todos() {
+        assert_eq!(
+            referenced_todo_ids("Fixes td#1, td#2 and td#3. Closes (td#2)"),
+            [1, 2, 3]
+        );
+        assert_eq!(referenced_todo_ids("See td#4, resolved: td#5"), [5]);
+        assert!(referenced_todo_ids("fixes td#x, closes #6").is_empty());
+    }
+}
@@ -0,0 +1,289 @@
+//! `Idempotency-Key` support for API writes: a retried request gets the
+//! stored answer of the first one instead of running again.
+
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+use axum::{
+    body::{Body, Bytes},
+    extract::{Request, State},
+    http::{HeaderMap, HeaderValue, Method, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+    Json,
+};
+use serde_json::json;
+use sha2::{Digest, Sha256};
+
+use crate::base_path;
+
+/// How long a response is remembered.
+const RETENTION: Duration = Duration::from_secs(24 * 60 * 60);
+
+/// Most keys remembered at once; the oldest is forgotten first.
+const MAX_KEYS: usize = 10_000;
+
+/// Longest accepted key.
+const MAX_KEY_LEN: usize = 255;
+
+/// Response headers about the connection or the body's framing, which the
+/// replay gets anew.
+const NOT_REPLAYED: &[&str] = &[
+    "connection",
+    "content-length",
+    "keep-alive",
+    "proxy-connection",
+    "te",
+    "trailer",
+    "transfer-encoding",
+    "upgrade",
+];
+
+#[derive(Clone, Default)]
+pub struct Idempotency(Arc<Mutex<HashMap<String, Entry>>>);
+
+struct Entry {
+    /// Method, path and body of the first request, so a key reused for
+    /// something else is noticed.
+    fingerprint: [u8; 32],
+    created: Instant,
+    /// `None` while the first request is still running.
+    response: Option<Stored>,
+}
+
+struct Stored {
+    status: StatusCode,
+    headers: HeaderMap,
+    body: Bytes,
+}
+
+enum Lookup {
+    New,
+    Replay(Response),
+    Conflict(StatusCode, &'static str),
+}
+
+impl Idempotency {
+    fn begin(&self, key: &str, fingerprint: [u8; 32]) -> Lookup {
+        let now = Instant::now();
+        let mut entries = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        entries.retain(|_, entry| now - entry.created < RETENTION);
+
+        match entries.get(key) {
+            Some(entry) if entry.fingerprint != fingerprint => {
+                return Lookup::Conflict(
+                    StatusCode::UNPROCESSABLE_ENTITY,
+                    "idempotency key was used for a different request",
+                )
+            }
+            Some(Entry { response: None, .. }) => {
+                return Lookup::Conflict(
+                    StatusCode::CONFLICT,
+                    "a request with this idempotency key is still running",
+                )
+            }
+            Some(Entry {
+                response: Some(stored),
+                ..
+            }) => return Lookup::Replay(stored.replay()),
+            None => {}
+        }
+
+        if entries.len() >= MAX_KEYS {
+            let oldest = entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.created)
+                .map(|(key, _)| key.clone());
+            if let Some(oldest) = oldest {
+                entries.remove(&oldest);
+            }
+        }
+        entries.insert(
+            key.to_string(),
+            Entry {
+                fingerprint,
+                created: now,
+                response: None,
+            },
+        );
+        Lookup::New
+    }
+
+    /// Remembers the answer, or forgets the key if the request may be retried.
+    fn finish(&self, key: &str, stored: Option<Stored>) {
+        let mut entries = self.0.lock().unwrap_or_else(PoisonError::into_inner);
+        match stored {
+            Some(stored) => {
+                if let Some(entry) = entries.get_mut(key) {
+                    entry.response = Some(stored);
+                }
+            }
+            None => {
+                entries.remove(key);
+            }
+        }
+    }
+}
+
+impl Stored {
+    fn replay(&self) -> Response {
+        let mut response = (self.status, self.body.clone()).into_response();
+        let headers = response.headers_mut();
+        headers.extend(self.headers.clone());
+        headers.insert("idempotent-replayed", HeaderValue::from_static("true"));
+        response
+    }
+}
+
+/// Runs a `POST` under `/api` with an `Idempotency-Key` at most once per key;
+/// repeats get the first answer. Server errors aren't remembered, so those
+/// requests can be retried.
+pub async fn remember(
+    State(idempotency): State<Idempotency>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let path = request.uri().path();
+    let path = path.strip_prefix(base_path()).unwrap_or(path);
+    if request.method() != Method::POST || !path.starts_with("/api/") {
+        return next.run(request).await;
+    }
+    let Some(key) = request.headers().get("idempotency-key") else {
+        return next.run(request).await;
+    };
+    let Some(key) = key
+        .to_str()
+        .ok()
+        .filter(|key| !key.is_empty() && key.len() <= MAX_KEY_LEN)
+        .map(str::to_string)
+    else {
+        return StatusCode::BAD_REQUEST.into_response();
+    };
+
+    let (parts, body) = request.into_parts();
+    // The body limit further out stops oversized bodies here.
+    let Ok(body) = axum::body::to_bytes(body, usize::MAX).await else {
+        return StatusCode::PAYLOAD_TOO_LARGE.into_response();
+    };
+    let fingerprint: [u8; 32] = Sha256::new()
+        .chain_update(parts.method.as_str())
+        .chain_update([0])
+        .chain_update(parts.uri.path())
+        .chain_update([0])
+        .chain_update(&body)
+        .finalize()
+        .into();
+
+    match idempotency.begin(&key, fingerprint) {
+        Lookup::New => {}
+        Lookup::Replay(response) => return response,
+        Lookup::Conflict(status, error) => {
+            return (status, Json(json!({ "error": error }))).into_response()
+        }
+    }
+
+    let response = next.run(Request::from_parts(parts, Body::from(body))).await;
+    let status = response.status();
+    if status.is_server_error() {
+        idempotency.finish(&key, None);
+        return response;
+    }
+
+    let (parts, body) = response.into_parts();
+    let Ok(body) = axum::body::to_bytes(body, usize::MAX).await else {
+        idempotency.finish(&key, None);
+        return StatusCode::INTERNAL_SERVER_ERROR.into_response();
+    };
+    let mut headers = parts.headers.clone();
+    for name in NOT_REPLAYED {
+        headers.remove(*name);
+    }
+    idempotency.finish(
+        &key,
+        Some(Stored {
+            status,
+            headers,
+            body: body.clone(),
+        }),
+    );
+    Response::from_parts(parts, Body::from(body))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use axum::{http::header, middleware, routing::post, Router};
+
+    use super::*;
+
+    /// Serves a `POST /api/todos` that counts its calls, behind
+    /// [`remember`]; returns the server's address and the count.
+    async fn serve() -> (String, Arc<AtomicUsize>) {
+        let calls = Arc::new(AtomicUsize::new(0));
+        let counter = calls.clone();
+        let app = Router::new()
+            .route(
+                "/api/todos",
+                post(move |body: Bytes| async move {
+                    let id = counter.fetch_add(1, Ordering::SeqCst) + 1;
+                    let headers = [
+                        (header::LOCATION, format!("/api/todos/{id}")),
+                        (header::CONTENT_TYPE, "application/json".to_string()),
+                        (
+                            header::HeaderName::from_static("undo-action"),
+                            id.to_string(),
+                        ),
+                    ];
+                    (StatusCode::CREATED, headers, body)
+                }),
+            )
+            .layer(middleware::from_fn_with_state(
+                Idempotency::default(),
+                remember,
+            ));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}/api/todos", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await });
+        (url, calls)
+    }
+
+    async fn post_todo(url: &str, key: &str, body: &'static str) -> reqwest::Response {
+        reqwest::Client::new()
+            .post(url)
+            .header("idempotency-key", key)
+            .body(body)
+            .send()
+            .await
+            .unwrap()
+    }
+
+    #[tokio::test]
+    async fn replays_the_first_response() {
+        let (url, calls) = serve().await;
+        let first = post_todo(&url, "a", r#"{"title": "Milch kaufen"}"#).await;
+        let retry = post_todo(&url, "a", r#"{"title": "Milch kaufen"}"#).await;
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+
+        assert_eq!(retry.status(), StatusCode::CREATED);
+        for name in ["location", "content-type", "undo-action"] {
+            assert_eq!(retry.headers()[name], first.headers()[name], "{name}");
+        }
+        assert_eq!(retry.headers()["idempotent-replayed"], "true");
+        assert_eq!(retry.text().await.unwrap(), first.text().await.unwrap());
+
+        // Another key runs again.
+        let other = post_todo(&url, "b", r#"{"title": "Milch kaufen"}"#).await;
+        assert_eq!(other.headers()["location"], "/api/todos/2");
+    }
+
+    #[tokio::test]
+    async fn rejects_a_key_reused_for_another_request() {
+        let (url, calls) = serve().await;
+        post_todo(&url, "a", r#"{"title": "Milch kaufen"}"#).await;
+        let reused = post_todo(&url, "a", r#"{"title": "Brot kaufen"}"#).await;
+        assert_eq!(reused.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(calls.load(Ordering::SeqCst), 1);
+    }
+}
@@ -0,0 +1,193 @@
+//! Bulk import: pasted lines become todos, one each, written in quick-add
+//! syntax. The lines are shown as a preview first and then added together,
+//! or not at all.
+
+use axum::{
+    extract::{Form, State},
+    http::StatusCode,
+    response::{Html, IntoResponse, Redirect, Response},
+    routing::get,
+    Router,
+};
+use chrono::Local;
+use serde::Deserialize;
+
+use crate::models::Goal;
+use crate::quickadd::{self, QuickAdd};
+use crate::validate::FieldError;
+use crate::{
+    base_path, error_status, goals, html_escape, page_start, preferences, AppState, PAGE_END,
+};
+
+/// More lines than this are rather a file to import than a paste.
+const MAX_LINES: usize = 500;
+
+#[derive(Deserialize)]
+struct ImportForm {
+    text: String,
+    /// Set by the button under the preview; otherwise only previews.
+    #[serde(default)]
+    confirm: bool,
+}
+
+pub fn router() -> Router<AppState> {
+    Router::new().route("/import", get(show).post(import))
+}
+
+async fn show() -> Html<String> {
+    Html(render("", None, &[]))
+}
+
+async fn import(
+    State(state): State<AppState>,
+    Form(form): Form<ImportForm>,
+) -> Result<Response, StatusCode> {
+    let db = state.db.lock();
+    let goals = db.list_goals().map_err(error_status)?;
+    let lines = parse(&form.text, &goals);
+    let valid =
+        !lines.is_empty() && lines.len() <= MAX_LINES && lines.iter().all(|(_, line)| line.is_ok());
+    if !form.confirm || !valid {
+        let status = if form.confirm {
+            StatusCode::UNPROCESSABLE_ENTITY
+        } else {
+            StatusCode::OK
+        };
+        let body = render(&form.text, Some(&lines), &goals);
+        return Ok((status, Html(body)).into_response());
+    }
+
+    let tx = db.transaction().map_err(error_status)?;
+    for todo in lines.iter().filter_map(|(_, line)| line.as_ref().ok()) {
+        let added = db
+            .add_todo(&todo.title, todo.deadline, todo.location.as_deref())
+            .map_err(error_status)?;
+        if let Some(goal_id) = todo.goal_id {
+            db.set_goal(added.id, goal_id).map_err(error_status)?;
+        }
+    }
+    tx.commit().map_err(|err| error_status(err.into()))?;
+    Ok(Redirect::to(&format!("{}/", base_path())).into_response())
+}
+
+/// Removes list markers such as `-`, `*`, `- [ ]` or `1.` that notes tend
+/// to start lines with.
+fn strip_marker(line: &str) -> &str {
+    let line = line.trim();
+    for marker in ["- [ ] ", "* [ ] ", "- ", "* ", "• "] {
+        if let Some(rest) = line.strip_prefix(marker) {
+            return rest.trim_start();
+        }
+    }
+    let digits = line.len() - line.trim_start_matches(|c: char| c.is_ascii_digit()).len();
+    if digits > 0 {
+        if let Some(rest) = line[digits..]
+            .strip_prefix(". ")
+            .or_else(|| line[digits..].strip_prefix(") "))
+        {
+            return rest.trim_start();
+        }
+    }
+    line
+}
+
+/// Each non-empty line with its text, parsed as quick-add input.
+fn parse(text: &str, goals: &[Goal]) -> Vec<(String, Result<QuickAdd, FieldError>)> {
+    let today = Local::now().date_naive();
+    text.lines()
+        .map(strip_marker)
+        .filter(|line| !line.is_empty())
+        .map(|line| (line.to_string(), quickadd::parse(line, today, goals)))
+        .collect()
+}
+
+/// The import form, with `text` in it, and the preview of its lines once
+/// they were parsed.
+fn render(
+    text: &str,
+    lines: Option<&[(String, Result<QuickAdd, FieldError>)]>,
+    goals: &[Goal],
+) -> String {
+    let base = base_path();
+    let mut body = page_start();
+    body.push_str(&format!(
+        r#"    <h1>Importieren</h1>
+    <div class="subtitle">Eine Zeile pro Todo, z. B. aus Notizen oder einem Protokoll. Aufzählungszeichen werden entfernt; wie beim schnellen Erfassen setzt <code>@freitag</code> die Deadline, <code>@büro</code> den Ort und <code>//Ziel</code> das Ziel.</div>
+    <form class="stacked" method="post" action="{base}/import">
+      <textarea name="text" rows="12" required>{text}</textarea>
+      <button type="submit">Vorschau</button>
+    </form>
+"#,
+        text = html_escape(text),
+    ));
+
+    if let Some(lines) = lines {
+        let errors = lines.iter().filter(|(_, line)| line.is_err()).count();
+        if lines.is_empty() {
+            body.push_str("    <div class=\"notice\">Keine Zeilen gefunden.</div>\n");
+        } else if lines.len() > MAX_LINES {
+            body.push_str(&format!(
+                "    <div class=\"notice\">Höchstens {MAX_LINES} Zeilen auf einmal, hier sind es {}.</div>\n",
+                lines.len()
+            ));
+        } else if errors > 0 {
+            body.push_str(&format!(
+                "    <div class=\"notice\">{errors} von {total} Zeilen enthalten Fehler.</div>\n",
+                total = lines.len(),
+            ));
+        }
+
+        body.push_str("    <div class=\"todo-list\">\n");
+        for (number, (text, line)) in lines.iter().enumerate() {
+            let (title, details) = match line {
+                Ok(todo) => {
+                    let mut details = Vec::new();
+                    if let Some(deadline) = todo.deadline {
+                        details.push(format!(
+                            "fällig am {}",
+                            deadline.format(preferences::date())
+                        ));
+                    }
+                    if let Some(location) = &todo.location {
+                        details.push(html_escape(location));
+                    }
+                    if let Some(goal) = goals.iter().find(|goal| Some(goal.id) == todo.goal_id) {
+                        details.push(goals::badge(goal));
+                    }
+                    (html_escape(&todo.title), details.join(" · "))
+                }
+                Err(error) => (html_escape(text), html_escape(&error.message)),
+            };
+            body.push_str(&format!(
+                r#"<div class="todo">
+  <div class="meta">
+    <div class="title">{number}. {title}</div>
+    <div class="time">{details}</div>
+  </div>
+</div>
+"#,
+                number = number + 1,
+            ));
+        }
+        body.push_str("    </div>\n");
+
+        if !lines.is_empty() && lines.len() <= MAX_LINES && errors == 0 {
+            body.push_str(&format!(
+                r#"    <form method="post" action="{base}/import">
+      <input type="hidden" name="text" value="{text}" />
+      <input type="hidden" name="confirm" value="true" />
+      <button type="submit">{count} Todos anlegen</button>
+    </form>
+"#,
+                text = html_escape(text),
+                count = lines.len(),
+            ));
+        }
+    }
+
+    body.push_str(&format!(
+        "    <a class=\"back\" href=\"{base}/\">Zurück zur Liste</a>\n"
+    ));
+    body.push_str(PAGE_END);
+    body
+}
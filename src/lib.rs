@@ -0,0 +1,10 @@
+//! Data types of simpletodo and, with the `client` feature, a typed client
+//! for its JSON API. Other Rust programs can depend on just that part:
+//!
+//! ```toml
+//! simpletodo = { version = "0.1", default-features = false, features = ["client"] }
+//! ```
+
+#[cfg(feature = "client")]
+pub mod client;
+pub mod models;
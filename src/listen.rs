@@ -0,0 +1,160 @@
+//! Addresses the server accepts connections on.
+
+use std::fmt;
+use std::net::SocketAddr;
+use std::os::fd::{FromRawFd, IntoRawFd, RawFd};
+use std::os::unix::fs::FileTypeExt;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use anyhow::{Context, Result};
+use axum::Router;
+use axum_server::tls_rustls::RustlsConfig;
+use hyper_util::rt::TokioIo;
+use hyper_util::service::TowerToHyperService;
+use tokio::net::UnixListener;
+
+/// First file descriptor passed by systemd socket activation.
+const SD_LISTEN_FDS_START: RawFd = 3;
+
+/// One listener, written as `http://ADDR:PORT`, `https://ADDR:PORT` or
+/// `unix:/path/to.sock`, or a socket inherited from systemd.
+#[derive(Clone)]
+pub enum Listener {
+    Http(SocketAddr),
+    Https(SocketAddr),
+    Unix(PathBuf),
+    /// Already bound and listening; TCP sockets serve HTTPS when TLS is set up.
+    Inherited(RawFd),
+}
+
+impl FromStr for Listener {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let addr = |addr: &str| {
+            addr.parse::<SocketAddr>()
+                .map_err(|_| format!("\"{addr}\" is not an address like 127.0.0.1:5876"))
+        };
+
+        if let Some(rest) = value.strip_prefix("http://") {
+            Ok(Listener::Http(addr(rest)?))
+        } else if let Some(rest) = value.strip_prefix("https://") {
+            Ok(Listener::Https(addr(rest)?))
+        } else if let Some(path) = value.strip_prefix("unix:") {
+            Ok(Listener::Unix(PathBuf::from(path)))
+        } else {
+            Err("expected http://ADDR:PORT, https://ADDR:PORT or unix:PATH".to_string())
+        }
+    }
+}
+
+impl fmt::Display for Listener {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Listener::Http(addr) => write!(f, "http://{addr}"),
+            Listener::Https(addr) => write!(f, "https://{addr}"),
+            Listener::Unix(path) => write!(f, "unix:{}", path.display()),
+            Listener::Inherited(fd) => write!(f, "systemd socket (fd {fd})"),
+        }
+    }
+}
+
+impl Listener {
+    /// Accepts connections until an error stops the listener.
+    pub async fn serve(self, app: Router, tls: Option<RustlsConfig>) -> Result<()> {
+        match self {
+            Listener::Http(addr) => {
+                let listener = tokio::net::TcpListener::bind(addr)
+                    .await
+                    .with_context(|| format!("binding {addr}"))?;
+                axum::serve(
+                    listener,
+                    app.into_make_service_with_connect_info::<SocketAddr>(),
+                )
+                .await?;
+            }
+            Listener::Https(addr) => {
+                let tls = tls.context("no TLS configuration for an https listener")?;
+                axum_server::bind_rustls(addr, tls)
+                    .serve(app.into_make_service_with_connect_info::<SocketAddr>())
+                    .await
+                    .with_context(|| format!("serving {addr}"))?;
+            }
+            Listener::Unix(path) => {
+                // A socket left behind by an earlier run would make bind fail.
+                if std::fs::metadata(&path).is_ok_and(|meta| meta.file_type().is_socket()) {
+                    std::fs::remove_file(&path)?;
+                }
+                let listener = UnixListener::bind(&path)
+                    .with_context(|| format!("binding {}", path.display()))?;
+                serve_unix(listener, app).await?;
+            }
+            Listener::Inherited(fd) => serve_inherited(fd, app, tls).await?,
+        }
+        Ok(())
+    }
+}
+
+/// Sockets handed over by systemd socket activation (`LISTEN_FDS`), if any.
+pub fn from_systemd() -> Vec<Listener> {
+    let for_us = std::env::var("LISTEN_PID")
+        .ok()
+        .and_then(|pid| pid.parse::<u32>().ok())
+        == Some(std::process::id());
+    let count = std::env::var("LISTEN_FDS")
+        .ok()
+        .and_then(|count| count.parse::<RawFd>().ok())
+        .filter(|_| for_us)
+        .unwrap_or(0);
+
+    (SD_LISTEN_FDS_START..SD_LISTEN_FDS_START + count)
+        .map(Listener::Inherited)
+        .collect()
+}
+
+async fn serve_inherited(fd: RawFd, app: Router, tls: Option<RustlsConfig>) -> Result<()> {
+    // SAFETY: systemd passes these descriptors to this process alone, and each
+    // one is wrapped exactly once.
+    let listener = unsafe { std::net::TcpListener::from_raw_fd(fd) };
+    // Only TCP sockets have an IP address; anything else is a Unix socket.
+    if listener.local_addr().is_err() {
+        // SAFETY: ownership moves back out of the TCP wrapper unchanged.
+        let listener =
+            unsafe { std::os::unix::net::UnixListener::from_raw_fd(listener.into_raw_fd()) };
+        listener.set_nonblocking(true)?;
+        return serve_unix(UnixListener::from_std(listener)?, app).await;
+    }
+
+    listener.set_nonblocking(true)?;
+    match tls {
+        Some(tls) => {
+            axum_server::from_tcp_rustls(listener, tls)
+                .serve(app.into_make_service_with_connect_info::<SocketAddr>())
+                .await?
+        }
+        None => {
+            axum::serve(
+                tokio::net::TcpListener::from_std(listener)?,
+                app.into_make_service_with_connect_info::<SocketAddr>(),
+            )
+            .await?
+        }
+    }
+    Ok(())
+}
+
+async fn serve_unix(listener: UnixListener, app: Router) -> Result<()> {
+    loop {
+        let (socket, _) = listener.accept().await?;
+        let service = TowerToHyperService::new(app.clone());
+        tokio::spawn(async move {
+            let connection = hyper::server::conn::http1::Builder::new()
+                .serve_connection(TokioIo::new(socket), service)
+                .with_upgrades();
+            if let Err(err) = connection.await {
+                eprintln!("unix socket connection failed: {err}");
+            }
+        });
+    }
+}
@@ -0,0 +1,82 @@
+//! Periodic `VACUUM`/`ANALYZE` to keep long-lived databases compact.
+
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+
+use crate::db::SharedDatabase;
+
+/// Runs maintenance on the shared connection and remembers the last run for
+/// the admin page.
+#[derive(Clone)]
+pub struct Maintenance {
+    db: SharedDatabase,
+    last: Arc<Mutex<Option<Run>>>,
+}
+
+#[derive(Clone, Copy)]
+pub struct Run {
+    pub finished_at: DateTime<Utc>,
+    pub took: Duration,
+    pub size_before: u64,
+    pub size_after: u64,
+}
+
+impl Maintenance {
+    pub fn new(db: SharedDatabase) -> Self {
+        Self {
+            db,
+            last: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    /// Blocks every other database access while it runs.
+    pub fn run(&self) -> Result<Run> {
+        let started = Instant::now();
+        let (size_before, size_after) = {
+            let db = self.db.lock();
+            let before = db.file_size()?;
+            db.maintain()?;
+            (before, db.file_size()?)
+        };
+
+        let run = Run {
+            finished_at: Utc::now(),
+            took: started.elapsed(),
+            size_before,
+            size_after,
+        };
+        *self.last.lock().unwrap_or_else(PoisonError::into_inner) = Some(run);
+        Ok(run)
+    }
+
+    pub fn last(&self) -> Option<Run> {
+        *self.last.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Runs maintenance every `interval`, starting one interval from now.
+    pub fn spawn(&self, interval: Duration) {
+        let maintenance = self.clone();
+        tokio::spawn(async move {
+            let mut ticker =
+                tokio::time::interval_at(tokio::time::Instant::now() + interval, interval);
+            loop {
+                ticker.tick().await;
+                if maintenance.db.is_read_only() {
+                    continue;
+                }
+                let job = maintenance.clone();
+                match tokio::task::spawn_blocking(move || job.run()).await {
+                    Ok(Ok(run)) => println!(
+                        "database maintenance took {:.1?}, {} -> {} bytes",
+                        run.took, run.size_before, run.size_after
+                    ),
+                    Ok(Err(err)) => eprintln!("database maintenance failed: {err:#}"),
+                    Err(err) => eprintln!("database maintenance panicked: {err}"),
+                }
+            }
+        });
+    }
+}
@@ -1,10 +1,214 @@
-use chrono::{DateTime, Utc};
-use serde::Serialize;
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, Deserialize)]
 pub struct Todo {
     pub id: i64,
     pub title: String,
     pub created_at: DateTime<Utc>,
     pub completed_at: Option<DateTime<Utc>>,
+    pub deadline: Option<NaiveDate>,
+    /// Bumped on every edit, so concurrent edits can be detected.
+    pub version: i64,
+    pub goal_id: Option<i64>,
+    /// Free text such as `@home`, or coordinates like `52.52,13.40`.
+    pub location: Option<String>,
+    /// Estimated effort in story points.
+    pub points: Option<u32>,
+    /// Workflow status; completed exactly when the status means done.
+    #[serde(default)]
+    pub status_id: Option<i64>,
+    /// Day work on the todo is planned to begin.
+    #[serde(default)]
+    pub start_date: Option<NaiveDate>,
+    /// Recurs daily: reopened every morning, with each day it was done kept.
+    #[serde(default)]
+    pub habit: bool,
+    /// Never overdue: a passed deadline moves to today each morning.
+    #[serde(default)]
+    pub floating: bool,
+    /// Put off to someday/maybe: kept off the list and every deadline view
+    /// until made active again.
+    #[serde(default)]
+    pub someday: bool,
+}
+
+/// A step of the workflow, such as `In Arbeit`; a column on the board.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Status {
+    pub id: i64,
+    pub name: String,
+    /// Todos with this status count as completed.
+    pub done: bool,
+}
+
+/// A longer-term objective that todos contribute to.
+#[derive(Debug, Serialize)]
+pub struct Goal {
+    pub id: i64,
+    pub title: String,
+    pub created_at: DateTime<Utc>,
+    pub todos_done: i64,
+    pub todos_total: i64,
+    /// `#rrggbb`, marking the goal and its todos.
+    pub color: Option<String>,
+    /// An emoji or a few characters shown before the title.
+    pub icon: Option<String>,
+}
+
+/// A captured idea that isn't a task (yet).
+#[derive(Debug, Serialize)]
+pub struct Note {
+    pub id: i64,
+    pub title: String,
+    /// Markdown.
+    pub body: String,
+    pub created_at: DateTime<Utc>,
+    /// The todo the note was turned into, if any.
+    pub todo_id: Option<i64>,
+}
+
+/// How one todo relates to another, read from the todo it was added on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RelationKind {
+    RelatesTo,
+    DuplicateOf,
+    Follows,
+}
+
+impl RelationKind {
+    pub const ALL: [RelationKind; 3] = [
+        RelationKind::RelatesTo,
+        RelationKind::DuplicateOf,
+        RelationKind::Follows,
+    ];
+
+    /// How the kind is stored and submitted.
+    pub fn as_str(self) -> &'static str {
+        match self {
+            RelationKind::RelatesTo => "relates-to",
+            RelationKind::DuplicateOf => "duplicate-of",
+            RelationKind::Follows => "follows",
+        }
+    }
+
+    pub fn parse(value: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|kind| kind.as_str() == value)
+    }
+
+    /// Label on the todo the relation was added on.
+    pub fn label(self) -> &'static str {
+        match self {
+            RelationKind::RelatesTo => "Verwandt mit",
+            RelationKind::DuplicateOf => "Duplikat von",
+            RelationKind::Follows => "Folgt auf",
+        }
+    }
+
+    /// Label on the other todo, looking back.
+    pub fn inverse_label(self) -> &'static str {
+        match self {
+            RelationKind::RelatesTo => "Verwandt mit",
+            RelationKind::DuplicateOf => "Hat Duplikat",
+            RelationKind::Follows => "Gefolgt von",
+        }
+    }
+}
+
+/// A relation as seen from one todo.
+#[derive(Debug)]
+pub struct Relation {
+    pub kind: RelationKind,
+    /// Whether it was added on this todo rather than on `other`.
+    pub outgoing: bool,
+    pub other: Todo,
+}
+
+/// A point in time at which to be reminded of a todo.
+#[derive(Debug, Serialize)]
+pub struct Reminder {
+    pub id: i64,
+    pub todo_id: i64,
+    pub remind_at: DateTime<Utc>,
+    /// Minutes before the deadline, for reminders that follow it.
+    pub offset_minutes: Option<i64>,
+}
+
+/// One pomodoro worked on a todo; running until `finished_at` is set.
+#[derive(Debug, Serialize)]
+pub struct Pomodoro {
+    pub id: i64,
+    pub todo_id: i64,
+    pub started_at: DateTime<Utc>,
+    /// Set while the pomodoro is paused.
+    pub paused_at: Option<DateTime<Utc>>,
+    /// Time spent in earlier pauses.
+    pub paused_seconds: i64,
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+/// One change to a todo in the audit log.
+#[derive(Debug, Serialize)]
+pub struct AuditEntry {
+    pub id: i64,
+    pub at: DateTime<Utc>,
+    /// How the change came in: `web`, `api`, `hook`, `sync` or `system`.
+    pub actor: String,
+    /// `create`, `update`, `complete`, `reopen`, `status`, `archive`,
+    /// `delete` or `restore`.
+    pub action: String,
+    /// Kept after the todo is deleted.
+    pub todo_id: Option<i64>,
+    /// The fields set, or for updates `{"field": [old, new]}`.
+    pub changes: serde_json::Value,
+}
+
+/// Something deleted that can still be brought back for a while.
+#[derive(Debug, Serialize)]
+pub struct Tombstone {
+    pub id: i64,
+    /// `todo`, `note` or `goal`.
+    pub kind: String,
+    /// Id of the todo, note or goal, which it gets back when restored.
+    pub object_id: i64,
+    pub title: String,
+    pub deleted_at: DateTime<Utc>,
+}
+
+/// Where to deliver Web Push messages to one browser.
+#[derive(Debug)]
+pub struct PushSubscription {
+    /// URL at the browser vendor's push service.
+    pub endpoint: String,
+    /// The browser's public key and authentication secret, base64url.
+    pub p256dh: String,
+    pub auth: String,
+}
+
+/// A mail waiting in the queue.
+#[derive(Debug)]
+pub struct QueuedMail {
+    pub id: i64,
+    pub recipient: String,
+    pub subject: String,
+    pub body: String,
+    /// Failed attempts so far.
+    pub attempts: u32,
+}
+
+#[derive(Debug)]
+pub struct MailQueueStatus {
+    /// Unsent mail that will be tried (again).
+    pub waiting: u32,
+    /// Unsent mail that failed too often to be tried again.
+    pub given_up: u32,
+    /// Why the newest unsent mail that failed did.
+    pub last_error: Option<String>,
+}
+
+#[derive(Debug)]
+pub struct OAuthToken {
+    pub access_token: String,
+    pub refresh_token: Option<String>,
+    pub expires_at: DateTime<Utc>,
 }
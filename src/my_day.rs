@@ -0,0 +1,249 @@
+//! My Day: a handful of todos picked for today, on a page that shows nothing
+//! else. Picks are stored per day; the next time the page is opened, the
+//! ones left open can be carried over or dropped.
+
+use axum::{
+    extract::{Form, State},
+    http::StatusCode,
+    response::{Html, IntoResponse, Redirect, Response},
+    routing::{get, post},
+    Router,
+};
+use chrono::{Local, NaiveDate};
+use serde::Deserialize;
+
+use crate::db::Database;
+use crate::models::Todo;
+use crate::validate::{FieldError, Invalid};
+use crate::{
+    base_path, error_notice, error_status, html_escape, page_start, preferences, AppState, IdForm,
+    PAGE_END,
+};
+
+/// More todos than this make it a list again rather than a focus.
+const MAX_FOCUS: usize = 7;
+
+#[derive(Deserialize)]
+struct ReviewForm {
+    /// `1` to pick the leftovers again today, anything else drops them.
+    #[serde(default)]
+    keep: String,
+}
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/my-day", get(show))
+        .route("/my-day/add", post(add))
+        .route("/my-day/remove", post(remove))
+        .route("/my-day/complete", post(complete))
+        .route("/my-day/review", post(review))
+}
+
+fn today() -> NaiveDate {
+    Local::now().date_naive()
+}
+
+fn redirect_my_day() -> Response {
+    Redirect::to(&format!("{}/my-day", base_path())).into_response()
+}
+
+async fn show(State(state): State<AppState>) -> Result<Html<String>, StatusCode> {
+    let body = render(&state.db.lock(), today(), None).map_err(error_status)?;
+    Ok(Html(body))
+}
+
+async fn add(
+    State(state): State<AppState>,
+    Form(form): Form<IdForm>,
+) -> Result<Response, StatusCode> {
+    let db = state.db.lock();
+    let today = today();
+    db.get_todo(form.id)
+        .map_err(error_status)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    let focus = db.focus(today).map_err(error_status)?;
+    if focus.len() >= MAX_FOCUS && !focus.iter().any(|todo| todo.id == form.id) {
+        let invalid = Invalid(vec![FieldError {
+            field: "id",
+            message: format!("Mehr als {MAX_FOCUS} Todos passen nicht in einen Tag."),
+        }]);
+        let body = render(&db, today, Some(&invalid)).map_err(error_status)?;
+        return Ok((StatusCode::UNPROCESSABLE_ENTITY, Html(body)).into_response());
+    }
+    db.add_focus(form.id, today).map_err(error_status)?;
+    Ok(redirect_my_day())
+}
+
+async fn remove(
+    State(state): State<AppState>,
+    Form(form): Form<IdForm>,
+) -> Result<Response, StatusCode> {
+    state
+        .db
+        .lock()
+        .remove_focus(form.id, today())
+        .map_err(error_status)?;
+    Ok(redirect_my_day())
+}
+
+async fn complete(
+    State(state): State<AppState>,
+    Form(form): Form<IdForm>,
+) -> Result<Response, StatusCode> {
+    state
+        .db
+        .lock()
+        .complete_todo(form.id)
+        .map_err(error_status)?;
+    Ok(redirect_my_day())
+}
+
+async fn review(
+    State(state): State<AppState>,
+    Form(form): Form<ReviewForm>,
+) -> Result<Response, StatusCode> {
+    let db = state.db.lock();
+    let today = today();
+    if form.keep == "1" {
+        if let Some(previous) = db.focus_to_review(today).map_err(error_status)? {
+            let room = MAX_FOCUS.saturating_sub(db.focus(today).map_err(error_status)?.len());
+            let tx = db.transaction().map_err(error_status)?;
+            let leftovers = db.focus(previous).map_err(error_status)?;
+            for todo in leftovers
+                .iter()
+                .filter(|todo| todo.completed_at.is_none())
+                .take(room)
+            {
+                db.add_focus(todo.id, today).map_err(error_status)?;
+            }
+            tx.commit().map_err(|err| error_status(err.into()))?;
+        }
+    }
+    db.set_focus_reviewed(today).map_err(error_status)?;
+    Ok(redirect_my_day())
+}
+
+/// A todo on the page, with the forms that apply to it.
+fn card(todo: &Todo) -> String {
+    let base = base_path();
+    let done = if todo.completed_at.is_some() {
+        "    <span class=\"status done\">Erledigt</span>\n".to_string()
+    } else {
+        format!(
+            r#"    <form method="post" action="{base}/my-day/complete">
+      <input type="hidden" name="id" value="{id}" />
+      <button type="submit">Done</button>
+    </form>
+"#,
+            id = todo.id,
+        )
+    };
+    format!(
+        r#"<div class="todo">
+  <div class="meta">
+    <div class="title"><a href="{base}/todo/{id}">{title}</a></div>
+  </div>
+  <div class="actions">
+{done}    <form method="post" action="{base}/my-day/remove">
+      <input type="hidden" name="id" value="{id}" />
+      <button class="delete" type="submit">Entfernen</button>
+    </form>
+  </div>
+</div>
+"#,
+        id = todo.id,
+        title = html_escape(&todo.title),
+    )
+}
+
+fn render(db: &Database, today: NaiveDate, invalid: Option<&Invalid>) -> anyhow::Result<String> {
+    let base = base_path();
+    let focus = db.focus(today)?;
+    let finished = focus
+        .iter()
+        .filter(|todo| todo.completed_at.is_some())
+        .count();
+
+    let mut body = page_start();
+    body.push_str(&format!(
+        "    <h1>Mein Tag</h1>\n    <div class=\"subtitle\">{date} · {finished} von {total} erledigt</div>\n",
+        date = today.format(preferences::date()),
+        total = focus.len(),
+    ));
+    if let Some(invalid) = invalid {
+        body.push_str(&error_notice(invalid));
+    }
+
+    if let Some(previous) = db.focus_to_review(today)? {
+        let leftovers: Vec<Todo> = db
+            .focus(previous)?
+            .into_iter()
+            .filter(|todo| {
+                todo.completed_at.is_none() && !focus.iter().any(|own| own.id == todo.id)
+            })
+            .collect();
+        if !leftovers.is_empty() {
+            let titles: Vec<String> = leftovers
+                .iter()
+                .map(|todo| html_escape(&todo.title))
+                .collect();
+            body.push_str(&format!(
+                r#"    <div class="notice">Vom {previous} ist noch offen: {titles}. Heute weitermachen?
+    <form method="post" action="{base}/my-day/review">
+      <button type="submit" name="keep" value="1">Übernehmen</button>
+      <button type="submit" name="keep" value="0">Verwerfen</button>
+    </form>
+    </div>
+"#,
+                previous = previous.format(preferences::day()),
+                titles = titles.join(", "),
+            ));
+        }
+    }
+
+    if focus.is_empty() {
+        body.push_str("    <div class=\"subtitle\">Noch nichts ausgewählt.</div>\n");
+    } else {
+        body.push_str("    <div class=\"todo-list\">\n");
+        for todo in &focus {
+            body.push_str(&card(todo));
+        }
+        body.push_str("    </div>\n");
+    }
+
+    // Kept out of sight, so the page stays about today's picks.
+    if focus.len() < MAX_FOCUS {
+        let candidates: Vec<Todo> = db
+            .list_todos(false)?
+            .into_iter()
+            .filter(|todo| !focus.iter().any(|own| own.id == todo.id))
+            .collect();
+        if !candidates.is_empty() {
+            let mut options = String::new();
+            for todo in &candidates {
+                options.push_str(&format!(
+                    "        <option value=\"{id}\">{title}</option>\n",
+                    id = todo.id,
+                    title = html_escape(&todo.title),
+                ));
+            }
+            body.push_str(&format!(
+                r#"    <details class="done">
+    <summary>Todo auswählen</summary>
+    <form method="post" action="{base}/my-day/add">
+      <select name="id">
+{options}      </select>
+      <button type="submit">Auswählen</button>
+    </form>
+    </details>
+"#
+            ));
+        }
+    }
+
+    body.push_str(&format!(
+        "    <a class=\"back\" href=\"{base}/\">Zurück zur Liste</a>\n"
+    ));
+    body.push_str(PAGE_END);
+    Ok(body)
+}
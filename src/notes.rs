@@ -0,0 +1,211 @@
+//! Notes: captured ideas with a Markdown text that can later become todos.
+
+use axum::{
+    extract::{Form, Query, State},
+    http::StatusCode,
+    response::{Html, IntoResponse, Redirect, Response},
+    routing::{get, post},
+    Router,
+};
+use pulldown_cmark::{html, CowStr, Event, Parser, Tag};
+use serde::Deserialize;
+
+use crate::db::Database;
+use crate::undo::{self, UndoQuery};
+use crate::validate::{self, Invalid};
+use crate::{
+    base_path, error_notice, error_status, html_escape, invalid_attr, page_start, preferences,
+    AppState, IdForm, PAGE_END,
+};
+
+#[derive(Deserialize, Default)]
+struct NoteForm {
+    title: String,
+    #[serde(default)]
+    body: String,
+}
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/notes", get(show).post(add_note))
+        .route("/notes/convert", post(convert_note))
+        .route("/notes/delete", post(delete_note))
+}
+
+async fn show(
+    State(state): State<AppState>,
+    Query(query): Query<UndoQuery>,
+) -> Result<Html<String>, StatusCode> {
+    let db = state.db.lock();
+    let notice = match query.undo {
+        Some(action_id) => undo::notice(&db, state.undo_window, action_id).map_err(error_status)?,
+        None => None,
+    };
+    let body = render(&db, &NoteForm::default(), None, notice.as_deref()).map_err(error_status)?;
+    Ok(Html(body))
+}
+
+async fn add_note(
+    State(state): State<AppState>,
+    Form(form): Form<NoteForm>,
+) -> Result<Response, StatusCode> {
+    let db = state.db.lock();
+    let input = match validate::note(&form.title, &form.body) {
+        Ok(input) => input,
+        Err(invalid) => {
+            let body = render(&db, &form, Some(&invalid), None).map_err(error_status)?;
+            return Ok((StatusCode::UNPROCESSABLE_ENTITY, Html(body)).into_response());
+        }
+    };
+    db.add_note(&input.title, &input.body)
+        .map_err(error_status)?;
+    Ok(redirect_notes())
+}
+
+async fn convert_note(
+    State(state): State<AppState>,
+    Form(form): Form<IdForm>,
+) -> Result<Response, StatusCode> {
+    let todo = state
+        .db
+        .lock()
+        .convert_note(form.id)
+        .map_err(error_status)?;
+    Ok(Redirect::to(&format!("{}/todo/{}", base_path(), todo.id)).into_response())
+}
+
+async fn delete_note(
+    State(state): State<AppState>,
+    Form(form): Form<IdForm>,
+) -> Result<Response, StatusCode> {
+    let action_id = state.db.lock().delete_note(form.id).map_err(error_status)?;
+    Ok(Redirect::to(&format!("{}/notes?undo={action_id}", base_path())).into_response())
+}
+
+fn redirect_notes() -> Response {
+    Redirect::to(&format!("{}/notes", base_path())).into_response()
+}
+
+/// Lists every note; `form` and `invalid` refill the add form after a
+/// rejected submission. `notice` is HTML shown above the form.
+fn render(
+    db: &Database,
+    form: &NoteForm,
+    invalid: Option<&Invalid>,
+    notice: Option<&str>,
+) -> anyhow::Result<String> {
+    let base = base_path();
+    let mut body = page_start();
+    body.push_str(
+        "    <h1>Notizen</h1>\n    <div class=\"subtitle\">Ideen, die (noch) keine Todos sind. Der Text darf Markdown enthalten.</div>\n",
+    );
+    if let Some(notice) = notice {
+        body.push_str(&format!("    <div class=\"notice\">{notice}</div>\n"));
+    }
+    if let Some(invalid) = invalid {
+        body.push_str(&error_notice(invalid));
+    }
+    body.push_str(&format!(
+        r#"    <form class="stacked" method="post" action="{base}/notes">
+      <input type="text" name="title" placeholder="Neue Notiz" value="{title}" maxlength="{max_title}" required{title_invalid} />
+      <textarea name="body" rows="4" maxlength="{max_body}" placeholder="Text (optional)"{body_invalid}>{text}</textarea>
+      <button type="submit">Speichern</button>
+    </form>
+    <div class="todo-list">
+"#,
+        title = html_escape(&form.title),
+        max_title = validate::MAX_TITLE_CHARS,
+        title_invalid = invalid_attr(invalid, "title"),
+        text = html_escape(&form.body),
+        max_body = validate::MAX_NOTE_CHARS,
+        body_invalid = invalid_attr(invalid, "body"),
+    ));
+
+    for note in db.list_notes()? {
+        let action = match note.todo_id {
+            Some(todo_id) => {
+                format!(r#"<a class="back" href="{base}/todo/{todo_id}">Todo #{todo_id}</a>"#)
+            }
+            None => format!(
+                r#"<form method="post" action="{base}/notes/convert">
+      <input type="hidden" name="id" value="{id}" />
+      <button type="submit">Als Todo</button>
+    </form>"#,
+                id = note.id
+            ),
+        };
+        body.push_str(&format!(
+            r#"<div class="todo">
+  <div class="meta">
+    <div class="title">{title}</div>
+    <div class="note-body">{text}</div>
+    <div class="time">Erstellt am {created}</div>
+  </div>
+  <div class="actions">
+    {action}
+    <form method="post" action="{base}/notes/delete">
+      <input type="hidden" name="id" value="{id}" />
+      <button class="delete" type="submit">Löschen</button>
+    </form>
+  </div>
+</div>
+"#,
+            id = note.id,
+            title = html_escape(&note.title),
+            text = render_markdown(&note.body),
+            created = note.created_at.format(preferences::date_time()),
+        ));
+    }
+
+    body.push_str(&format!(
+        "    </div>\n    <a class=\"back\" href=\"{base}/\">Zurück zur Liste</a>\n"
+    ));
+    body.push_str(PAGE_END);
+    Ok(body)
+}
+
+/// Renders Markdown to HTML. Embedded HTML is shown as text and script URLs
+/// are dropped, so a note can't inject anything into the page.
+fn render_markdown(markdown: &str) -> String {
+    let events = Parser::new(markdown).map(|event| match event {
+        Event::Html(raw) | Event::InlineHtml(raw) => Event::Text(raw),
+        Event::Start(Tag::Link {
+            link_type,
+            dest_url,
+            title,
+            id,
+        }) => Event::Start(Tag::Link {
+            link_type,
+            dest_url: safe_url(dest_url),
+            title,
+            id,
+        }),
+        Event::Start(Tag::Image {
+            link_type,
+            dest_url,
+            title,
+            id,
+        }) => Event::Start(Tag::Image {
+            link_type,
+            dest_url: safe_url(dest_url),
+            title,
+            id,
+        }),
+        event => event,
+    });
+    let mut output = String::new();
+    html::push_html(&mut output, events);
+    output
+}
+
+fn safe_url(url: CowStr<'_>) -> CowStr<'_> {
+    let scheme = url.trim_start().to_ascii_lowercase();
+    if ["javascript:", "vbscript:", "data:"]
+        .iter()
+        .any(|bad| scheme.starts_with(bad))
+    {
+        CowStr::Borrowed("#")
+    } else {
+        url
+    }
+}
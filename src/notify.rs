@@ -0,0 +1,151 @@
+//! Delivering notifications by email, ntfy, webhook or Web Push.
+
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use reqwest::Url;
+use serde_json::json;
+
+use crate::mail::{Mailer, Template};
+use crate::models::Todo;
+use crate::webpush::WebPush;
+
+/// Attempts per notification and channel before it is given up.
+pub const MAX_ATTEMPTS: u32 = 5;
+
+/// Something to tell the user about a todo.
+pub struct Notification {
+    /// What happened, e.g. `deadline` or `reminder`; sent to webhooks.
+    pub event: &'static str,
+    pub todo: Todo,
+    pub title: String,
+    pub body: String,
+    /// Link to the todo's page.
+    pub url: String,
+}
+
+pub enum Channel {
+    /// Queued in the outbox, which retries on its own.
+    Email(Mailer),
+    /// A topic on an ntfy server.
+    Ntfy {
+        server: Url,
+        topic: String,
+        token: Option<String>,
+    },
+    /// Receives every notification as JSON.
+    Webhook { url: Url },
+    /// Every browser subscribed to push messages.
+    WebPush(Arc<WebPush>),
+}
+
+impl Channel {
+    /// Publishes to the topic at `topic_url`, e.g. `https://ntfy.sh/my-todos`.
+    pub fn ntfy(topic_url: &str, token: Option<String>) -> Result<Self> {
+        let mut server: Url = topic_url
+            .parse()
+            .with_context(|| format!("invalid ntfy topic URL {topic_url}"))?;
+        let topic = server
+            .path_segments()
+            .and_then(|mut segments| segments.next_back())
+            .filter(|topic| !topic.is_empty())
+            .with_context(|| format!("{topic_url} names no ntfy topic"))?
+            .to_string();
+        // Messages with a title are published as JSON to the server's root.
+        server
+            .path_segments_mut()
+            .map_err(|()| anyhow::anyhow!("invalid ntfy topic URL {topic_url}"))?
+            .pop();
+        Ok(Channel::Ntfy {
+            server,
+            topic,
+            token,
+        })
+    }
+
+    pub fn webhook(url: &str) -> Result<Self> {
+        let url = url
+            .parse()
+            .with_context(|| format!("invalid webhook URL {url}"))?;
+        Ok(Channel::Webhook { url })
+    }
+
+    /// Stable name stored with every delivery.
+    pub fn name(&self) -> &'static str {
+        match self {
+            Channel::Email(_) => "email",
+            Channel::Ntfy { .. } => "ntfy",
+            Channel::Webhook { .. } => "webhook",
+            Channel::WebPush(_) => "webpush",
+        }
+    }
+
+    async fn send(&self, http: &reqwest::Client, notification: &Notification) -> Result<()> {
+        match self {
+            Channel::Email(mailer) => mailer.queue(
+                Template::for_event(notification.event),
+                &[
+                    ("title", &notification.title),
+                    ("message", &notification.body),
+                    ("url", &notification.url),
+                    ("todo", &notification.todo.title),
+                ],
+            )?,
+            Channel::Ntfy {
+                server,
+                topic,
+                token,
+            } => {
+                let mut request = http.post(server.clone()).json(&json!({
+                    "topic": topic,
+                    "title": notification.title,
+                    "message": notification.body,
+                    "click": notification.url,
+                }));
+                if let Some(token) = token {
+                    request = request.bearer_auth(token);
+                }
+                request.send().await?.error_for_status()?;
+            }
+            Channel::Webhook { url } => {
+                http.post(url.clone())
+                    .json(&json!({
+                        "event": notification.event,
+                        "title": notification.title,
+                        "message": notification.body,
+                        "url": notification.url,
+                        "todo": notification.todo,
+                    }))
+                    .send()
+                    .await?
+                    .error_for_status()?;
+            }
+            Channel::WebPush(web_push) => web_push.send(http, notification).await?,
+        }
+        Ok(())
+    }
+}
+
+/// The configured channels; notifications go out on every one of them.
+#[derive(Default)]
+pub struct Notifier {
+    http: reqwest::Client,
+    channels: Vec<Channel>,
+}
+
+impl Notifier {
+    pub fn add(&mut self, channel: Channel) {
+        self.channels.push(channel);
+    }
+
+    pub fn channels(&self) -> &[Channel] {
+        &self.channels
+    }
+
+    pub async fn send(&self, channel: &Channel, notification: &Notification) -> Result<()> {
+        channel
+            .send(&self.http, notification)
+            .await
+            .with_context(|| format!("sending {} notification", channel.name()))
+    }
+}
@@ -0,0 +1,299 @@
+//! A todo as a one-page PDF, to attach to a mail or print: title, the
+//! details, and the notes it was made from. Written with the PDF base font
+//! Helvetica, so nothing is embedded; characters outside Windows-1252 come
+//! out as `?`.
+
+use axum::{
+    extract::{Path, State},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    routing::get,
+    Router,
+};
+use chrono::Local;
+use pdf_writer::{Content, Name, Pdf, Rect, Ref, Str};
+
+use crate::models::{Goal, Note, Status, Todo};
+use crate::{error_status, preferences, statuses, AppState};
+
+/// A4 in points, and the margin on every side.
+const WIDTH: f32 = 595.0;
+const HEIGHT: f32 = 842.0;
+const MARGIN: f32 = 56.0;
+/// Line height as a multiple of the font size.
+const LEADING: f32 = 1.35;
+
+const REGULAR: Name = Name(b"F1");
+const BOLD: Name = Name(b"F2");
+
+/// Helvetica's advance widths for ASCII 32 to 126, in thousandths of the
+/// font size. Other characters count as wide as a digit.
+const WIDTHS: [u16; 95] = [
+    278, 278, 355, 556, 556, 889, 667, 191, 333, 333, 389, 584, 278, 333, 278, 278, 556, 556, 556,
+    556, 556, 556, 556, 556, 556, 556, 278, 278, 584, 584, 584, 556, 1015, 667, 667, 722, 722, 667,
+    611, 778, 722, 278, 500, 667, 556, 833, 722, 778, 667, 778, 722, 667, 611, 722, 667, 944, 667,
+    667, 611, 278, 278, 278, 469, 556, 333, 556, 556, 500, 556, 556, 278, 556, 556, 222, 222, 500,
+    222, 833, 556, 556, 556, 556, 333, 500, 278, 556, 500, 722, 500, 500, 500, 334, 260, 334, 584,
+];
+/// Helvetica-Bold runs wider; this keeps wrapped bold lines inside the
+/// margin without a second table.
+const BOLD_FACTOR: f32 = 1.1;
+
+pub fn router() -> Router<AppState> {
+    Router::new().route("/todo/:id/pdf", get(download))
+}
+
+async fn download(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<Response, StatusCode> {
+    let (todo, statuses, goals, notes) = {
+        let db = state.db.lock();
+        let todo = db
+            .get_todo(id)
+            .map_err(error_status)?
+            .ok_or(StatusCode::NOT_FOUND)?;
+        let notes: Vec<Note> = db
+            .list_notes()
+            .map_err(error_status)?
+            .into_iter()
+            .filter(|note| note.todo_id == Some(id))
+            .collect();
+        (
+            todo,
+            db.statuses().map_err(error_status)?,
+            db.list_goals().map_err(error_status)?,
+            notes,
+        )
+    };
+    let body = render(&todo, &statuses, &goals, &notes);
+    let disposition = format!("attachment; filename=\"todo-{id}.pdf\"");
+    Ok((
+        [
+            (header::CONTENT_TYPE, "application/pdf".to_string()),
+            (header::CONTENT_DISPOSITION, disposition),
+        ],
+        body,
+    )
+        .into_response())
+}
+
+fn render(todo: &Todo, statuses: &[Status], goals: &[Goal], notes: &[Note]) -> Vec<u8> {
+    let mut page = Page::new();
+    page.text(BOLD, 18.0, &todo.title);
+    page.gap(6.0);
+
+    let mut details = vec![format!("Status: {}", statuses::label(todo, statuses))];
+    if let Some(deadline) = todo.deadline {
+        details.push(format!(
+            "Fällig am {}",
+            deadline.format(preferences::date())
+        ));
+    }
+    if let Some(start) = todo.start_date {
+        details.push(format!("Beginn {}", start.format(preferences::date())));
+    }
+    if let Some(goal) = goals.iter().find(|goal| Some(goal.id) == todo.goal_id) {
+        details.push(format!("Ziel: {}", goal.title));
+    }
+    if let Some(location) = &todo.location {
+        details.push(format!("Ort: {location}"));
+    }
+    if let Some(points) = todo.points {
+        details.push(format!("{points} Punkte"));
+    }
+    details.push(format!(
+        "Erstellt am {}",
+        todo.created_at
+            .with_timezone(&Local)
+            .format(preferences::date_time())
+    ));
+    if let Some(completed_at) = todo.completed_at {
+        details.push(format!(
+            "Erledigt am {}",
+            completed_at
+                .with_timezone(&Local)
+                .format(preferences::date_time())
+        ));
+    }
+    for detail in &details {
+        page.text(REGULAR, 11.0, detail);
+    }
+
+    for note in notes {
+        page.gap(10.0);
+        page.rule();
+        page.gap(10.0);
+        page.text(BOLD, 13.0, &note.title);
+        page.gap(4.0);
+        for paragraph in note.body.lines() {
+            if paragraph.trim().is_empty() {
+                page.gap(6.0);
+            } else {
+                page.text(REGULAR, 11.0, paragraph);
+            }
+        }
+    }
+    page.finish()
+}
+
+/// Lays out lines from the top of the page down, dropping what doesn't fit
+/// and ending with `…` instead.
+struct Page {
+    content: Content,
+    /// Baseline of the next line.
+    y: f32,
+    full: bool,
+}
+
+impl Page {
+    fn new() -> Self {
+        Self {
+            content: Content::new(),
+            y: HEIGHT - MARGIN,
+            full: false,
+        }
+    }
+
+    /// Writes `text`, wrapped at spaces to the width between the margins.
+    fn text(&mut self, font: Name, size: f32, text: &str) {
+        for line in wrap(text, font, size) {
+            self.line(font, size, &line);
+        }
+    }
+
+    fn line(&mut self, font: Name, size: f32, line: &str) {
+        if self.full {
+            return;
+        }
+        self.y -= size;
+        let last = self.y - size * LEADING < MARGIN;
+        let line = if last {
+            self.full = true;
+            "…"
+        } else {
+            line
+        };
+        self.content
+            .begin_text()
+            .set_font(font, size)
+            .next_line(MARGIN, self.y)
+            .show(Str(&encode(line)))
+            .end_text();
+        self.y -= size * (LEADING - 1.0);
+    }
+
+    fn gap(&mut self, points: f32) {
+        self.y -= points;
+    }
+
+    /// A thin gray line across the page.
+    fn rule(&mut self) {
+        if self.full {
+            return;
+        }
+        self.content
+            .set_stroke_gray(0.8)
+            .set_line_width(0.5)
+            .move_to(MARGIN, self.y)
+            .line_to(WIDTH - MARGIN, self.y)
+            .stroke();
+    }
+
+    fn finish(self) -> Vec<u8> {
+        let catalog = Ref::new(1);
+        let pages = Ref::new(2);
+        let page = Ref::new(3);
+        let content = Ref::new(4);
+        let regular = Ref::new(5);
+        let bold = Ref::new(6);
+
+        let mut pdf = Pdf::new();
+        pdf.catalog(catalog).pages(pages);
+        pdf.pages(pages).kids([page]).count(1);
+        let mut writer = pdf.page(page);
+        writer
+            .media_box(Rect::new(0.0, 0.0, WIDTH, HEIGHT))
+            .parent(pages)
+            .contents(content);
+        writer
+            .resources()
+            .fonts()
+            .pair(REGULAR, regular)
+            .pair(BOLD, bold);
+        drop(writer);
+        for (id, name) in [(regular, "Helvetica"), (bold, "Helvetica-Bold")] {
+            pdf.type1_font(id)
+                .base_font(Name(name.as_bytes()))
+                .encoding_predefined(Name(b"WinAnsiEncoding"));
+        }
+        pdf.stream(content, &self.content.finish());
+        pdf.finish()
+    }
+}
+
+/// `text` in lines that fit between the margins, broken at spaces where
+/// possible.
+fn wrap(text: &str, font: Name, size: f32) -> Vec<String> {
+    let max = (WIDTH - 2.0 * MARGIN) / size;
+    let factor = if font == BOLD { BOLD_FACTOR } else { 1.0 };
+    let width = |text: &str| text.chars().map(char_width).sum::<f32>() * factor;
+
+    let mut lines = Vec::new();
+    let mut line = String::new();
+    for word in text.split_whitespace() {
+        let candidate = if line.is_empty() {
+            word.to_string()
+        } else {
+            format!("{line} {word}")
+        };
+        if width(&candidate) <= max {
+            line = candidate;
+            continue;
+        }
+        if !line.is_empty() {
+            lines.push(std::mem::take(&mut line));
+        }
+        // A word longer than a line is cut wherever it has to be.
+        for c in word.chars() {
+            if width(&line) + char_width(c) * factor > max {
+                lines.push(std::mem::take(&mut line));
+            }
+            line.push(c);
+        }
+    }
+    if !line.is_empty() || lines.is_empty() {
+        lines.push(line);
+    }
+    lines
+}
+
+/// Width of `c` as a fraction of the font size.
+fn char_width(c: char) -> f32 {
+    let width = match c {
+        ' '..='~' => WIDTHS[c as usize - 32],
+        _ => 556,
+    };
+    f32::from(width) / 1000.0
+}
+
+/// `text` in Windows-1252, the encoding the base fonts are used with.
+fn encode(text: &str) -> Vec<u8> {
+    text.chars()
+        .map(|c| match c {
+            ' '..='~' | '\u{a0}'..='\u{ff}' => c as u8,
+            '€' => 0x80,
+            '‚' => 0x82,
+            '„' => 0x84,
+            '…' => 0x85,
+            '‘' => 0x91,
+            '’' => 0x92,
+            '“' => 0x93,
+            '”' => 0x94,
+            '•' => 0x95,
+            '–' => 0x96,
+            '—' => 0x97,
+            _ => b'?',
+        })
+        .collect()
+}
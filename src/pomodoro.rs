@@ -0,0 +1,153 @@
+//! Pomodoros: timed stretches of work on one todo that can be paused and are
+//! logged when finished. One that ran its full length counts as completed.
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::{IntoResponse, Redirect, Response},
+    routing::post,
+    Router,
+};
+use chrono::{DateTime, Local, TimeDelta, Utc};
+
+use crate::db::Database;
+use crate::models::Pomodoro;
+use crate::{base_path, error_status, AppState};
+
+/// How long a pomodoro lasts.
+pub const MINUTES: i64 = 25;
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/todo/:id/pomodoro/start", post(start))
+        .route("/todo/:id/pomodoro/pause", post(pause))
+        .route("/todo/:id/pomodoro/finish", post(finish))
+}
+
+/// Time worked on a pomodoro up to `now`, leaving out its pauses.
+pub fn worked(pomodoro: &Pomodoro, now: DateTime<Utc>) -> TimeDelta {
+    let end = pomodoro.finished_at.or(pomodoro.paused_at).unwrap_or(now);
+    end - pomodoro.started_at - TimeDelta::seconds(pomodoro.paused_seconds)
+}
+
+pub fn completed(pomodoro: &Pomodoro) -> bool {
+    pomodoro
+        .finished_at
+        .is_some_and(|finished_at| worked(pomodoro, finished_at) >= TimeDelta::minutes(MINUTES))
+}
+
+/// The todo's pomodoro that isn't finished yet, if any.
+fn current(db: &Database, id: i64) -> Result<Option<Pomodoro>, StatusCode> {
+    db.get_todo(id)
+        .map_err(error_status)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    let pomodoros = db.pomodoros(id).map_err(error_status)?;
+    Ok(pomodoros
+        .into_iter()
+        .find(|pomodoro| pomodoro.finished_at.is_none()))
+}
+
+/// Ends a pause, adding it to the pomodoro's paused time.
+fn resume(pomodoro: &mut Pomodoro, now: DateTime<Utc>) {
+    if let Some(paused_at) = pomodoro.paused_at.take() {
+        pomodoro.paused_seconds += (now - paused_at).num_seconds();
+    }
+}
+
+fn redirect_todo(id: i64) -> Response {
+    Redirect::to(&format!("{}/todo/{id}", base_path())).into_response()
+}
+
+/// Starts a pomodoro, or resumes the paused one.
+async fn start(State(state): State<AppState>, Path(id): Path<i64>) -> Result<Response, StatusCode> {
+    let db = state.db.lock();
+    let now = Utc::now();
+    match current(&db, id)? {
+        Some(mut pomodoro) => {
+            resume(&mut pomodoro, now);
+            db.update_pomodoro(&pomodoro).map_err(error_status)?;
+        }
+        None => db.add_pomodoro(id, now).map_err(error_status)?,
+    }
+    Ok(redirect_todo(id))
+}
+
+async fn pause(State(state): State<AppState>, Path(id): Path<i64>) -> Result<Response, StatusCode> {
+    let db = state.db.lock();
+    let mut pomodoro = current(&db, id)?.ok_or(StatusCode::CONFLICT)?;
+    if pomodoro.paused_at.is_none() {
+        pomodoro.paused_at = Some(Utc::now());
+        db.update_pomodoro(&pomodoro).map_err(error_status)?;
+    }
+    Ok(redirect_todo(id))
+}
+
+async fn finish(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<Response, StatusCode> {
+    let db = state.db.lock();
+    let now = Utc::now();
+    let mut pomodoro = current(&db, id)?.ok_or(StatusCode::CONFLICT)?;
+    resume(&mut pomodoro, now);
+    pomodoro.finished_at = Some(now);
+    db.update_pomodoro(&pomodoro).map_err(error_status)?;
+    Ok(redirect_todo(id))
+}
+
+/// The pomodoro part of a todo's page: how many were completed, and the
+/// one under way with buttons to pause, resume or finish it.
+pub fn section(id: i64, pomodoros: &[Pomodoro]) -> String {
+    let base = base_path();
+    let now = Utc::now();
+    let count = pomodoros
+        .iter()
+        .filter(|pomodoro| completed(pomodoro))
+        .count();
+    let minutes: i64 = pomodoros
+        .iter()
+        .filter(|pomodoro| pomodoro.finished_at.is_some())
+        .map(|pomodoro| worked(pomodoro, now).num_minutes())
+        .sum();
+    let mut section = format!(
+        "    <h2>Pomodoro</h2>\n    <div class=\"subtitle\">{count} erledigt · {minutes} Minuten gearbeitet</div>\n"
+    );
+    let current = pomodoros
+        .iter()
+        .find(|pomodoro| pomodoro.finished_at.is_none());
+    let form = match current {
+        Some(pomodoro) => {
+            let worked = worked(pomodoro, now).num_minutes();
+            let (state, action, label) = if pomodoro.paused_at.is_some() {
+                (format!("Pausiert nach {worked} Minuten"), "start", "Weiter")
+            } else {
+                let left = MINUTES - worked;
+                let state = if left > 0 {
+                    format!(
+                        "Läuft seit {} Uhr · noch {left} Minuten",
+                        pomodoro.started_at.with_timezone(&Local).format("%H:%M")
+                    )
+                } else {
+                    "Die Zeit ist um.".to_string()
+                };
+                (state, "pause", "Pause")
+            };
+            format!(
+                r#"    <div class="notice">{state}</div>
+    <form method="post" action="{base}/todo/{id}/pomodoro/{action}">
+      <button type="submit">{label}</button>
+      <button type="submit" formaction="{base}/todo/{id}/pomodoro/finish">Abschließen</button>
+    </form>
+"#
+            )
+        }
+        None => format!(
+            r#"    <form method="post" action="{base}/todo/{id}/pomodoro/start">
+      <button type="submit">Pomodoro starten ({MINUTES} Minuten)</button>
+    </form>
+"#
+        ),
+    };
+    section.push_str(&form);
+    section
+}
@@ -0,0 +1,36 @@
+//! Floating todos: an overdue deadline moves to today each morning instead
+//! of drifting into the past, for todos that matter but have no fixed date.
+//! The settings can make every todo float.
+
+use std::time::Duration;
+
+use chrono::Local;
+
+use crate::db::SharedDatabase;
+
+/// How often the job looks for overdue todos to move.
+const CHECK_INTERVAL: Duration = Duration::from_secs(10 * 60);
+
+/// Moves overdue floating todos to today, now and then every few minutes.
+pub fn spawn(db: SharedDatabase) {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(CHECK_INTERVAL);
+        loop {
+            ticker.tick().await;
+            if db.is_read_only() {
+                continue;
+            }
+            let db = db.clone();
+            let job = tokio::task::spawn_blocking(move || {
+                let db = db.lock();
+                db.postpone_overdue(Local::now().date_naive(), db.auto_postpone()?)
+            });
+            match job.await {
+                Ok(Ok(0)) => {}
+                Ok(Ok(count)) => println!("moved {count} overdue todos to today"),
+                Ok(Err(err)) => eprintln!("postponing todos failed: {err:#}"),
+                Err(err) => eprintln!("postponing todos panicked: {err}"),
+            }
+        }
+    });
+}
@@ -0,0 +1,146 @@
+//! Display preferences: the day weeks begin on, and how dates are written.
+//! They are stored in the settings and kept here as well, since nearly every
+//! page, mail and export writes dates.
+
+use std::sync::{PoisonError, RwLock};
+
+use chrono::{Datelike, Days, NaiveDate, Weekday};
+
+use crate::db::Database;
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+pub enum DateFormat {
+    /// `24.12.2026`
+    Dmy,
+    /// `2026-12-24`
+    Iso,
+    /// `12/24/2026`
+    Mdy,
+}
+
+impl DateFormat {
+    pub const ALL: [DateFormat; 3] = [DateFormat::Dmy, DateFormat::Iso, DateFormat::Mdy];
+
+    pub fn name(self) -> &'static str {
+        match self {
+            DateFormat::Dmy => "dmy",
+            DateFormat::Iso => "iso",
+            DateFormat::Mdy => "mdy",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::ALL.into_iter().find(|format| format.name() == name)
+    }
+
+    /// Christmas Eve as this format writes it, for the settings page.
+    pub fn example(self) -> &'static str {
+        match self {
+            DateFormat::Dmy => "24.12.2026",
+            DateFormat::Iso => "2026-12-24",
+            DateFormat::Mdy => "12/24/2026",
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct Preferences {
+    pub week_start: Weekday,
+    pub date_format: DateFormat,
+}
+
+impl Default for Preferences {
+    fn default() -> Self {
+        DEFAULT
+    }
+}
+
+/// Weeks begin on the days offered here.
+pub const WEEK_STARTS: [Weekday; 2] = [Weekday::Mon, Weekday::Sun];
+
+const DEFAULT: Preferences = Preferences {
+    week_start: Weekday::Mon,
+    date_format: DateFormat::Dmy,
+};
+
+static PREFERENCES: RwLock<Preferences> = RwLock::new(DEFAULT);
+
+/// Reads the stored preferences; until then, the defaults apply.
+pub fn load(db: &Database) -> anyhow::Result<()> {
+    *PREFERENCES.write().unwrap_or_else(PoisonError::into_inner) = db.preferences()?;
+    Ok(())
+}
+
+pub fn get() -> Preferences {
+    *PREFERENCES.read().unwrap_or_else(PoisonError::into_inner)
+}
+
+pub fn set(db: &Database, preferences: Preferences) -> anyhow::Result<()> {
+    db.set_preferences(preferences)?;
+    *PREFERENCES.write().unwrap_or_else(PoisonError::into_inner) = preferences;
+    Ok(())
+}
+
+/// The first day of the week `date` is in.
+pub fn week_of(date: NaiveDate) -> NaiveDate {
+    date - Days::new(date.weekday().days_since(get().week_start).into())
+}
+
+/// `strftime` patterns for dates in the preferred format.
+pub fn date() -> &'static str {
+    match get().date_format {
+        DateFormat::Dmy => "%d.%m.%Y",
+        DateFormat::Iso => "%Y-%m-%d",
+        DateFormat::Mdy => "%m/%d/%Y",
+    }
+}
+
+/// Day and month only, where the year goes without saying.
+pub fn day() -> &'static str {
+    match get().date_format {
+        DateFormat::Dmy => "%d.%m.",
+        DateFormat::Iso => "%m-%d",
+        DateFormat::Mdy => "%m/%d",
+    }
+}
+
+/// Month and year, for axes spanning months.
+pub fn month() -> &'static str {
+    match get().date_format {
+        DateFormat::Dmy | DateFormat::Mdy => "%m/%Y",
+        DateFormat::Iso => "%Y-%m",
+    }
+}
+
+pub fn date_time() -> &'static str {
+    match get().date_format {
+        DateFormat::Dmy => "%d.%m.%Y %H:%M",
+        DateFormat::Iso => "%Y-%m-%d %H:%M",
+        DateFormat::Mdy => "%m/%d/%Y %H:%M",
+    }
+}
+
+pub fn date_time_seconds() -> &'static str {
+    match get().date_format {
+        DateFormat::Dmy => "%d.%m.%Y %H:%M:%S",
+        DateFormat::Iso => "%Y-%m-%d %H:%M:%S",
+        DateFormat::Mdy => "%m/%d/%Y %H:%M:%S",
+    }
+}
+
+/// The same as Excel number formats, for date cells.
+pub fn excel_date() -> &'static str {
+    match get().date_format {
+        DateFormat::Dmy => "dd.mm.yyyy",
+        DateFormat::Iso => "yyyy-mm-dd",
+        DateFormat::Mdy => "mm/dd/yyyy",
+    }
+}
+
+pub fn excel_date_time() -> &'static str {
+    match get().date_format {
+        DateFormat::Dmy => "dd.mm.yyyy hh:mm",
+        DateFormat::Iso => "yyyy-mm-dd hh:mm",
+        DateFormat::Mdy => "mm/dd/yyyy hh:mm",
+    }
+}
@@ -0,0 +1,48 @@
+//! QR codes, drawn on the server as inline SVG, so a phone's camera can open
+//! what is on the screen: a todo's public link, or this instance at `/qr`.
+
+use axum::{response::Html, routing::get, Router};
+use qrcode::render::svg;
+use qrcode::QrCode;
+
+use crate::{base_path, html_escape, page_start, public_url, AppState, PAGE_END};
+
+/// Side of the drawn code in pixels, at least.
+const SIZE: u32 = 200;
+
+pub fn router() -> Router<AppState> {
+    Router::new().route("/qr", get(show))
+}
+
+/// `url` as an `<svg>` element, or nothing if it is too long for a QR code.
+pub fn svg(url: &str) -> String {
+    let Ok(code) = QrCode::new(url) else {
+        return String::new();
+    };
+    let image = code
+        .render::<svg::Color>()
+        .min_dimensions(SIZE, SIZE)
+        .build();
+    // Inline SVG must not carry an XML declaration.
+    match image.find("<svg") {
+        Some(start) => image[start..].to_string(),
+        None => image,
+    }
+}
+
+async fn show() -> Html<String> {
+    let url = public_url();
+    let mut body = page_start();
+    body.push_str(&format!(
+        r#"    <h1>Auf dem Handy öffnen</h1>
+    <div class="subtitle">Mit der Kamera des Handys scannen, um <a href="{url}/">{url}/</a> zu öffnen.</div>
+    <div class="qr">{code}</div>
+    <a class="back" href="{base}/">Zurück zur Liste</a>
+"#,
+        url = html_escape(url),
+        code = svg(&format!("{url}/")),
+        base = base_path(),
+    ));
+    body.push_str(PAGE_END);
+    Html(body)
+}
@@ -0,0 +1,237 @@
+//! Quick add: a whole todo typed on one line, e.g.
+//! `Bericht schreiben @freitag @büro //Umzug`.
+//!
+//! `@` followed by a day (`heute`, `morgen`, a weekday or a date) sets the
+//! deadline, any other `@word` the location, and `//goal` links the goal whose
+//! title is or starts with `goal`. Everything else is the title, including
+//! `#words` and `!words`, since todos have neither tags nor priorities.
+
+use axum::{
+    extract::{Form, State},
+    http::StatusCode,
+    response::{Html, IntoResponse, Response},
+    routing::post,
+    Router,
+};
+use chrono::{Datelike, Local, NaiveDate, TimeDelta, Weekday};
+use serde::Deserialize;
+
+use crate::models::Goal;
+use crate::validate::{self, FieldError, Invalid};
+use crate::{
+    base_path, error_status, html_escape, invalid_attr, redirect_home, render_index, AddForm,
+    AppState,
+};
+
+#[derive(Deserialize)]
+struct QuickForm {
+    text: String,
+}
+
+/// The fields found on a quick-add line.
+pub struct QuickAdd {
+    pub title: String,
+    pub deadline: Option<NaiveDate>,
+    pub location: Option<String>,
+    pub goal_id: Option<i64>,
+}
+
+pub fn router() -> Router<AppState> {
+    Router::new().route("/quick-add", post(quick_add))
+}
+
+async fn quick_add(
+    State(state): State<AppState>,
+    Form(form): Form<QuickForm>,
+) -> Result<Response, StatusCode> {
+    let db = state.db.lock();
+    let goals = db.list_goals().map_err(error_status)?;
+    let todo = match parse(&form.text, Local::now().date_naive(), &goals) {
+        Ok(todo) => todo,
+        Err(error) => {
+            let refill = AddForm {
+                quick: form.text,
+                ..Default::default()
+            };
+            let body = render_index(&db, &refill, Some(&Invalid(vec![error])), None, None, false)
+                .map_err(error_status)?;
+            return Ok((StatusCode::UNPROCESSABLE_ENTITY, Html(body)).into_response());
+        }
+    };
+
+    let tx = db.transaction().map_err(error_status)?;
+    let added = db
+        .add_todo(&todo.title, todo.deadline, todo.location.as_deref())
+        .map_err(error_status)?;
+    if let Some(goal_id) = todo.goal_id {
+        db.set_goal(added.id, goal_id).map_err(error_status)?;
+    }
+    tx.commit().map_err(|err| error_status(err.into()))?;
+    Ok(redirect_home())
+}
+
+pub fn parse(input: &str, today: NaiveDate, goals: &[Goal]) -> Result<QuickAdd, FieldError> {
+    let error = |message: String| FieldError {
+        field: "text",
+        message,
+    };
+
+    let mut words = Vec::new();
+    let mut deadline = None;
+    let mut location = None;
+    let mut goal_id = None;
+    for word in input.split_whitespace() {
+        if let Some(name) = word.strip_prefix("//").filter(|name| !name.is_empty()) {
+            let goal = find_goal(goals, name)
+                .ok_or_else(|| error(format!("Es gibt kein eindeutiges Ziel „{name}“.")))?;
+            goal_id = Some(goal.id);
+        } else if let Some(value) = word.strip_prefix('@').filter(|value| !value.is_empty()) {
+            match day(value, today) {
+                Some(date) => deadline = Some(date),
+                None => location = Some(word),
+            }
+        } else {
+            words.push(word);
+        }
+    }
+
+    let title = validate::title(&words.join(" ")).map_err(|err| error(err.message))?;
+    let location =
+        validate::location(location.unwrap_or_default()).map_err(|err| error(err.message))?;
+    Ok(QuickAdd {
+        title,
+        deadline,
+        location,
+        goal_id,
+    })
+}
+
+/// Reads `heute`, `morgen`, a weekday (the next one, not today) or a date.
+fn day(value: &str, today: NaiveDate) -> Option<NaiveDate> {
+    let lower = value.to_lowercase();
+    let weekday = match lower.as_str() {
+        "heute" | "today" => return Some(today),
+        "morgen" | "tomorrow" => return today.succ_opt(),
+        "übermorgen" => return today.checked_add_signed(TimeDelta::days(2)),
+        "montag" | "monday" | "mo" | "mon" => Weekday::Mon,
+        "dienstag" | "tuesday" | "di" | "tue" => Weekday::Tue,
+        "mittwoch" | "wednesday" | "mi" | "wed" => Weekday::Wed,
+        "donnerstag" | "thursday" | "do" | "thu" => Weekday::Thu,
+        "freitag" | "friday" | "fr" | "fri" => Weekday::Fri,
+        "samstag" | "saturday" | "sa" | "sat" => Weekday::Sat,
+        "sonntag" | "sunday" | "so" | "sun" => Weekday::Sun,
+        _ => return validate::deadline(value).ok().flatten(),
+    };
+    let ahead = (7 + weekday.num_days_from_monday() - today.weekday().num_days_from_monday()) % 7;
+    let ahead = if ahead == 0 { 7 } else { ahead };
+    today.checked_add_signed(TimeDelta::days(ahead.into()))
+}
+
+/// The goal titled `name`, or else the only one whose title starts with it,
+/// ignoring case.
+fn find_goal<'a>(goals: &'a [Goal], name: &str) -> Option<&'a Goal> {
+    let name = name.to_lowercase();
+    if let Some(goal) = goals.iter().find(|goal| goal.title.to_lowercase() == name) {
+        return Some(goal);
+    }
+    let mut matches = goals
+        .iter()
+        .filter(|goal| goal.title.to_lowercase().starts_with(&name));
+    match (matches.next(), matches.next()) {
+        (Some(goal), None) => Some(goal),
+        _ => None,
+    }
+}
+
+/// The quick-add form for the list page; `text` refills it after a rejected
+/// submission.
+pub fn form(text: &str, invalid: Option<&Invalid>) -> String {
+    format!(
+        r#"    <form method="post" action="{base}/quick-add">
+      <input type="text" name="text" placeholder="Schnell: Bericht schreiben @freitag @büro //Ziel" value="{text}" required{invalid} />
+      <button type="submit">Erfassen</button>
+    </form>
+"#,
+        base = base_path(),
+        text = html_escape(text),
+        invalid = invalid_attr(invalid, "text"),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::Utc;
+
+    use super::*;
+
+    fn goal(id: i64, title: &str) -> Goal {
+        Goal {
+            id,
+            title: title.to_string(),
+            created_at: Utc::now(),
+            todos_done: 0,
+            todos_total: 0,
+            color: None,
+            icon: None,
+        }
+    }
+
+    fn date(value: &str) -> NaiveDate {
+        value.parse().unwrap()
+    }
+
+    fn quick_add(input: &str, goals: &[Goal]) -> QuickAdd {
+        parse(input, today(), goals).unwrap_or_else(|error| panic!("{}", error.message))
+    }
+
+    /// A Friday.
+    fn today() -> NaiveDate {
+        date("2026-10-16")
+    }
+
+    #[test]
+    fn reads_deadline_location_and_goal() {
+        let goals = [goal(1, "Umzug"), goal(2, "Urlaub")];
+        let todo = quick_add("Bericht schreiben @freitag @büro //umz", &goals);
+        assert_eq!(todo.title, "Bericht schreiben");
+        assert_eq!(todo.deadline, Some(date("2026-10-23")));
+        assert_eq!(todo.location.as_deref(), Some("@büro"));
+        assert_eq!(todo.goal_id, Some(1));
+    }
+
+    #[test]
+    fn keeps_other_words_in_the_title() {
+        let todo = quick_add("  #wichtig !jetzt   @ mails  ", &[]);
+        assert_eq!(todo.title, "#wichtig !jetzt @ mails");
+        assert_eq!(todo.deadline, None);
+        assert_eq!(todo.location, None);
+        assert_eq!(todo.goal_id, None);
+    }
+
+    #[test]
+    fn reads_days() {
+        assert_eq!(day("heute", today()), Some(today()));
+        assert_eq!(day("Morgen", today()), Some(date("2026-10-17")));
+        assert_eq!(day("übermorgen", today()), Some(date("2026-10-18")));
+        assert_eq!(day("mo", today()), Some(date("2026-10-19")));
+        assert_eq!(day("24.12.2026", today()), Some(date("2026-12-24")));
+        assert_eq!(day("2027-01-02", today()), Some(date("2027-01-02")));
+        assert_eq!(day("büro", today()), None);
+    }
+
+    #[test]
+    fn rejects_unknown_or_ambiguous_goals() {
+        let goals = [goal(1, "Umzug"), goal(2, "Urlaub")];
+        assert!(parse("Kisten packen //Garten", today(), &goals).is_err());
+        assert!(parse("Kisten packen //u", today(), &goals).is_err());
+        // An exact title wins over other titles it is the start of.
+        let goals = [goal(1, "Haus"), goal(2, "Hausbau")];
+        assert_eq!(quick_add("Steine //haus", &goals).goal_id, Some(1));
+    }
+
+    #[test]
+    fn needs_a_title() {
+        let error = parse("@morgen @büro", today(), &[]).err().unwrap();
+        assert_eq!(error.field, "text");
+    }
+}
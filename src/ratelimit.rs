@@ -0,0 +1,203 @@
+//! Per-client request quota for the JSON API, so one runaway script can't
+//! hog the instance. The API has no tokens, so clients are told apart by
+//! address: the connection's, or behind a trusted proxy the one it forwards
+//! in `X-Forwarded-For`.
+
+use std::collections::HashMap;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+use axum::{
+    extract::{ConnectInfo, Request, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    middleware::Next,
+    response::{IntoResponse, Response},
+};
+
+use crate::{base_path, AppState};
+
+/// Quotas are counted in fixed windows of this length.
+const WINDOW: Duration = Duration::from_secs(60);
+
+/// Beyond this many tracked clients, expired windows are dropped.
+const PRUNE_ABOVE: usize = 1024;
+
+/// Requests per client and minute; `None` when quotas are off.
+#[derive(Clone)]
+pub struct RateLimiter {
+    limit: Option<u32>,
+    clients: Arc<Mutex<HashMap<IpAddr, Window>>>,
+    /// Proxies whose `X-Forwarded-For` is believed.
+    proxies: Arc<Vec<IpAddr>>,
+    /// Whether connections over a Unix socket come from a trusted proxy.
+    trust_unix: bool,
+}
+
+struct Window {
+    started: Instant,
+    requests: u32,
+}
+
+/// How a request fared against its client's quota.
+struct Verdict {
+    allowed: bool,
+    limit: u32,
+    remaining: u32,
+    reset: Duration,
+}
+
+impl RateLimiter {
+    /// `proxies` are addresses of trusted proxies, or `unix` for
+    /// connections over a Unix socket.
+    pub fn new(limit: u32, proxies: &[String]) -> anyhow::Result<Self> {
+        let mut addresses = Vec::new();
+        let mut trust_unix = false;
+        for proxy in proxies {
+            if proxy == "unix" {
+                trust_unix = true;
+                continue;
+            }
+            let address = proxy
+                .parse()
+                .map_err(|_| anyhow::anyhow!("invalid trusted proxy address {proxy:?}"))?;
+            addresses.push(address);
+        }
+        Ok(Self {
+            limit: (limit > 0).then_some(limit),
+            clients: Arc::new(Mutex::new(HashMap::new())),
+            proxies: Arc::new(addresses),
+            trust_unix,
+        })
+    }
+
+    /// The address a request counts against. Behind trusted proxies, that
+    /// is the last one in `X-Forwarded-For` that isn't a proxy itself;
+    /// entries left of it could be made up by the client.
+    fn client(&self, request: &Request) -> IpAddr {
+        // Connections over a Unix socket have no address of their own.
+        let peer = request
+            .extensions()
+            .get::<ConnectInfo<SocketAddr>>()
+            .map(|ConnectInfo(addr)| addr.ip());
+        let trusted = match peer {
+            Some(peer) => self.proxies.contains(&peer),
+            None => self.trust_unix,
+        };
+        let unspecified = IpAddr::V4(Ipv4Addr::UNSPECIFIED);
+        if !trusted {
+            return peer.unwrap_or(unspecified);
+        }
+
+        let forwarded: Vec<IpAddr> = request
+            .headers()
+            .get_all("x-forwarded-for")
+            .iter()
+            .filter_map(|value| value.to_str().ok())
+            .flat_map(|value| value.split(','))
+            .filter_map(|address| address.trim().parse().ok())
+            .collect();
+        forwarded
+            .iter()
+            .rev()
+            .find(|address| !self.proxies.contains(address))
+            .or(forwarded.first())
+            .copied()
+            .or(peer)
+            .unwrap_or(unspecified)
+    }
+
+    fn check(&self, client: IpAddr) -> Option<Verdict> {
+        let limit = self.limit?;
+        let now = Instant::now();
+        let mut clients = self.clients.lock().unwrap_or_else(PoisonError::into_inner);
+        if clients.len() > PRUNE_ABOVE {
+            clients.retain(|_, window| now - window.started < WINDOW);
+        }
+
+        let window = clients.entry(client).or_insert(Window {
+            started: now,
+            requests: 0,
+        });
+        if now - window.started >= WINDOW {
+            *window = Window {
+                started: now,
+                requests: 0,
+            };
+        }
+        let allowed = window.requests < limit;
+        if allowed {
+            window.requests += 1;
+        }
+        Some(Verdict {
+            allowed,
+            limit,
+            remaining: limit - window.requests,
+            reset: WINDOW.saturating_sub(now - window.started),
+        })
+    }
+}
+
+impl Verdict {
+    fn add_headers(&self, headers: &mut HeaderMap) {
+        // Round up so clients waiting that long are never a moment early.
+        let reset = self.reset.as_secs() + u64::from(self.reset.subsec_nanos() > 0);
+        headers.insert("x-ratelimit-limit", HeaderValue::from(self.limit));
+        headers.insert("x-ratelimit-remaining", HeaderValue::from(self.remaining));
+        headers.insert("x-ratelimit-reset", HeaderValue::from(reset));
+        if !self.allowed {
+            headers.insert(header::RETRY_AFTER, HeaderValue::from(reset));
+        }
+    }
+}
+
+/// Counts `/api` requests against the quota of the client's address and
+/// answers 429 once it is used up.
+pub async fn limit(State(state): State<AppState>, request: Request, next: Next) -> Response {
+    let path = request.uri().path();
+    let path = path.strip_prefix(base_path()).unwrap_or(path);
+    if !path.starts_with("/api/") {
+        return next.run(request).await;
+    }
+
+    let client = state.rate_limiter.client(&request);
+    let Some(verdict) = state.rate_limiter.check(client) else {
+        return next.run(request).await;
+    };
+
+    let mut response = if verdict.allowed {
+        next.run(request).await
+    } else {
+        StatusCode::TOO_MANY_REQUESTS.into_response()
+    };
+    verdict.add_headers(response.headers_mut());
+    response
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::body::Body;
+
+    use super::*;
+
+    fn request(peer: &str, forwarded: &str) -> Request {
+        let mut request = Request::builder()
+            .header("x-forwarded-for", forwarded)
+            .body(Body::empty())
+            .unwrap();
+        request
+            .extensions_mut()
+            .insert(ConnectInfo(SocketAddr::new(peer.parse().unwrap(), 40000)));
+        request
+    }
+
+    #[test]
+    fn believes_only_trusted_proxies() {
+        let limiter = RateLimiter::new(10, &["127.0.0.1".to_string()]).unwrap();
+        // The client made up the first entry; the proxy appended the second.
+        let client = limiter.client(&request("127.0.0.1", "1.2.3.4, 198.51.100.7"));
+        assert_eq!(client, "198.51.100.7".parse::<IpAddr>().unwrap());
+        let client = limiter.client(&request("203.0.113.9", "1.2.3.4"));
+        assert_eq!(client, "203.0.113.9".parse::<IpAddr>().unwrap());
+    }
+}
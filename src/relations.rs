@@ -0,0 +1,157 @@
+//! Typed links between todos (relates to, duplicate of, follows), shown on
+//! both todos' edit pages.
+
+use axum::{
+    extract::{Form, Path, State},
+    http::StatusCode,
+    response::{Html, IntoResponse, Redirect, Response},
+    routing::post,
+    Router,
+};
+use serde::Deserialize;
+
+use crate::models::{Relation, RelationKind};
+use crate::validate::{FieldError, Invalid};
+use crate::{
+    base_path, error_status, html_escape, invalid_attr, render_detail, AppState, DetailContext,
+    EditForm,
+};
+
+#[derive(Deserialize)]
+struct AddForm {
+    kind: String,
+    related: String,
+}
+
+/// A stored relation, in the direction it was added.
+#[derive(Deserialize)]
+struct DeleteForm {
+    todo_id: i64,
+    related_id: i64,
+    kind: String,
+}
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/todo/:id/relations", post(add_relation))
+        .route("/todo/:id/relations/delete", post(delete_relation))
+}
+
+async fn add_relation(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    Form(form): Form<AddForm>,
+) -> Result<Response, StatusCode> {
+    let kind = RelationKind::parse(&form.kind).ok_or(StatusCode::UNPROCESSABLE_ENTITY)?;
+    let db = state.db.lock();
+    let todo = db
+        .get_todo(id)
+        .map_err(error_status)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    let related = form.related.trim().trim_start_matches('#');
+    let message = match related.parse::<i64>() {
+        Err(_) => Some("Bitte gib die Nummer eines Todos ein.".to_string()),
+        Ok(related) if related == id => {
+            Some("Ein Todo kann nicht mit sich selbst verknüpft werden.".to_string())
+        }
+        Ok(related) if db.get_todo(related).map_err(error_status)?.is_none() => {
+            Some(format!("Es gibt kein Todo #{related}."))
+        }
+        Ok(related) => {
+            db.add_relation(id, related, kind).map_err(error_status)?;
+            None
+        }
+    };
+    if let Some(message) = message {
+        let invalid = Invalid(vec![FieldError {
+            field: "related",
+            message,
+        }]);
+        let context = DetailContext::load(&db, id).map_err(error_status)?;
+        let body = render_detail(
+            &todo,
+            &EditForm::from(&todo),
+            &context,
+            None,
+            Some(&invalid),
+        );
+        return Ok((StatusCode::UNPROCESSABLE_ENTITY, Html(body)).into_response());
+    }
+    Ok(Redirect::to(&format!("{}/todo/{id}", base_path())).into_response())
+}
+
+async fn delete_relation(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    Form(form): Form<DeleteForm>,
+) -> Result<Response, StatusCode> {
+    let kind = RelationKind::parse(&form.kind).ok_or(StatusCode::UNPROCESSABLE_ENTITY)?;
+    state
+        .db
+        .lock()
+        .delete_relation(form.todo_id, form.related_id, kind)
+        .map_err(error_status)?;
+    Ok(Redirect::to(&format!("{}/todo/{id}", base_path())).into_response())
+}
+
+/// The relations of todo `id` as links to the other todos, plus a form to
+/// add one; `invalid` marks a rejected todo number.
+pub fn section(id: i64, relations: &[Relation], invalid: Option<&Invalid>) -> String {
+    let base = base_path();
+    let mut section = String::from("    <h2>Verknüpfungen</h2>\n    <div class=\"todo-list\">\n");
+    for relation in relations {
+        let other = &relation.other;
+        let (label, todo_id, related_id) = if relation.outgoing {
+            (relation.kind.label(), id, other.id)
+        } else {
+            (relation.kind.inverse_label(), other.id, id)
+        };
+        let done = if other.completed_at.is_some() {
+            " ✓"
+        } else {
+            ""
+        };
+        section.push_str(&format!(
+            r#"<div class="todo">
+  <div class="meta">
+    <div class="time">{label}</div>
+    <div class="title"><a href="{base}/todo/{other_id}">#{other_id} {title}</a>{done}</div>
+  </div>
+  <div class="actions">
+    <form method="post" action="{base}/todo/{id}/relations/delete">
+      <input type="hidden" name="todo_id" value="{todo_id}" />
+      <input type="hidden" name="related_id" value="{related_id}" />
+      <input type="hidden" name="kind" value="{kind}" />
+      <button class="delete" type="submit">Entfernen</button>
+    </form>
+  </div>
+</div>
+"#,
+            other_id = other.id,
+            title = html_escape(&other.title),
+            kind = relation.kind.as_str(),
+        ));
+    }
+
+    let mut kinds = String::new();
+    for kind in RelationKind::ALL {
+        kinds.push_str(&format!(
+            "        <option value=\"{}\">{}</option>\n",
+            kind.as_str(),
+            kind.label()
+        ));
+    }
+    section.push_str(&format!(
+        r#"    </div>
+    <form method="post" action="{base}/todo/{id}/relations">
+      <select name="kind">
+{kinds}      </select>
+      <input type="text" name="related" placeholder="Todo-Nr." inputmode="numeric" required{invalid} />
+      <button type="submit">Verknüpfen</button>
+    </form>
+"#,
+        invalid = invalid_attr(invalid, "related"),
+    ));
+    section
+}
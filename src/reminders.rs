@@ -0,0 +1,380 @@
+//! Reminders for todos, and the background job that sends them together with
+//! notices about todos due today.
+//!
+//! Reminders either fall at a fixed time or a number of minutes before the
+//! todo's deadline, taken to be at [`DEADLINE_NOTICE_TIME`] on that day; the
+//! latter move along with the deadline. Todos that get a deadline receive the
+//! default offsets from the settings page.
+//!
+//! Todos left overdue for a while can be escalated: announced once more, on
+//! channels of their own, e.g. to someone else's address.
+//!
+//! Every notification has a key (`deadline:<todo>:<date>`,
+//! `reminder:<id>:<time>`, `escalation:<todo>:<deadline>`), and each attempt to send it on a channel is recorded in
+//! `notification_jobs`, so a restart neither repeats a notification nor
+//! loses one that failed; failed ones are retried on the next run.
+
+use std::sync::Arc;
+use std::time::Duration;
+
+use axum::{
+    extract::{Form, Path, State},
+    http::StatusCode,
+    response::{Html, IntoResponse, Redirect, Response},
+    routing::post,
+    Router,
+};
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeDelta, TimeZone, Utc};
+use serde::Deserialize;
+
+use crate::db::SharedDatabase;
+use crate::models::{Reminder, Todo};
+use crate::notify::{Notification, Notifier, MAX_ATTEMPTS};
+use crate::validate::{self, FieldError, Invalid};
+use crate::{
+    base_path, error_status, invalid_attr, preferences, render_detail, AppState, DetailContext,
+    EditForm,
+};
+
+/// How often the job looks for due notifications.
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Reminders missed by more than this, e.g. while the server was down, are
+/// dropped rather than sent late.
+const MAX_DELAY: TimeDelta = TimeDelta::hours(12);
+
+/// When todos due today are announced, in the server's time zone.
+pub const DEADLINE_NOTICE_TIME: NaiveTime = match NaiveTime::from_hms_opt(8, 0, 0) {
+    Some(time) => time,
+    None => panic!("invalid time"),
+};
+
+#[derive(Deserialize)]
+struct AddForm {
+    /// Local date and time from a `datetime-local` input.
+    #[serde(default)]
+    at: String,
+    /// Distance to the deadline instead, e.g. `2h`.
+    #[serde(default)]
+    offset: String,
+}
+
+#[derive(Deserialize)]
+struct DeleteForm {
+    reminder_id: i64,
+}
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/todo/:id/reminders", post(add_reminder))
+        .route("/todo/:id/reminders/delete", post(delete_reminder))
+}
+
+async fn add_reminder(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    Form(form): Form<AddForm>,
+) -> Result<Response, StatusCode> {
+    let db = state.db.lock();
+    let todo = db
+        .get_todo(id)
+        .map_err(error_status)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+
+    let reminder = match (form.offset.trim(), todo.deadline) {
+        ("", _) => match parse_local(form.at.trim()) {
+            Some(at) => Ok((at, None)),
+            None => Err(FieldError {
+                field: "at",
+                message: "Bitte gib Datum und Uhrzeit an.".to_string(),
+            }),
+        },
+        (_, None) => Err(FieldError {
+            field: "offset",
+            message: "Das Todo hat keine Deadline.".to_string(),
+        }),
+        (offset, Some(deadline)) => validate::reminder_offset(offset, "offset")
+            .map(|offset| (before_deadline(deadline, offset), Some(offset))),
+    };
+    let error = match reminder {
+        Ok((at, _)) if at <= Utc::now() => FieldError {
+            field: if form.offset.trim().is_empty() {
+                "at"
+            } else {
+                "offset"
+            },
+            message: "Der Zeitpunkt liegt in der Vergangenheit.".to_string(),
+        },
+        Ok((at, offset)) => {
+            db.add_reminder(id, at, offset).map_err(error_status)?;
+            return Ok(Redirect::to(&format!("{}/todo/{id}", base_path())).into_response());
+        }
+        Err(error) => error,
+    };
+    let invalid = Invalid(vec![error]);
+    let context = DetailContext::load(&db, id).map_err(error_status)?;
+    let body = render_detail(
+        &todo,
+        &EditForm::from(&todo),
+        &context,
+        None,
+        Some(&invalid),
+    );
+    Ok((StatusCode::UNPROCESSABLE_ENTITY, Html(body)).into_response())
+}
+
+async fn delete_reminder(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    Form(form): Form<DeleteForm>,
+) -> Result<Response, StatusCode> {
+    state
+        .db
+        .lock()
+        .delete_reminder(id, form.reminder_id)
+        .map_err(error_status)?;
+    Ok(Redirect::to(&format!("{}/todo/{id}", base_path())).into_response())
+}
+
+/// When a reminder `offset_minutes` before `deadline` is due.
+pub fn before_deadline(deadline: NaiveDate, offset_minutes: i64) -> DateTime<Utc> {
+    let notice = deadline.and_time(DEADLINE_NOTICE_TIME);
+    let notice = Local
+        .from_local_datetime(&notice)
+        .earliest()
+        .map_or_else(|| notice.and_utc(), |notice| notice.with_timezone(&Utc));
+    notice - TimeDelta::minutes(offset_minutes)
+}
+
+/// Reads a `datetime-local` value as the server's local time.
+fn parse_local(value: &str) -> Option<chrono::DateTime<Utc>> {
+    let naive = NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M")
+        .or_else(|_| NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S"))
+        .ok()?;
+    // A time skipped by a DST change doesn't exist; take the earlier of
+    // two that happen twice.
+    Some(
+        Local
+            .from_local_datetime(&naive)
+            .earliest()?
+            .with_timezone(&Utc),
+    )
+}
+
+/// The reminders of `todo` with buttons to remove them, plus forms to add
+/// one at a fixed time or, given a deadline, before it; `invalid` marks a
+/// rejected time.
+pub fn section(todo: &Todo, reminders: &[Reminder], invalid: Option<&Invalid>) -> String {
+    let base = base_path();
+    let id = todo.id;
+    let mut section = String::from("    <h2>Erinnerungen</h2>\n    <div class=\"todo-list\">\n");
+    for reminder in reminders {
+        let at = reminder.remind_at.with_timezone(&Local);
+        section.push_str(&format!(
+            r#"<div class="todo">
+  <div class="meta">
+    <div class="title">{at}</div>{offset}
+  </div>
+  <div class="actions">
+    <form method="post" action="{base}/todo/{id}/reminders/delete">
+      <input type="hidden" name="reminder_id" value="{reminder_id}" />
+      <button class="delete" type="submit">Entfernen</button>
+    </form>
+  </div>
+</div>
+"#,
+            at = at.format(preferences::date_time()),
+            offset = reminder
+                .offset_minutes
+                .map(|offset| format!(
+                    "\n    <div class=\"subtitle\">{} vor der Deadline</div>",
+                    offset_label(offset)
+                ))
+                .unwrap_or_default(),
+            reminder_id = reminder.id,
+        ));
+    }
+    section.push_str(&format!(
+        r#"    </div>
+    <form method="post" action="{base}/todo/{id}/reminders">
+      <input type="datetime-local" name="at" required{invalid} />
+      <button type="submit">Erinnern</button>
+    </form>
+"#,
+        invalid = invalid_attr(invalid, "at"),
+    ));
+    if todo.deadline.is_some() {
+        section.push_str(&format!(
+            r#"    <form method="post" action="{base}/todo/{id}/reminders">
+      <input type="text" name="offset" placeholder="z. B. 2h oder 3d" required{invalid} />
+      <button type="submit">Vor der Deadline erinnern</button>
+    </form>
+"#,
+            invalid = invalid_attr(invalid, "offset"),
+        ));
+    }
+    section
+}
+
+/// Reads e.g. `2 Stunden` for an offset in minutes.
+fn offset_label(minutes: i64) -> String {
+    let (count, one, many) = if minutes % (24 * 60) == 0 {
+        (minutes / (24 * 60), "Tag", "Tage")
+    } else if minutes % 60 == 0 {
+        (minutes / 60, "Stunde", "Stunden")
+    } else {
+        (minutes, "Minute", "Minuten")
+    };
+    format!("{count} {}", if count == 1 { one } else { many })
+}
+
+/// Where todos overdue for `after_days` days are announced.
+pub struct Escalation {
+    pub after_days: u32,
+    pub notifier: Notifier,
+}
+
+/// Sends due notifications through `notifier`, and escalations through their
+/// own channels, every minute. `public_url` is where links in notifications
+/// point to.
+pub fn spawn(
+    db: SharedDatabase,
+    notifier: Arc<Notifier>,
+    escalation: Option<Escalation>,
+    public_url: String,
+) {
+    let public_url = public_url.trim_end_matches('/').to_string();
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(CHECK_INTERVAL);
+        loop {
+            ticker.tick().await;
+            if db.is_read_only() {
+                continue;
+            }
+            if let Err(err) = notify_due(&db, &notifier, escalation.as_ref(), &public_url).await {
+                eprintln!("sending notifications failed: {err:#}");
+            }
+        }
+    });
+}
+
+async fn notify_due(
+    db: &SharedDatabase,
+    notifier: &Notifier,
+    escalation: Option<&Escalation>,
+    public_url: &str,
+) -> anyhow::Result<()> {
+    let now = Local::now();
+    let today = now.date_naive();
+    // Collected first so the database lock is not held while sending.
+    let mut pending = Vec::new();
+    {
+        let db = db.lock();
+        let mut due = Vec::new();
+        if now.time() >= DEADLINE_NOTICE_TIME {
+            for todo in db.todos_due_on(today)? {
+                let key = format!("deadline:{}:{today}", todo.id);
+                due.push((notifier, key, deadline_notification(todo, public_url)));
+            }
+            if let Some(escalation) = escalation {
+                // Overdue by `after_days` days is a deadline before the day
+                // after this many days ago.
+                let cutoff = today - TimeDelta::days(i64::from(escalation.after_days) - 1);
+                for todo in db.overdue_todos(cutoff)? {
+                    let Some(deadline) = todo.deadline else {
+                        continue;
+                    };
+                    let key = format!("escalation:{}:{deadline}", todo.id);
+                    let notification = escalation_notification(todo, today, public_url);
+                    due.push((&escalation.notifier, key, notification));
+                }
+            }
+        }
+        let now = now.with_timezone(&Utc);
+        for (reminder, todo) in db.due_reminders(now - MAX_DELAY, now)? {
+            // With the time in the key, a reminder that moves with its
+            // deadline is sent again at its new time.
+            let key = format!(
+                "reminder:{}:{}",
+                reminder.id,
+                reminder.remind_at.timestamp()
+            );
+            due.push((notifier, key, reminder_notification(todo, public_url)));
+        }
+
+        for (notifier, key, notification) in due {
+            let mut channels = Vec::new();
+            for channel in notifier.channels() {
+                if db.notification_pending(&key, channel.name(), MAX_ATTEMPTS)? {
+                    channels.push(channel);
+                }
+            }
+            if !channels.is_empty() {
+                pending.push((notifier, key, notification, channels));
+            }
+        }
+    }
+
+    for (notifier, key, notification, channels) in pending {
+        for channel in channels {
+            let error = match notifier.send(channel, &notification).await {
+                Ok(()) => None,
+                Err(err) => {
+                    eprintln!("{err:#}");
+                    Some(format!("{err:#}"))
+                }
+            };
+            db.lock()
+                .record_notification(&key, channel.name(), error.as_deref())?;
+        }
+    }
+    Ok(())
+}
+
+fn deadline_notification(todo: Todo, public_url: &str) -> Notification {
+    Notification {
+        event: "deadline",
+        title: format!("Heute fällig: {}", todo.title),
+        body: format!("Todo #{} ist heute fällig.", todo.id),
+        url: format!("{public_url}/todo/{}", todo.id),
+        todo,
+    }
+}
+
+fn escalation_notification(todo: Todo, today: NaiveDate, public_url: &str) -> Notification {
+    let deadline = todo.deadline.unwrap_or(today);
+    let days = (today - deadline).num_days();
+    Notification {
+        event: "escalation",
+        title: format!(
+            "Seit {days} {} überfällig: {}",
+            if days == 1 { "Tag" } else { "Tagen" },
+            todo.title
+        ),
+        body: format!(
+            "Todo #{} war am {} fällig und ist noch offen.",
+            todo.id,
+            deadline.format(preferences::date())
+        ),
+        url: format!("{public_url}/todo/{}", todo.id),
+        todo,
+    }
+}
+
+fn reminder_notification(todo: Todo, public_url: &str) -> Notification {
+    let body = match todo.deadline {
+        Some(deadline) => format!(
+            "Todo #{}, fällig am {}.",
+            todo.id,
+            deadline.format(preferences::date())
+        ),
+        None => format!("Todo #{}.", todo.id),
+    };
+    Notification {
+        event: "reminder",
+        title: format!("Erinnerung: {}", todo.title),
+        body,
+        url: format!("{public_url}/todo/{}", todo.id),
+        todo,
+    }
+}
@@ -0,0 +1,301 @@
+//! Continuous replication into a directory, e.g. on another disk or a
+//! mounted bucket. The database runs in WAL mode, and the frames of each
+//! committed transaction are shipped within a second. Every hour a new
+//! generation starts with a full copy of the database, followed by the WAL
+//! written since, so `simpletodo restore` can rebuild the database as it was
+//! at any moment the kept generations cover.
+
+use std::fs::{self, File, OpenOptions};
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, NaiveDateTime, Utc};
+use rusqlite::Connection;
+
+use crate::db::{Database, SharedDatabase};
+
+/// How often new WAL frames are looked for.
+const SHIP_INTERVAL: Duration = Duration::from_secs(1);
+/// How long a generation grows before the next one starts.
+const GENERATION_INTERVAL: Duration = Duration::from_secs(60 * 60);
+/// Generation directories are named after their start, in UTC.
+const GENERATION_FORMAT: &str = "%Y%m%d-%H%M%S";
+
+const BASE: &str = "base.db";
+const WAL: &str = "wal";
+/// One line per shipment: the WAL's length after it, and when it was sent.
+const INDEX: &str = "index";
+
+const WAL_HEADER: usize = 32;
+const FRAME_HEADER: usize = 24;
+
+/// Ships the WAL of the database into `dir`.
+pub struct Replicator {
+    db: SharedDatabase,
+    dir: PathBuf,
+    keep: usize,
+    key: Option<String>,
+    wal: PathBuf,
+    generation: Generation,
+    /// Database generation at the last look, to skip unchanged seconds.
+    seen: u64,
+}
+
+struct Generation {
+    dir: PathBuf,
+    started: Instant,
+    /// Bytes of the WAL already in the replica.
+    shipped: usize,
+    /// Salt of the WAL header when the generation started shipping; a
+    /// different one means the WAL was reset.
+    salt: Option<[u8; 8]>,
+}
+
+impl Replicator {
+    /// Switches the database to WAL mode and starts the first generation.
+    /// `key` unlocks an encrypted database; the copies stay encrypted.
+    pub fn start(
+        db: SharedDatabase,
+        dir: PathBuf,
+        keep: usize,
+        key: Option<String>,
+    ) -> Result<Self> {
+        let guard = db.lock();
+        let wal = guard.enable_wal()?;
+        let generation = start_generation(&guard, &dir, key.as_deref())?;
+        drop(guard);
+        let replicator = Self {
+            seen: db.generation(),
+            db,
+            dir,
+            keep: keep.max(1),
+            key,
+            wal,
+            generation,
+        };
+        replicator.prune()?;
+        Ok(replicator)
+    }
+
+    /// Ships new frames every second and starts a new generation every hour.
+    pub fn spawn(mut self) {
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(SHIP_INTERVAL);
+            loop {
+                ticker.tick().await;
+                let job = tokio::task::spawn_blocking(move || {
+                    let result = self.run();
+                    (self, result)
+                });
+                match job.await {
+                    Ok((replicator, result)) => {
+                        self = replicator;
+                        if let Err(err) = result {
+                            eprintln!("replication failed: {err:#}");
+                        }
+                    }
+                    Err(err) => {
+                        eprintln!("replication panicked, stopping: {err}");
+                        return;
+                    }
+                }
+            }
+        });
+    }
+
+    fn run(&mut self) -> Result<()> {
+        let rotate = self.generation.started.elapsed() >= GENERATION_INTERVAL;
+        let generation = self.db.generation();
+        if generation == self.seen && !rotate {
+            return Ok(());
+        }
+        // Holding the lock keeps writes out while the WAL is read.
+        let shared = self.db.clone();
+        let db = shared.lock();
+        let shipped = self.ship();
+        self.seen = generation;
+        if (rotate || shipped.is_err()) && !self.db.is_read_only() {
+            self.generation = start_generation(&db, &self.dir, self.key.as_deref())?;
+            drop(db);
+            self.prune()?;
+        }
+        shipped
+    }
+
+    /// Appends the WAL's committed frames since the last shipment.
+    fn ship(&mut self) -> Result<()> {
+        let wal = match fs::read(&self.wal) {
+            Ok(wal) => wal,
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+            Err(err) => return Err(err).context("reading the WAL"),
+        };
+        let generation = &mut self.generation;
+        if wal.len() < WAL_HEADER {
+            if generation.shipped > 0 {
+                anyhow::bail!("the WAL was emptied by another connection");
+            }
+            return Ok(());
+        }
+        let salt: [u8; 8] = wal[16..24].try_into().expect("eight bytes");
+        if generation.salt.is_some_and(|shipped| shipped != salt) || wal.len() < generation.shipped
+        {
+            anyhow::bail!("the WAL was reset by another connection");
+        }
+
+        let page_size = u32::from_be_bytes(wal[8..12].try_into().expect("four bytes")) as usize;
+        let frame = FRAME_HEADER + page_size;
+        let mut offset = generation.shipped.max(WAL_HEADER);
+        let mut end = generation.shipped;
+        while offset + frame <= wal.len() {
+            let header = &wal[offset..offset + FRAME_HEADER];
+            // Frames left over from before the WAL last restarted.
+            if header[8..16] != salt {
+                break;
+            }
+            offset += frame;
+            // Only a transaction's last frame carries the database size.
+            if header[4..8] != [0; 4] {
+                end = offset;
+            }
+        }
+        if end <= generation.shipped {
+            return Ok(());
+        }
+
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(generation.dir.join(WAL))?;
+        file.write_all(&wal[generation.shipped..end])?;
+        file.sync_data()?;
+        let mut index = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(generation.dir.join(INDEX))?;
+        writeln!(index, "{end} {}", Utc::now().to_rfc3339())?;
+        index.sync_data()?;
+        generation.shipped = end;
+        generation.salt = Some(salt);
+        Ok(())
+    }
+
+    /// Deletes all but the newest `keep` generations.
+    fn prune(&self) -> Result<()> {
+        let generations = generations(&self.dir)?;
+        let excess = generations.len().saturating_sub(self.keep);
+        for (_, path) in &generations[..excess] {
+            fs::remove_dir_all(path)
+                .with_context(|| format!("removing old generation {}", path.display()))?;
+        }
+        Ok(())
+    }
+}
+
+/// Empties the WAL into the database file and copies that as the base of a
+/// new generation.
+fn start_generation(db: &Database, dir: &Path, key: Option<&str>) -> Result<Generation> {
+    db.checkpoint()?;
+    let started_at = Utc::now();
+    let path = dir.join(started_at.format(GENERATION_FORMAT).to_string());
+    fs::create_dir_all(&path).with_context(|| format!("creating {}", path.display()))?;
+    // Only complete copies get the name that restores look for.
+    let partial = path.join(format!("{BASE}.partial"));
+    if let Err(err) = db.backup_to(&partial, key) {
+        let _ = fs::remove_file(&partial);
+        return Err(err);
+    }
+    fs::rename(&partial, path.join(BASE))?;
+    File::create(path.join(INDEX))?;
+    Ok(Generation {
+        dir: path,
+        started: Instant::now(),
+        shipped: 0,
+        salt: None,
+    })
+}
+
+/// Complete generations in `dir` with their start, oldest first.
+fn generations(dir: &Path) -> Result<Vec<(DateTime<Utc>, PathBuf)>> {
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(err.into()),
+    };
+    let mut generations = Vec::new();
+    for entry in entries {
+        let path = entry?.path();
+        let started = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| NaiveDateTime::parse_from_str(name, GENERATION_FORMAT).ok());
+        if let Some(started) = started {
+            if path.join(BASE).is_file() {
+                generations.push((started.and_utc(), path));
+            }
+        }
+    }
+    generations.sort();
+    Ok(generations)
+}
+
+/// Rebuilds the database from the replica in `dir` as of `at`, or as of the
+/// last shipment, into the new file `output`. Returns the moment restored.
+pub fn restore(
+    dir: &Path,
+    at: Option<DateTime<Utc>>,
+    output: &Path,
+    key: Option<&str>,
+) -> Result<DateTime<Utc>> {
+    if output.exists() {
+        anyhow::bail!("{} already exists", output.display());
+    }
+    let (started, generation) = generations(dir)?
+        .into_iter()
+        .rev()
+        .find(|(started, _)| at.is_none_or(|at| *started <= at))
+        .with_context(|| format!("no generation in {} covers that time", dir.display()))?;
+
+    // The last shipment up to `at`; without one, the base alone.
+    let mut restored_at = started;
+    let mut length = 0;
+    let index = fs::read_to_string(generation.join(INDEX))?;
+    for line in index.lines() {
+        let Some((end, sent_at)) = line.split_once(' ') else {
+            continue;
+        };
+        let (Ok(end), Ok(sent_at)) = (end.parse::<usize>(), DateTime::parse_from_rfc3339(sent_at))
+        else {
+            continue;
+        };
+        let sent_at = sent_at.with_timezone(&Utc);
+        if at.is_some_and(|at| sent_at > at) {
+            break;
+        }
+        length = end;
+        restored_at = sent_at;
+    }
+
+    fs::copy(generation.join(BASE), output)
+        .with_context(|| format!("creating {}", output.display()))?;
+    if length > 0 {
+        let wal = fs::read(generation.join(WAL))?;
+        let wal = wal
+            .get(..length)
+            .context("the replica's WAL is shorter than its index")?;
+        fs::write(format!("{}-wal", output.display()), wal)?;
+    }
+    // Opening replays the WAL; the checkpoint writes it into the file.
+    let conn = Connection::open(output)?;
+    if let Some(key) = key {
+        conn.pragma_update(None, "key", key)?;
+    }
+    conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))?;
+    conn.pragma_update(None, "journal_mode", "delete")?;
+    let check: String = conn.query_row("PRAGMA quick_check", [], |row| row.get(0))?;
+    if check != "ok" {
+        anyhow::bail!("the restored database is damaged: {check}");
+    }
+    Ok(restored_at)
+}
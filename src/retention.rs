@@ -0,0 +1,59 @@
+//! Retention: completed todos, audit log entries and pomodoros older than
+//! set under Einstellungen are deleted, or for todos anonymized, by a job
+//! that runs hourly.
+
+use std::time::Duration;
+
+use chrono::{DateTime, Months, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::db::SharedDatabase;
+
+/// How often the job looks for data past its retention.
+const CHECK_INTERVAL: Duration = Duration::from_secs(60 * 60);
+
+/// How many months each kind of data is kept; 0 keeps it for good.
+#[derive(Clone, Copy, Default, Serialize, Deserialize)]
+pub struct RetentionPolicy {
+    /// Counted from completion.
+    pub todos: u32,
+    /// Clears the title and location of completed todos instead of deleting
+    /// them, which keeps statistics intact.
+    pub anonymize: bool,
+    pub audit: u32,
+    /// Counted from when the pomodoro finished.
+    pub pomodoros: u32,
+}
+
+/// The moment before which data kept for `months` is due; `None` for 0.
+pub fn cutoff(now: DateTime<Utc>, months: u32) -> Option<DateTime<Utc>> {
+    if months == 0 {
+        return None;
+    }
+    now.checked_sub_months(Months::new(months))
+}
+
+/// Applies the retention policy, now and then hourly.
+pub fn spawn(db: SharedDatabase) {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(CHECK_INTERVAL);
+        loop {
+            ticker.tick().await;
+            if db.is_read_only() {
+                continue;
+            }
+            let db = db.clone();
+            let job = tokio::task::spawn_blocking(move || {
+                let db = db.lock();
+                let policy = db.retention_policy()?;
+                db.apply_retention(&policy, Utc::now())
+            });
+            match job.await {
+                Ok(Ok(0)) => {}
+                Ok(Ok(count)) => println!("retention removed or anonymized {count} rows"),
+                Ok(Err(err)) => eprintln!("applying retention failed: {err:#}"),
+                Err(err) => eprintln!("applying retention panicked: {err}"),
+            }
+        }
+    });
+}
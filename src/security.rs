@@ -0,0 +1,74 @@
+//! Security headers on every response. The default Content-Security-Policy
+//! only lets pages load styles and scripts from this server, which is why
+//! neither is ever inlined; the referrer policy keeps share links and other
+//! addresses from leaking to the sites notes link to.
+
+use std::sync::Arc;
+
+use axum::{
+    extract::{Request, State},
+    http::{header, HeaderName, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+
+/// Images may come from anywhere secure, e.g. in a note's Markdown.
+pub const DEFAULT_CSP: &str =
+    "default-src 'self'; img-src 'self' https: data:; object-src 'none'; base-uri 'none'";
+
+/// The headers to add, as configured.
+#[derive(Clone)]
+pub struct SecurityHeaders(Arc<Vec<(HeaderName, HeaderValue)>>);
+
+impl SecurityHeaders {
+    /// `frame_ancestors` is appended to the policy as its own directive. An
+    /// empty value leaves out the header or directive.
+    pub fn new(
+        content_security_policy: &str,
+        frame_ancestors: &str,
+        referrer_policy: &str,
+    ) -> anyhow::Result<Self> {
+        let mut policy = Vec::new();
+        let content_security_policy = content_security_policy.trim().trim_end_matches(';');
+        if !content_security_policy.is_empty() {
+            policy.push(content_security_policy.to_string());
+        }
+        let frame_ancestors = frame_ancestors.trim();
+        if !frame_ancestors.is_empty() {
+            policy.push(format!("frame-ancestors {frame_ancestors}"));
+        }
+
+        let mut headers = vec![(
+            header::X_CONTENT_TYPE_OPTIONS,
+            HeaderValue::from_static("nosniff"),
+        )];
+        for (name, value) in [
+            (header::CONTENT_SECURITY_POLICY, policy.join("; ")),
+            (header::REFERRER_POLICY, referrer_policy.trim().to_string()),
+        ] {
+            if value.is_empty() {
+                continue;
+            }
+            let value = HeaderValue::from_str(&value)
+                .map_err(|_| anyhow::anyhow!("invalid {name} header {value:?}"))?;
+            headers.push((name, value));
+        }
+        Ok(Self(Arc::new(headers)))
+    }
+}
+
+/// Adds the headers that the handler hasn't set itself.
+pub async fn apply(
+    State(headers): State<SecurityHeaders>,
+    request: Request,
+    next: Next,
+) -> Response {
+    let mut response = next.run(request).await;
+    for (name, value) in headers.0.iter() {
+        response
+            .headers_mut()
+            .entry(name)
+            .or_insert_with(|| value.clone());
+    }
+    response
+}
@@ -0,0 +1,66 @@
+//! Synthetic data for measuring performance with realistically sized lists.
+
+use anyhow::Result;
+use chrono::{TimeDelta, Utc};
+use rand::seq::SliceRandom;
+use rand::Rng;
+
+use crate::db::Database;
+
+const SUBJECTS: &[&str] = &[
+    "Milch",
+    "Steuererklärung",
+    "Fahrrad",
+    "Keller",
+    "Geburtstagsgeschenk",
+    "Rechnung",
+    "Präsentation",
+    "Arzttermin",
+    "Wohnung",
+    "Bericht",
+];
+
+const VERBS: &[&str] = &[
+    "kaufen",
+    "erledigen",
+    "reparieren",
+    "aufräumen",
+    "besorgen",
+    "bezahlen",
+    "vorbereiten",
+    "vereinbaren",
+];
+
+const LOCATIONS: &[&str] = &["@home", "@office", "@unterwegs", "52.5163,13.3777"];
+
+/// Adds `count` todos in one transaction; about a third get a deadline and
+/// about 40% are completed.
+pub fn run(db: &Database, count: usize) -> Result<()> {
+    let mut rng = rand::thread_rng();
+    let today = Utc::now().date_naive();
+
+    let tx = db.transaction()?;
+    for n in 1..=count {
+        let title = format!(
+            "{} {} #{n}",
+            SUBJECTS.choose(&mut rng).expect("subjects are not empty"),
+            VERBS.choose(&mut rng).expect("verbs are not empty"),
+        );
+        let deadline = rng
+            .gen_bool(0.3)
+            .then(|| today + TimeDelta::days(rng.gen_range(-30..90)));
+
+        let location = rng
+            .gen_bool(0.5)
+            .then(|| *LOCATIONS.choose(&mut rng).expect("locations are not empty"));
+
+        let todo = db.add_todo(&title, deadline, location)?;
+        if rng.gen_bool(0.4) {
+            db.complete_todo(todo.id)?;
+        }
+    }
+    tx.commit()?;
+
+    println!("added {count} todos");
+    Ok(())
+}
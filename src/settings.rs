@@ -0,0 +1,353 @@
+//! The settings page: preferences kept in the database's `settings` table,
+//! and the workflow statuses.
+
+use axum::{
+    extract::{Form, State},
+    http::StatusCode,
+    response::{Html, IntoResponse, Redirect, Response},
+    routing::{get, post},
+    Router,
+};
+use serde::Deserialize;
+
+use crate::agenda;
+use crate::aging::AgeThresholds;
+use crate::db::Database;
+use crate::preferences::{self, DateFormat, WEEK_STARTS};
+use crate::reminders::DEADLINE_NOTICE_TIME;
+use crate::retention::RetentionPolicy;
+use crate::statuses;
+use crate::validate::{self, Invalid};
+use crate::{
+    base_path, error_notice, error_status, html_escape, invalid_attr, page_start, AppState,
+    PAGE_END,
+};
+
+#[derive(Deserialize)]
+struct PostponeForm {
+    /// Checkbox; present when every overdue todo moves to today.
+    enabled: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct AgingForm {
+    #[serde(default)]
+    aging: String,
+    #[serde(default)]
+    stale: String,
+}
+
+#[derive(Deserialize)]
+struct DisplayForm {
+    #[serde(default)]
+    week_start: String,
+    #[serde(default)]
+    date_format: String,
+}
+
+#[derive(Deserialize)]
+struct TrashForm {
+    #[serde(default)]
+    days: String,
+}
+
+#[derive(Deserialize)]
+struct RetentionForm {
+    #[serde(default)]
+    todos: String,
+    /// Checkbox; present when completed todos are anonymized, not deleted.
+    anonymize: Option<String>,
+    #[serde(default)]
+    audit: String,
+    #[serde(default)]
+    pomodoros: String,
+}
+
+#[derive(Deserialize)]
+struct SettingsForm {
+    /// Default reminder offsets, e.g. `3d, 1d, 2h`.
+    #[serde(default)]
+    offsets: String,
+}
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/settings", get(show).post(save))
+        .route("/settings/postpone", post(save_postpone))
+        .route("/settings/display", post(save_display))
+        .route("/settings/aging", post(save_aging))
+        .route("/settings/trash", post(save_trash))
+        .route("/settings/retention", post(save_retention))
+}
+
+async fn show(State(state): State<AppState>) -> Result<Html<String>, StatusCode> {
+    let body = render(&state.db.lock(), None, "", None).map_err(error_status)?;
+    Ok(Html(body))
+}
+
+async fn save(
+    State(state): State<AppState>,
+    Form(form): Form<SettingsForm>,
+) -> Result<Response, StatusCode> {
+    let db = state.db.lock();
+    let offsets = match validate::reminder_offsets(&form.offsets) {
+        Ok(offsets) => offsets,
+        Err(error) => {
+            let invalid = Invalid(vec![error]);
+            let body =
+                render(&db, Some(&form.offsets), "", Some(&invalid)).map_err(error_status)?;
+            return Ok((StatusCode::UNPROCESSABLE_ENTITY, Html(body)).into_response());
+        }
+    };
+    db.set_default_reminder_offsets(&offsets)
+        .map_err(error_status)?;
+    Ok(Redirect::to(&format!("{}/settings", base_path())).into_response())
+}
+
+async fn save_postpone(
+    State(state): State<AppState>,
+    Form(form): Form<PostponeForm>,
+) -> Result<Response, StatusCode> {
+    state
+        .db
+        .lock()
+        .set_auto_postpone(form.enabled.is_some())
+        .map_err(error_status)?;
+    Ok(Redirect::to(&format!("{}/settings", base_path())).into_response())
+}
+
+async fn save_display(
+    State(state): State<AppState>,
+    Form(form): Form<DisplayForm>,
+) -> Result<Response, StatusCode> {
+    let db = state.db.lock();
+    let choice = match validate::preferences(&form.week_start, &form.date_format) {
+        Ok(choice) => choice,
+        Err(invalid) => {
+            let body = render(&db, None, "", Some(&invalid)).map_err(error_status)?;
+            return Ok((StatusCode::UNPROCESSABLE_ENTITY, Html(body)).into_response());
+        }
+    };
+    preferences::set(&db, choice).map_err(error_status)?;
+    Ok(Redirect::to(&format!("{}/settings", base_path())).into_response())
+}
+
+async fn save_aging(
+    State(state): State<AppState>,
+    Form(form): Form<AgingForm>,
+) -> Result<Response, StatusCode> {
+    let db = state.db.lock();
+    let (aging, stale) = match validate::age_thresholds(&form.aging, &form.stale) {
+        Ok(thresholds) => thresholds,
+        Err(invalid) => {
+            let body = render(&db, None, "", Some(&invalid)).map_err(error_status)?;
+            return Ok((StatusCode::UNPROCESSABLE_ENTITY, Html(body)).into_response());
+        }
+    };
+    db.set_age_thresholds(AgeThresholds { aging, stale })
+        .map_err(error_status)?;
+    Ok(Redirect::to(&format!("{}/settings", base_path())).into_response())
+}
+
+async fn save_trash(
+    State(state): State<AppState>,
+    Form(form): Form<TrashForm>,
+) -> Result<Response, StatusCode> {
+    let db = state.db.lock();
+    let days = match validate::days(&form.days, "days") {
+        Ok(days) => days,
+        Err(error) => {
+            let invalid = Invalid(vec![error]);
+            let body = render(&db, None, "", Some(&invalid)).map_err(error_status)?;
+            return Ok((StatusCode::UNPROCESSABLE_ENTITY, Html(body)).into_response());
+        }
+    };
+    db.set_trash_retention(days).map_err(error_status)?;
+    Ok(Redirect::to(&format!("{}/settings", base_path())).into_response())
+}
+
+async fn save_retention(
+    State(state): State<AppState>,
+    Form(form): Form<RetentionForm>,
+) -> Result<Response, StatusCode> {
+    let db = state.db.lock();
+    let fields = [
+        validate::months(&form.todos, "todos"),
+        validate::months(&form.audit, "audit"),
+        validate::months(&form.pomodoros, "pomodoros"),
+    ];
+    let policy = match fields {
+        [Ok(todos), Ok(audit), Ok(pomodoros)] => RetentionPolicy {
+            todos,
+            anonymize: form.anonymize.is_some(),
+            audit,
+            pomodoros,
+        },
+        fields => {
+            let invalid = Invalid(fields.into_iter().filter_map(Result::err).collect());
+            let body = render(&db, None, "", Some(&invalid)).map_err(error_status)?;
+            return Ok((StatusCode::UNPROCESSABLE_ENTITY, Html(body)).into_response());
+        }
+    };
+    db.set_retention_policy(&policy).map_err(error_status)?;
+    Ok(Redirect::to(&format!("{}/settings", base_path())).into_response())
+}
+
+/// Writes an offset in minutes the way it is typed, in the largest whole unit.
+fn offset_input(minutes: i64) -> String {
+    if minutes % (24 * 60) == 0 {
+        format!("{}d", minutes / (24 * 60))
+    } else if minutes % 60 == 0 {
+        format!("{}h", minutes / 60)
+    } else {
+        format!("{minutes}m")
+    }
+}
+
+/// The settings page. `offsets` and `status_name` refill the forms after a
+/// rejected submission; without `offsets`, the stored ones are shown.
+pub fn render(
+    db: &Database,
+    offsets: Option<&str>,
+    status_name: &str,
+    invalid: Option<&Invalid>,
+) -> anyhow::Result<String> {
+    let offsets = match offsets {
+        Some(offsets) => offsets.to_string(),
+        None => {
+            let offsets: Vec<String> = db
+                .default_reminder_offsets()?
+                .into_iter()
+                .map(offset_input)
+                .collect();
+            offsets.join(", ")
+        }
+    };
+    let base = base_path();
+    let mut body = page_start();
+    body.push_str("    <h1>Einstellungen</h1>\n");
+    if let Some(invalid) = invalid {
+        body.push_str(&error_notice(invalid));
+    }
+    body.push_str(&format!(
+        r#"    <h2>Erinnerungen vor der Deadline</h2>
+    <form class="stacked" method="post" action="{base}/settings">
+      <input type="text" name="offsets" value="{offsets}" placeholder="z. B. 3d, 1d, 2h"{offsets_invalid} />
+      <div class="subtitle">Gilt für Todos, die ab jetzt eine Deadline bekommen; einzelne Erinnerungen lassen sich beim Todo ändern. Die Deadline zählt als {notice} Uhr an dem Tag.</div>
+      <button type="submit">Speichern</button>
+    </form>
+"#,
+        offsets = html_escape(&offsets),
+        offsets_invalid = invalid_attr(invalid, "offsets"),
+        notice = DEADLINE_NOTICE_TIME.format("%H:%M"),
+    ));
+    body.push_str(&format!(
+        r#"    <h2>Überfällige Todos</h2>
+    <form method="post" action="{base}/settings/postpone">
+      <label><input type="checkbox" name="enabled" value="1"{checked} /> Jeden Morgen alle überfälligen Todos auf heute verschieben</label>
+      <div class="subtitle">Sonst nur die, bei denen das einzeln eingestellt ist. Wie oft ein Todo verschoben wurde, steht auf seiner Seite.</div>
+      <button type="submit">Speichern</button>
+    </form>
+"#,
+        checked = if db.auto_postpone()? { " checked" } else { "" },
+    ));
+    let current = preferences::get();
+    let mut week_starts = String::new();
+    for day in WEEK_STARTS {
+        week_starts.push_str(&format!(
+            "        <option value=\"{day}\"{selected}>{name}</option>\n",
+            selected = if day == current.week_start {
+                " selected"
+            } else {
+                ""
+            },
+            name = agenda::weekday_name(day),
+        ));
+    }
+    let mut date_formats = String::new();
+    for format in DateFormat::ALL {
+        date_formats.push_str(&format!(
+            "        <option value=\"{name}\"{selected}>{example}</option>\n",
+            name = format.name(),
+            selected = if format == current.date_format {
+                " selected"
+            } else {
+                ""
+            },
+            example = format.example(),
+        ));
+    }
+    body.push_str(&format!(
+        r#"    <h2>Anzeige</h2>
+    <form method="post" action="{base}/settings/display">
+      <select name="week_start" title="Die Woche beginnt am"{week_start_invalid}>
+{week_starts}      </select>
+      <select name="date_format" title="Datumsformat"{date_format_invalid}>
+{date_formats}      </select>
+      <button type="submit">Speichern</button>
+    </form>
+    <div class="subtitle">Der erste Wochentag gilt für die Wochen in der Liste, im Kalender einer Gewohnheit, im Gantt-Diagramm und in der Statistik; das Datumsformat für alle Seiten, Mails und den Excel-Export.</div>
+"#,
+        week_start_invalid = invalid_attr(invalid, "week_start"),
+        date_format_invalid = invalid_attr(invalid, "date_format"),
+    ));
+    let thresholds = db.age_thresholds()?;
+    body.push_str(&format!(
+        r#"    <h2>Alte Todos</h2>
+    <form method="post" action="{base}/settings/aging">
+      <input type="number" name="aging" value="{aging}" min="0" max="{max}" title="Angestaubt nach Tagen"{aging_invalid} />
+      <input type="number" name="stale" value="{stale}" min="0" max="{max}" title="Vergessen nach Tagen"{stale_invalid} />
+      <button type="submit">Speichern</button>
+    </form>
+    <div class="subtitle">Offene Todos werden nach so vielen Tagen seit dem Anlegen gelb (angestaubt) und dann rot (vergessen) markiert; 0 schaltet eine Stufe ab.</div>
+"#,
+        aging = thresholds.aging,
+        stale = thresholds.stale,
+        max = validate::MAX_DAYS,
+        aging_invalid = invalid_attr(invalid, "aging"),
+        stale_invalid = invalid_attr(invalid, "stale"),
+    ));
+    body.push_str(&format!(
+        r#"    <h2>Papierkorb</h2>
+    <form method="post" action="{base}/settings/trash">
+      <input type="number" name="days" value="{days}" min="0" max="{max}" title="Tage"{days_invalid} />
+      <button type="submit">Speichern</button>
+    </form>
+    <div class="subtitle">So viele Tage bleiben gelöschte Todos, Notizen und Ziele im <a class="back" href="{base}/trash">Papierkorb</a>, danach werden sie endgültig gelöscht; bei 0 nur so lange, wie sich das Löschen rückgängig machen lässt.</div>
+"#,
+        days = db.trash_retention()?,
+        max = validate::MAX_DAYS,
+        days_invalid = invalid_attr(invalid, "days"),
+    ));
+    let policy = db.retention_policy()?;
+    body.push_str(&format!(
+        r#"    <h2>Aufbewahrung</h2>
+    <form class="stacked" method="post" action="{base}/settings/retention">
+      <label>Erledigte Todos, Monate nach dem Erledigen <input type="number" name="todos" value="{todos}" min="0" max="{max}"{todos_invalid} /></label>
+      <label><input type="checkbox" name="anonymize" value="1"{anonymize} /> anonymisieren statt löschen</label>
+      <label>Protokoll, Monate <input type="number" name="audit" value="{audit}" min="0" max="{max}"{audit_invalid} /></label>
+      <label>Pomodoros, Monate <input type="number" name="pomodoros" value="{pomodoros}" min="0" max="{max}"{pomodoros_invalid} /></label>
+      <div class="subtitle">Älteres wird stündlich gelöscht; 0 behält alles. Anonymisierte Todos verlieren Titel und Ort, zählen aber weiter in der Statistik.</div>
+      <button type="submit">Speichern</button>
+    </form>
+"#,
+        todos = policy.todos,
+        anonymize = if policy.anonymize { " checked" } else { "" },
+        audit = policy.audit,
+        pomodoros = policy.pomodoros,
+        max = validate::MAX_MONTHS,
+        todos_invalid = invalid_attr(invalid, "todos"),
+        audit_invalid = invalid_attr(invalid, "audit"),
+        pomodoros_invalid = invalid_attr(invalid, "pomodoros"),
+    ));
+    body.push_str(&statuses::settings_section(
+        &db.statuses()?,
+        status_name,
+        invalid,
+    ));
+    body.push_str(&format!(
+        "    <a class=\"back\" href=\"{base}/\">Zurück zur Liste</a>\n"
+    ));
+    body.push_str(PAGE_END);
+    Ok(body)
+}
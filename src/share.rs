@@ -0,0 +1,162 @@
+//! Public links: a read-only page for one todo under an unguessable URL, for
+//! people without access to the list. Removing the link revokes it.
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::{Html, IntoResponse, Redirect, Response},
+    routing::{get, post},
+    Router,
+};
+use chrono::Local;
+use rand::{distributions::Alphanumeric, Rng};
+
+use crate::models::Todo;
+use crate::{
+    base_path, error_status, goals, habits, html_escape, page_start, preferences, public_url, qr,
+    statuses, AppState, PAGE_END,
+};
+
+/// Length of a link's token; 32 alphanumerics are about 190 bits.
+const TOKEN_CHARS: usize = 32;
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/share/:token", get(show))
+        .route("/todo/:id/share", post(add))
+        .route("/todo/:id/share/delete", post(delete))
+}
+
+async fn show(
+    State(state): State<AppState>,
+    Path(token): Path<String>,
+) -> Result<Html<String>, StatusCode> {
+    let db = state.db.lock();
+    let todo = db
+        .shared_todo(&token)
+        .map_err(error_status)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    let statuses = db.statuses().map_err(error_status)?;
+    let goal = match todo.goal_id {
+        Some(goal_id) => db
+            .list_goals()
+            .map_err(error_status)?
+            .into_iter()
+            .find(|goal| goal.id == goal_id),
+        None => None,
+    };
+    let habit_days = db.habit_days(todo.id).map_err(error_status)?;
+
+    let mut details = vec![format!(
+        "Erstellt am {}",
+        todo.created_at
+            .with_timezone(&Local)
+            .format(preferences::date())
+    )];
+    if let Some(start) = todo.start_date {
+        details.push(format!("Beginn {}", start.format(preferences::date())));
+    }
+    if let Some(deadline) = todo.deadline {
+        details.push(format!(
+            "fällig am {}",
+            deadline.format(preferences::date())
+        ));
+    }
+    if let Some(completed_at) = todo.completed_at {
+        details.push(format!(
+            "erledigt am {}",
+            completed_at
+                .with_timezone(&Local)
+                .format(preferences::date())
+        ));
+    }
+    if let Some(location) = &todo.location {
+        details.push(html_escape(location));
+    }
+    if let Some(goal) = &goal {
+        details.push(goals::badge(goal));
+    }
+
+    let mut body = page_start();
+    body.push_str(&format!(
+        r#"    <h1>{title}</h1>
+    <div class="subtitle">{details}</div>
+    <div class="todo-list">
+<div class="todo">
+  <div class="meta">
+    <div class="title">Status</div>
+  </div>
+  <div class="actions">
+    <span class="{class}">{status}</span>
+  </div>
+</div>
+    </div>
+"#,
+        title = html_escape(&todo.title),
+        details = details.join(" · "),
+        class = if todo.completed_at.is_some() {
+            "status done"
+        } else {
+            "status"
+        },
+        status = html_escape(statuses::label(&todo, &statuses)),
+    ));
+    if todo.habit {
+        body.push_str(&habits::section(&habit_days, Local::now().date_naive()));
+    }
+    body.push_str(PAGE_END);
+    Ok(Html(body))
+}
+
+async fn add(State(state): State<AppState>, Path(id): Path<i64>) -> Result<Response, StatusCode> {
+    let db = state.db.lock();
+    db.get_todo(id)
+        .map_err(error_status)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    let token: String = rand::thread_rng()
+        .sample_iter(&Alphanumeric)
+        .take(TOKEN_CHARS)
+        .map(char::from)
+        .collect();
+    db.add_share_token(id, &token).map_err(error_status)?;
+    Ok(Redirect::to(&format!("{}/todo/{id}", base_path())).into_response())
+}
+
+async fn delete(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+) -> Result<Response, StatusCode> {
+    state
+        .db
+        .lock()
+        .delete_share_token(id)
+        .map_err(error_status)?;
+    Ok(Redirect::to(&format!("{}/todo/{id}", base_path())).into_response())
+}
+
+/// The public link part of a todo's page: the link with a button to revoke
+/// it, or a button to create one.
+pub fn section(todo: &Todo, token: Option<&str>) -> String {
+    let base = base_path();
+    let id = todo.id;
+    match token {
+        Some(token) => format!(
+            r#"    <h2>Teilen</h2>
+    <div class="subtitle">Wer diesen Link hat, kann das Todo ansehen, aber nicht ändern: <a href="{base}/share/{token}">{base}/share/{token}</a></div>
+    <div class="qr">{code}</div>
+    <form method="post" action="{base}/todo/{id}/share/delete">
+      <button class="delete" type="submit">Link widerrufen</button>
+    </form>
+"#,
+            // The whole link, for a phone that knows nothing of this page.
+            code = qr::svg(&format!("{}/share/{token}", public_url())),
+        ),
+        None => format!(
+            r#"    <h2>Teilen</h2>
+    <form method="post" action="{base}/todo/{id}/share">
+      <button type="submit">Öffentlichen Link erstellen</button>
+    </form>
+"#
+        ),
+    }
+}
@@ -0,0 +1,138 @@
+//! Someday/maybe: todos put off without a date. They leave the list and
+//! every deadline view for `/someday` until made active again.
+
+use axum::{
+    extract::{Form, Path, State},
+    http::StatusCode,
+    response::{Html, IntoResponse, Redirect, Response},
+    routing::{get, post},
+    Router,
+};
+use serde::Deserialize;
+
+use crate::models::Todo;
+use crate::{
+    base_path, error_status, goals, html_escape, page_start, preferences, AppState, PAGE_END,
+};
+
+#[derive(Deserialize)]
+struct ActivateForm {
+    /// `someday` to return to the someday page instead of the todo's page.
+    #[serde(default)]
+    from: String,
+}
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/someday", get(show))
+        .route("/todo/:id/someday", post(defer))
+        .route("/todo/:id/activate", post(activate))
+}
+
+async fn show(State(state): State<AppState>) -> Result<Html<String>, StatusCode> {
+    let db = state.db.lock();
+    let todos = db.someday_todos().map_err(error_status)?;
+    let goals = db.list_goals().map_err(error_status)?;
+
+    let base = base_path();
+    let mut body = page_start();
+    body.push_str(
+        "    <h1>Irgendwann</h1>\n    <div class=\"subtitle\">Todos ohne Eile. Sie stehen weder in der Liste noch in Agenda, Mein Tag oder Gantt und erinnern an nichts, bis sie wieder aktiv sind.</div>\n    <div class=\"todo-list\">\n",
+    );
+    if todos.is_empty() {
+        body.push_str("<div class=\"subtitle\">Nichts für irgendwann.</div>\n");
+    }
+    for todo in &todos {
+        let mut details = vec![format!(
+            "Erstellt am {}",
+            todo.created_at.format(preferences::date())
+        )];
+        if let Some(deadline) = todo.deadline {
+            details.push(format!(
+                "fällig am {}",
+                deadline.format(preferences::date())
+            ));
+        }
+        if let Some(location) = &todo.location {
+            details.push(html_escape(location));
+        }
+        if let Some(goal) = goals.iter().find(|goal| Some(goal.id) == todo.goal_id) {
+            details.push(goals::badge(goal));
+        }
+        body.push_str(&format!(
+            r#"<div class="todo">
+  <div class="meta">
+    <div class="title"><a href="{base}/todo/{id}">{title}</a></div>
+    <div class="time">{details}</div>
+  </div>
+  <div class="actions">
+    <form method="post" action="{base}/todo/{id}/activate">
+      <input type="hidden" name="from" value="someday" />
+      <button type="submit">Aktivieren</button>
+    </form>
+  </div>
+</div>
+"#,
+            id = todo.id,
+            title = html_escape(&todo.title),
+            details = details.join(" · "),
+        ));
+    }
+    body.push_str(&format!(
+        "    </div>\n    <a class=\"back\" href=\"{base}/\">Zurück zur Liste</a>\n"
+    ));
+    body.push_str(PAGE_END);
+    Ok(Html(body))
+}
+
+async fn defer(State(state): State<AppState>, Path(id): Path<i64>) -> Result<Response, StatusCode> {
+    let db = state.db.lock();
+    db.get_todo(id)
+        .map_err(error_status)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    db.set_someday(id, true).map_err(error_status)?;
+    Ok(Redirect::to(&format!("{}/someday", base_path())).into_response())
+}
+
+async fn activate(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    Form(form): Form<ActivateForm>,
+) -> Result<Response, StatusCode> {
+    let db = state.db.lock();
+    db.get_todo(id)
+        .map_err(error_status)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    db.set_someday(id, false).map_err(error_status)?;
+    let target = if form.from == "someday" {
+        format!("{}/someday", base_path())
+    } else {
+        format!("{}/todo/{id}", base_path())
+    };
+    Ok(Redirect::to(&target).into_response())
+}
+
+/// The someday part of a todo's page: a button to put it off, or to make
+/// it active again.
+pub fn section(todo: &Todo) -> String {
+    let base = base_path();
+    let id = todo.id;
+    if todo.someday {
+        format!(
+            r#"    <h2>Irgendwann</h2>
+    <div class="subtitle">Zurückgestellt: steht nicht in der Liste und erinnert an nichts.</div>
+    <form method="post" action="{base}/todo/{id}/activate">
+      <button type="submit">Aktivieren</button>
+    </form>
+"#
+        )
+    } else {
+        format!(
+            r#"    <h2>Irgendwann</h2>
+    <form method="post" action="{base}/todo/{id}/someday">
+      <button type="submit">Auf irgendwann verschieben</button>
+    </form>
+"#
+        )
+    }
+}
@@ -0,0 +1,98 @@
+//! Velocity: story points (and todos and pomodoros) completed per calendar
+//! week.
+
+use axum::{extract::State, http::StatusCode, response::Html, routing::get, Router};
+use chrono::{Datelike, Days, NaiveDate, Utc};
+
+use crate::{base_path, error_status, page_start, pomodoro, preferences, AppState, PAGE_END};
+
+/// Weeks shown, including the current one.
+const WEEKS: u64 = 8;
+
+pub fn router() -> Router<AppState> {
+    Router::new().route("/stats", get(show))
+}
+
+struct Week {
+    start: NaiveDate,
+    todos: u32,
+    points: u32,
+    pomodoros: u32,
+}
+
+async fn show(State(state): State<AppState>) -> Result<Html<String>, StatusCode> {
+    let today = Utc::now().date_naive();
+    let this_week = preferences::week_of(today);
+    let mut weeks: Vec<Week> = (0..WEEKS)
+        .rev()
+        .map(|ago| Week {
+            start: this_week - Days::new(ago * 7),
+            todos: 0,
+            points: 0,
+            pomodoros: 0,
+        })
+        .collect();
+
+    let since = weeks[0].start.and_time(Default::default()).and_utc();
+    let db = state.db.lock();
+    let completed = db.completed_since(since).map_err(error_status)?;
+    for (completed_at, points) in completed {
+        let date = completed_at.date_naive();
+        if let Some(week) = weeks.iter_mut().rev().find(|week| week.start <= date) {
+            week.todos += 1;
+            week.points += points.unwrap_or(0);
+        }
+    }
+    let pomodoros = db.pomodoros_finished_since(since).map_err(error_status)?;
+    for session in pomodoros
+        .iter()
+        .filter(|session| pomodoro::completed(session))
+    {
+        let date = session
+            .finished_at
+            .unwrap_or(session.started_at)
+            .date_naive();
+        if let Some(week) = weeks.iter_mut().rev().find(|week| week.start <= date) {
+            week.pomodoros += 1;
+        }
+    }
+
+    // The current week is still running and would drag the average down.
+    let finished = &weeks[..weeks.len() - 1];
+    let average =
+        f64::from(finished.iter().map(|week| week.points).sum::<u32>()) / finished.len() as f64;
+    let max = weeks
+        .iter()
+        .map(|week| week.points)
+        .max()
+        .unwrap_or(0)
+        .max(1);
+
+    let mut rows = String::new();
+    for week in &weeks {
+        rows.push_str(&format!(
+            r#"      <tr><td>KW {number} · ab {start}</td><td>{points} Punkte</td><td>{todos} Todos</td><td>{pomodoros} Pomodoros</td><td><progress max="{max}" value="{points}"></progress></td></tr>
+"#,
+            // Weeks starting on Sunday are counted as the Monday after.
+            number = (week.start + Days::new(3)).iso_week().week(),
+            start = week.start.format(preferences::day()),
+            points = week.points,
+            todos = week.todos,
+            pomodoros = week.pomodoros,
+        ));
+    }
+
+    let mut body = page_start();
+    body.push_str(&format!(
+        r#"    <h1>Statistik</h1>
+    <div class="subtitle">Erledigte Punkte pro Woche. Durchschnitt der letzten {finished} abgeschlossenen Wochen: {average:.1} Punkte.</div>
+    <table>
+{rows}    </table>
+    <a class="back" href="{base}/">Zurück zur Liste</a>
+"#,
+        finished = finished.len(),
+        base = base_path(),
+    ));
+    body.push_str(PAGE_END);
+    Ok(Html(body))
+}
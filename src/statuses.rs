@@ -0,0 +1,282 @@
+//! Workflow statuses such as Backlog, In Arbeit or Wartet, and the board that
+//! shows one column per status.
+//!
+//! A todo is completed exactly when its status means done, so everything that
+//! only knows open and done (filters, the API, the archive) keeps working.
+
+use axum::{
+    extract::{Form, Path, State},
+    http::StatusCode,
+    response::{Html, IntoResponse, Redirect, Response},
+    routing::{get, post},
+    Router,
+};
+use serde::Deserialize;
+
+use crate::goals;
+use crate::models::{Goal, Status, Todo};
+use crate::validate::{self, FieldError, Invalid};
+use crate::{
+    base_path, error_status, html_escape, invalid_attr, page_start, settings, AppState, IdForm,
+    PAGE_END,
+};
+
+#[derive(Deserialize)]
+struct StatusForm {
+    status_id: i64,
+    /// `board` to return to the board instead of the todo's page.
+    #[serde(default)]
+    from: String,
+}
+
+#[derive(Deserialize)]
+struct AddForm {
+    name: String,
+    /// Checkbox; present when todos with this status count as completed.
+    done: Option<String>,
+}
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/board", get(board))
+        .route("/todo/:id/status", post(set_status))
+        .route("/statuses", post(add_status))
+        .route("/statuses/delete", post(delete_status))
+}
+
+async fn board(State(state): State<AppState>) -> Result<Html<String>, StatusCode> {
+    let db = state.db.lock();
+    let statuses = db.statuses().map_err(error_status)?;
+    let todos = db.list_todos(true).map_err(error_status)?;
+    let goals = db.list_goals().map_err(error_status)?;
+    Ok(Html(render_board(&statuses, &todos, &goals)))
+}
+
+async fn set_status(
+    State(state): State<AppState>,
+    Path(id): Path<i64>,
+    Form(form): Form<StatusForm>,
+) -> Result<Response, StatusCode> {
+    let db = state.db.lock();
+    db.get_todo(id)
+        .map_err(error_status)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    if !db
+        .statuses()
+        .map_err(error_status)?
+        .iter()
+        .any(|status| status.id == form.status_id)
+    {
+        return Err(StatusCode::UNPROCESSABLE_ENTITY);
+    }
+    db.set_status(id, form.status_id).map_err(error_status)?;
+    let target = if form.from == "board" {
+        format!("{}/board", base_path())
+    } else {
+        format!("{}/todo/{id}", base_path())
+    };
+    Ok(Redirect::to(&target).into_response())
+}
+
+async fn add_status(
+    State(state): State<AppState>,
+    Form(form): Form<AddForm>,
+) -> Result<Response, StatusCode> {
+    let db = state.db.lock();
+    let error = match validate::status_name(&form.name) {
+        Ok(name)
+            if db
+                .statuses()
+                .map_err(error_status)?
+                .iter()
+                .any(|status| status.name == name) =>
+        {
+            FieldError {
+                field: "name",
+                message: format!("Den Status „{name}“ gibt es schon."),
+            }
+        }
+        Ok(name) => {
+            db.add_status(&name, form.done.is_some())
+                .map_err(error_status)?;
+            return Ok(redirect_settings());
+        }
+        Err(error) => error,
+    };
+    let body = settings::render(&db, None, &form.name, Some(&Invalid(vec![error])))
+        .map_err(error_status)?;
+    Ok((StatusCode::UNPROCESSABLE_ENTITY, Html(body)).into_response())
+}
+
+async fn delete_status(
+    State(state): State<AppState>,
+    Form(form): Form<IdForm>,
+) -> Result<Response, StatusCode> {
+    let db = state.db.lock();
+    let statuses = db.statuses().map_err(error_status)?;
+    let status = statuses
+        .iter()
+        .find(|status| status.id == form.id)
+        .ok_or(StatusCode::NOT_FOUND)?;
+    // Completing and reopening todos needs a status of either kind.
+    if statuses
+        .iter()
+        .filter(|other| other.done == status.done)
+        .count()
+        == 1
+    {
+        let message = if status.done {
+            "Es muss mindestens einen Status für erledigte Todos geben."
+        } else {
+            "Es muss mindestens einen Status für offene Todos geben."
+        };
+        let invalid = Invalid(vec![FieldError {
+            field: "status",
+            message: message.to_string(),
+        }]);
+        let body = settings::render(&db, None, "", Some(&invalid)).map_err(error_status)?;
+        return Ok((StatusCode::UNPROCESSABLE_ENTITY, Html(body)).into_response());
+    }
+    db.delete_status(form.id).map_err(error_status)?;
+    Ok(redirect_settings())
+}
+
+fn redirect_settings() -> Response {
+    Redirect::to(&format!("{}/settings", base_path())).into_response()
+}
+
+/// The name shown for a todo's status; todos whose status was lost fall
+/// back to open or done.
+pub fn label<'a>(todo: &Todo, statuses: &'a [Status]) -> &'a str {
+    statuses
+        .iter()
+        .find(|status| Some(status.id) == todo.status_id)
+        .map_or(
+            if todo.completed_at.is_some() {
+                "Erledigt"
+            } else {
+                "Offen"
+            },
+            |status| status.name.as_str(),
+        )
+}
+
+/// A form that moves `todo` to another status; `from` is passed on to pick
+/// where to return to.
+fn select(todo: &Todo, statuses: &[Status], from: &str, class: &str) -> String {
+    let mut options = String::new();
+    for status in statuses {
+        let selected = if Some(status.id) == todo.status_id {
+            " selected"
+        } else {
+            ""
+        };
+        options.push_str(&format!(
+            "        <option value=\"{id}\"{selected}>{name}</option>\n",
+            id = status.id,
+            name = html_escape(&status.name),
+        ));
+    }
+    format!(
+        r#"    <form{class} method="post" action="{base}/todo/{id}/status">
+      <input type="hidden" name="from" value="{from}" />
+      <select name="status_id">
+{options}      </select>
+      <button type="submit">Status setzen</button>
+    </form>
+"#,
+        base = base_path(),
+        id = todo.id,
+    )
+}
+
+/// The status form on a todo's edit page.
+pub fn section(todo: &Todo, statuses: &[Status]) -> String {
+    select(todo, statuses, "todo", "")
+}
+
+/// The statuses on the settings page, with a form to add one.
+pub fn settings_section(statuses: &[Status], name: &str, invalid: Option<&Invalid>) -> String {
+    let base = base_path();
+    let mut section = String::from(
+        "    <h2>Status</h2>\n    <div class=\"subtitle\">Die Spalten des Boards, in dieser Reihenfolge. Todos mit einem Status für Erledigtes gelten als erledigt.</div>\n    <div class=\"todo-list\">\n",
+    );
+    for status in statuses {
+        section.push_str(&format!(
+            r#"<div class="todo">
+  <div class="meta">
+    <div class="title">{name}</div>
+  </div>
+  <div class="actions">
+    <span class="{class}">{kind}</span>
+    <form method="post" action="{base}/statuses/delete">
+      <input type="hidden" name="id" value="{id}" />
+      <button class="delete" type="submit">Löschen</button>
+    </form>
+  </div>
+</div>
+"#,
+            id = status.id,
+            name = html_escape(&status.name),
+            class = if status.done { "status done" } else { "status" },
+            kind = if status.done { "Erledigt" } else { "Offen" },
+        ));
+    }
+    section.push_str(&format!(
+        r#"    </div>
+    <form method="post" action="{base}/statuses">
+      <input type="text" name="name" placeholder="Neuer Status" value="{name}" maxlength="{max}" required{invalid} />
+      <label><input type="checkbox" name="done" value="1" /> erledigt</label>
+      <button type="submit">Hinzufügen</button>
+    </form>
+"#,
+        name = html_escape(name),
+        max = validate::MAX_STATUS_CHARS,
+        invalid = invalid_attr(invalid, "name"),
+    ));
+    section
+}
+
+/// One column per status with its todos, each with a form to move it.
+fn render_board(statuses: &[Status], todos: &[Todo], goals: &[Goal]) -> String {
+    let base = base_path();
+    let mut body = page_start();
+    body.push_str("    <h1>Board</h1>\n    <div class=\"board\">\n");
+    let fallback = |done: bool| statuses.iter().find(|status| status.done == done);
+    for status in statuses {
+        let column: Vec<&Todo> = todos
+            .iter()
+            .filter(|todo| {
+                let own = statuses
+                    .iter()
+                    .find(|other| Some(other.id) == todo.status_id);
+                own.or_else(|| fallback(todo.completed_at.is_some()))
+                    .is_some_and(|own| own.id == status.id)
+            })
+            .collect();
+        body.push_str(&format!(
+            "    <div class=\"column\">\n    <h2>{name} ({count})</h2>\n",
+            name = html_escape(&status.name),
+            count = column.len(),
+        ));
+        for todo in column {
+            let goal = goals
+                .iter()
+                .find(|goal| Some(goal.id) == todo.goal_id)
+                .map(|goal| format!("    <div class=\"time\">{}</div>\n", goals::badge(goal)))
+                .unwrap_or_default();
+            body.push_str(&format!(
+                "    <div class=\"todo\">\n    <div class=\"title\"><a href=\"{base}/todo/{id}\">{title}</a></div>\n{goal}{select}    </div>\n",
+                id = todo.id,
+                title = html_escape(&todo.title),
+                select = select(todo, statuses, "board", " class=\"stacked\""),
+            ));
+        }
+        body.push_str("    </div>\n");
+    }
+    body.push_str(&format!(
+        "    </div>\n    <a class=\"back\" href=\"{base}/\">Zurück zur Liste</a>\n"
+    ));
+    body.push_str(PAGE_END);
+    body
+}
@@ -0,0 +1,240 @@
+//! The stylesheet every page links to, served as a file since the
+//! Content-Security-Policy keeps out inline styles.
+
+use axum::{http::header, response::IntoResponse, routing::get, Router};
+
+use crate::AppState;
+
+pub fn router() -> Router<AppState> {
+    Router::new().route("/style.css", get(stylesheet))
+}
+
+async fn stylesheet() -> impl IntoResponse {
+    ([(header::CONTENT_TYPE, "text/css; charset=utf-8")], STYLE)
+}
+
+const STYLE: &str = r#":root {
+  color-scheme: light;
+  font-family: "Inter", system-ui, -apple-system, sans-serif;
+  background: #f4f5f7;
+}
+body {
+  margin: 0;
+  padding: 32px;
+  display: flex;
+  justify-content: center;
+}
+.app {
+  width: min(720px, 100%);
+  background: #ffffff;
+  border-radius: 16px;
+  box-shadow: 0 24px 48px rgba(15, 23, 42, 0.08);
+  padding: 28px;
+}
+h1 {
+  margin: 0 0 16px 0;
+  font-size: 28px;
+  letter-spacing: -0.02em;
+}
+.subtitle {
+  color: #64748b;
+  margin-bottom: 24px;
+}
+form {
+  display: flex;
+  gap: 12px;
+  margin-bottom: 24px;
+}
+form.stacked {
+  flex-direction: column;
+}
+input[type="text"],
+input[type="date"],
+input[type="number"],
+textarea {
+  flex: 1;
+  padding: 12px 14px;
+  border-radius: 10px;
+  border: 1px solid #e2e8f0;
+  font-size: 15px;
+}
+input[type="date"],
+select {
+  flex: 0 0 auto;
+}
+input[type="number"] {
+  flex: 0 0 90px;
+}
+select {
+  padding: 12px 14px;
+  border-radius: 10px;
+  border: 1px solid #e2e8f0;
+  font-size: 15px;
+  background: #ffffff;
+}
+progress {
+  width: 100%;
+  height: 8px;
+  accent-color: #16a34a;
+}
+textarea {
+  font-family: inherit;
+  resize: vertical;
+}
+.note-body p {
+  margin: 4px 0;
+}
+input.invalid,
+select.invalid,
+textarea.invalid {
+  border-color: #dc2626;
+}
+button {
+  border: none;
+  border-radius: 10px;
+  padding: 12px 16px;
+  background: #111827;
+  color: white;
+  font-weight: 600;
+  cursor: pointer;
+}
+.todo-list {
+  display: grid;
+  gap: 12px;
+}
+.todo {
+  display: flex;
+  align-items: center;
+  justify-content: space-between;
+  padding: 12px 16px;
+  border-radius: 12px;
+  background: #f8fafc;
+  border: 1px solid #e2e8f0;
+}
+.todo .meta {
+  display: flex;
+  flex-direction: column;
+  gap: 4px;
+}
+.todo .title {
+  font-weight: 600;
+}
+.todo .title a {
+  color: inherit;
+  text-decoration: none;
+}
+.notice {
+  padding: 12px 16px;
+  margin-bottom: 24px;
+  border-radius: 12px;
+  background: #fef3c7;
+  color: #92400e;
+}
+.back {
+  color: #64748b;
+}
+.todo .time {
+  font-size: 12px;
+  color: #94a3b8;
+}
+.status {
+  font-size: 12px;
+  text-transform: uppercase;
+  letter-spacing: 0.08em;
+  color: #0f172a;
+  background: #e2e8f0;
+  padding: 4px 8px;
+  border-radius: 999px;
+}
+.status.done {
+  background: #dcfce7;
+  color: #166534;
+}
+.todo.aging {
+  background: #fffbeb;
+  border-color: #fcd34d;
+}
+.todo.stale {
+  background: #fef2f2;
+  border-color: #fca5a5;
+}
+.actions {
+  display: flex;
+  gap: 8px;
+}
+.actions button {
+  background: #e2e8f0;
+  color: #0f172a;
+  font-weight: 600;
+  padding: 8px 12px;
+}
+.actions button.delete {
+  background: #fee2e2;
+  color: #991b1b;
+}
+.goal {
+  font-weight: 600;
+}
+details.done summary {
+  margin: 24px 0 12px 0;
+  color: #64748b;
+  font-weight: 600;
+  cursor: pointer;
+}
+details.done h2 {
+  font-size: 16px;
+  margin: 16px 0 8px 0;
+}
+.board {
+  display: grid;
+  grid-auto-flow: column;
+  grid-auto-columns: minmax(180px, 1fr);
+  gap: 12px;
+  overflow-x: auto;
+  margin-bottom: 24px;
+}
+.board .column {
+  display: grid;
+  gap: 8px;
+  align-content: start;
+}
+.board h2 {
+  font-size: 16px;
+  margin: 0;
+}
+.board .todo {
+  flex-direction: column;
+  align-items: stretch;
+  gap: 8px;
+}
+.board form {
+  gap: 8px;
+  margin: 0;
+}
+.heatmap {
+  display: grid;
+  grid-template-rows: repeat(7, 12px);
+  grid-auto-flow: column;
+  grid-auto-columns: 12px;
+  gap: 3px;
+  margin-bottom: 24px;
+}
+.heatmap span {
+  background: #e2e8f0;
+  border-radius: 2px;
+}
+.heatmap span.done {
+  background: #16a34a;
+}
+.qr svg {
+  display: block;
+  margin-bottom: 24px;
+}
+.gantt {
+  overflow-x: auto;
+  margin-bottom: 24px;
+}
+.gantt a:hover text {
+  text-decoration: underline;
+}
+"#;
@@ -0,0 +1,130 @@
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use reqwest::header::{HeaderMap, HeaderValue, ACCEPT, AUTHORIZATION, USER_AGENT};
+use serde::Deserialize;
+use serde_json::json;
+
+use super::{Provider, RemoteItem};
+
+const API_BASE: &str = "https://api.github.com";
+
+/// Mirrors the todo list with the issues of one repository.
+pub struct GithubIssues {
+    repository: String,
+    http: reqwest::Client,
+}
+
+#[derive(Deserialize)]
+struct Issue {
+    number: i64,
+    title: String,
+    state: String,
+    updated_at: Option<DateTime<Utc>>,
+    pull_request: Option<serde_json::Value>,
+}
+
+impl GithubIssues {
+    pub fn new(repository: String, token: &str) -> Result<Self> {
+        let mut headers = HeaderMap::new();
+        headers.insert(
+            ACCEPT,
+            HeaderValue::from_static("application/vnd.github+json"),
+        );
+        headers.insert(
+            "X-GitHub-Api-Version",
+            HeaderValue::from_static("2022-11-28"),
+        );
+        headers.insert(USER_AGENT, HeaderValue::from_static("simpletodo"));
+        let mut auth = HeaderValue::from_str(&format!("Bearer {token}"))
+            .context("GitHub token contains invalid characters")?;
+        auth.set_sensitive(true);
+        headers.insert(AUTHORIZATION, auth);
+
+        let http = reqwest::Client::builder()
+            .default_headers(headers)
+            .build()?;
+        Ok(Self { repository, http })
+    }
+}
+
+impl Provider for GithubIssues {
+    fn name(&self) -> &'static str {
+        "github"
+    }
+
+    fn remote_list(&self) -> &str {
+        &self.repository
+    }
+
+    async fn fetch(&self) -> Result<Vec<RemoteItem>> {
+        const PER_PAGE: usize = 100;
+        let mut items = Vec::new();
+
+        for page in 1.. {
+            let batch: Vec<Issue> = self
+                .http
+                .get(format!("{API_BASE}/repos/{}/issues", self.repository))
+                .query(&[
+                    ("state", "all".to_string()),
+                    ("per_page", PER_PAGE.to_string()),
+                    ("page", page.to_string()),
+                ])
+                .send()
+                .await
+                .context("fetching issues")?
+                .error_for_status()?
+                .json()
+                .await?;
+            let done = batch.len() < PER_PAGE;
+            items.extend(
+                batch
+                    .into_iter()
+                    .filter(|issue| issue.pull_request.is_none())
+                    .map(|issue| RemoteItem {
+                        id: issue.number.to_string(),
+                        title: issue.title,
+                        completed: issue.state == "closed",
+                        modified_at: issue.updated_at,
+                    }),
+            );
+            if done {
+                break;
+            }
+        }
+
+        Ok(items)
+    }
+
+    async fn create(&self, title: &str) -> Result<String> {
+        let issue: Issue = self
+            .http
+            .post(format!("{API_BASE}/repos/{}/issues", self.repository))
+            .json(&json!({ "title": title }))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        Ok(issue.number.to_string())
+    }
+
+    async fn complete(&self, id: &str) -> Result<()> {
+        self.http
+            .patch(format!("{API_BASE}/repos/{}/issues/{id}", self.repository))
+            .json(&json!({ "state": "closed" }))
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+
+    async fn reopen(&self, id: &str) -> Result<()> {
+        self.http
+            .patch(format!("{API_BASE}/repos/{}/issues/{id}", self.repository))
+            .json(&json!({ "state": "open" }))
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,168 @@
+use std::sync::Arc;
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::db::SharedDatabase;
+
+use super::oauth::{OAuthClient, OAuthEndpoints};
+use super::{Provider, RemoteItem};
+
+const API_BASE: &str = "https://tasks.googleapis.com/tasks/v1";
+
+pub fn oauth_client(
+    client_id: String,
+    client_secret: String,
+    public_url: &str,
+    db: SharedDatabase,
+) -> OAuthClient {
+    OAuthClient::new(
+        OAuthEndpoints {
+            provider: "google",
+            auth_url: "https://accounts.google.com/o/oauth2/v2/auth",
+            token_url: "https://oauth2.googleapis.com/token",
+            scope: "https://www.googleapis.com/auth/tasks",
+            auth_params: &[("access_type", "offline"), ("prompt", "consent")],
+        },
+        client_id,
+        client_secret,
+        public_url,
+        db,
+    )
+}
+
+/// Mirrors the todo list with one Google Tasks list.
+pub struct GoogleTasks {
+    oauth: Arc<OAuthClient>,
+    tasklist: String,
+    http: reqwest::Client,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TaskPage {
+    #[serde(default)]
+    items: Vec<Task>,
+    next_page_token: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct Task {
+    id: String,
+    #[serde(default)]
+    title: String,
+    status: String,
+    updated: Option<DateTime<Utc>>,
+}
+
+impl GoogleTasks {
+    pub fn new(oauth: Arc<OAuthClient>, tasklist: String) -> Self {
+        Self {
+            oauth,
+            tasklist,
+            http: reqwest::Client::new(),
+        }
+    }
+}
+
+impl Provider for GoogleTasks {
+    fn name(&self) -> &'static str {
+        "google"
+    }
+
+    fn remote_list(&self) -> &str {
+        &self.tasklist
+    }
+
+    fn is_ready(&self) -> bool {
+        self.oauth.is_connected().unwrap_or(false)
+    }
+
+    async fn fetch(&self) -> Result<Vec<RemoteItem>> {
+        let token = self.oauth.access_token().await?;
+        let mut items = Vec::new();
+        let mut page_token: Option<String> = None;
+
+        loop {
+            let mut query = vec![
+                ("showCompleted", "true".to_string()),
+                ("showHidden", "true".to_string()),
+                ("maxResults", "100".to_string()),
+            ];
+            if let Some(page_token) = page_token.take() {
+                query.push(("pageToken", page_token));
+            }
+
+            let page: TaskPage = self
+                .http
+                .get(format!("{API_BASE}/lists/{}/tasks", self.tasklist))
+                .bearer_auth(&token)
+                .query(&query)
+                .send()
+                .await?
+                .error_for_status()?
+                .json()
+                .await?;
+            items.extend(
+                page.items
+                    .into_iter()
+                    // Google Tasks keeps blank placeholder rows around.
+                    .filter(|task| !task.title.trim().is_empty())
+                    .map(|task| RemoteItem {
+                        id: task.id,
+                        title: task.title,
+                        completed: task.status == "completed",
+                        modified_at: task.updated,
+                    }),
+            );
+
+            match page.next_page_token {
+                Some(next) => page_token = Some(next),
+                None => break,
+            }
+        }
+
+        Ok(items)
+    }
+
+    async fn create(&self, title: &str) -> Result<String> {
+        let token = self.oauth.access_token().await?;
+        let task: Task = self
+            .http
+            .post(format!("{API_BASE}/lists/{}/tasks", self.tasklist))
+            .bearer_auth(&token)
+            .json(&json!({ "title": title }))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        Ok(task.id)
+    }
+
+    async fn complete(&self, id: &str) -> Result<()> {
+        let token = self.oauth.access_token().await?;
+        self.http
+            .patch(format!("{API_BASE}/lists/{}/tasks/{id}", self.tasklist))
+            .bearer_auth(&token)
+            .json(&json!({ "status": "completed" }))
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+
+    async fn reopen(&self, id: &str) -> Result<()> {
+        let token = self.oauth.access_token().await?;
+        self.http
+            .patch(format!("{API_BASE}/lists/{}/tasks/{id}", self.tasklist))
+            .bearer_auth(&token)
+            .json(&json!({ "status": "needsAction", "completed": null }))
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+}
@@ -0,0 +1,193 @@
+use std::sync::Arc;
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+use serde_json::json;
+use tokio::sync::OnceCell;
+
+use crate::db::SharedDatabase;
+
+use super::oauth::{OAuthClient, OAuthEndpoints};
+use super::{Provider, RemoteItem};
+
+const API_BASE: &str = "https://graph.microsoft.com/v1.0/me/todo";
+
+/// Name used for the user's default list when no list is configured.
+pub const DEFAULT_LIST: &str = "defaultList";
+
+pub fn oauth_client(
+    client_id: String,
+    client_secret: String,
+    public_url: &str,
+    db: SharedDatabase,
+) -> OAuthClient {
+    OAuthClient::new(
+        OAuthEndpoints {
+            provider: "microsoft",
+            auth_url: "https://login.microsoftonline.com/common/oauth2/v2.0/authorize",
+            token_url: "https://login.microsoftonline.com/common/oauth2/v2.0/token",
+            scope: "offline_access Tasks.ReadWrite",
+            auth_params: &[],
+        },
+        client_id,
+        client_secret,
+        public_url,
+        db,
+    )
+}
+
+/// Mirrors the todo list with one Microsoft To Do list, chosen by display name.
+pub struct MicrosoftTodo {
+    oauth: Arc<OAuthClient>,
+    list_name: String,
+    list_id: OnceCell<String>,
+    http: reqwest::Client,
+}
+
+#[derive(Deserialize)]
+struct Page<T> {
+    value: Vec<T>,
+    #[serde(rename = "@odata.nextLink")]
+    next_link: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TaskList {
+    id: String,
+    display_name: String,
+    wellknown_list_name: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct Task {
+    id: String,
+    title: String,
+    status: String,
+    last_modified_date_time: Option<DateTime<Utc>>,
+}
+
+impl MicrosoftTodo {
+    pub fn new(oauth: Arc<OAuthClient>, list_name: String) -> Self {
+        Self {
+            oauth,
+            list_name,
+            list_id: OnceCell::new(),
+            http: reqwest::Client::new(),
+        }
+    }
+
+    async fn get_all<T: serde::de::DeserializeOwned>(&self, url: String) -> Result<Vec<T>> {
+        let token = self.oauth.access_token().await?;
+        let mut items = Vec::new();
+        let mut next = Some(url);
+
+        while let Some(url) = next.take() {
+            let page: Page<T> = self
+                .http
+                .get(url)
+                .bearer_auth(&token)
+                .send()
+                .await?
+                .error_for_status()?
+                .json()
+                .await?;
+            items.extend(page.value);
+            next = page.next_link;
+        }
+
+        Ok(items)
+    }
+
+    async fn list_id(&self) -> Result<&str> {
+        let id = self
+            .list_id
+            .get_or_try_init(|| async {
+                let lists: Vec<TaskList> = self.get_all(format!("{API_BASE}/lists")).await?;
+                lists
+                    .into_iter()
+                    .find(|list| {
+                        list.display_name == self.list_name
+                            || list.wellknown_list_name.as_deref() == Some(self.list_name.as_str())
+                    })
+                    .map(|list| list.id)
+                    .with_context(|| format!("To Do list \"{}\" not found", self.list_name))
+            })
+            .await?;
+        Ok(id)
+    }
+}
+
+impl Provider for MicrosoftTodo {
+    fn name(&self) -> &'static str {
+        "microsoft"
+    }
+
+    fn remote_list(&self) -> &str {
+        &self.list_name
+    }
+
+    fn is_ready(&self) -> bool {
+        self.oauth.is_connected().unwrap_or(false)
+    }
+
+    async fn fetch(&self) -> Result<Vec<RemoteItem>> {
+        let list_id = self.list_id().await?;
+        let tasks: Vec<Task> = self
+            .get_all(format!("{API_BASE}/lists/{list_id}/tasks"))
+            .await?;
+        Ok(tasks
+            .into_iter()
+            .map(|task| RemoteItem {
+                id: task.id,
+                title: task.title,
+                completed: task.status == "completed",
+                modified_at: task.last_modified_date_time,
+            })
+            .collect())
+    }
+
+    async fn create(&self, title: &str) -> Result<String> {
+        let list_id = self.list_id().await?;
+        let token = self.oauth.access_token().await?;
+        let task: Task = self
+            .http
+            .post(format!("{API_BASE}/lists/{list_id}/tasks"))
+            .bearer_auth(&token)
+            .json(&json!({ "title": title }))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        Ok(task.id)
+    }
+
+    async fn complete(&self, id: &str) -> Result<()> {
+        let list_id = self.list_id().await?;
+        let token = self.oauth.access_token().await?;
+        self.http
+            .patch(format!("{API_BASE}/lists/{list_id}/tasks/{id}"))
+            .bearer_auth(&token)
+            .json(&json!({ "status": "completed" }))
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+
+    async fn reopen(&self, id: &str) -> Result<()> {
+        let list_id = self.list_id().await?;
+        let token = self.oauth.access_token().await?;
+        self.http
+            .patch(format!("{API_BASE}/lists/{list_id}/tasks/{id}"))
+            .bearer_auth(&token)
+            .json(&json!({ "status": "notStarted" }))
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+}
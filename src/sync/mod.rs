@@ -0,0 +1,265 @@
+//! Mirroring todos with external task services.
+//!
+//! Every provider exposes a flat list of remote items; [`spawn`] reconciles
+//! that list with the local todos on an interval. Open remote items without a
+//! link become todos and open todos without a link are created remotely. When
+//! one side is completed and the other is not, the side changed last wins: the
+//! remote item's modification time is compared with the todo's newest audit
+//! log entry, and without a remote time the completion wins. Links live in
+//! `sync_links` so nothing is imported twice.
+
+use std::future::Future;
+use std::time::Duration;
+
+use anyhow::Result;
+use chrono::{DateTime, Utc};
+
+use crate::audit;
+use crate::db::SharedDatabase;
+
+pub mod github;
+pub mod google;
+pub mod microsoft;
+pub mod oauth;
+
+/// A task as seen by a remote service.
+pub struct RemoteItem {
+    pub id: String,
+    pub title: String,
+    pub completed: bool,
+    pub modified_at: Option<DateTime<Utc>>,
+}
+
+pub trait Provider: Send + Sync + 'static {
+    /// Stable name stored with every link, e.g. `github`.
+    fn name(&self) -> &'static str;
+
+    /// Identifies the remote list (repository, task list id, ...).
+    fn remote_list(&self) -> &str;
+
+    /// Whether the provider can sync yet, e.g. once OAuth is connected.
+    fn is_ready(&self) -> bool {
+        true
+    }
+
+    fn fetch(&self) -> impl Future<Output = Result<Vec<RemoteItem>>> + Send;
+
+    /// Creates an item and returns its remote id.
+    fn create(&self, title: &str) -> impl Future<Output = Result<String>> + Send;
+
+    fn complete(&self, id: &str) -> impl Future<Output = Result<()>> + Send;
+
+    fn reopen(&self, id: &str) -> impl Future<Output = Result<()>> + Send;
+}
+
+#[derive(Default)]
+struct SyncStats {
+    imported: usize,
+    exported: usize,
+    completed: usize,
+    reopened: usize,
+    closed: usize,
+    reopened_remotely: usize,
+}
+
+/// Runs the sync for `provider` in the background every `interval`.
+pub fn spawn<P: Provider>(provider: P, db: SharedDatabase, interval: Duration) {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(interval);
+        loop {
+            ticker.tick().await;
+            if db.is_read_only() {
+                continue;
+            }
+            match audit::scope("sync", sync_once(&provider, &db)).await {
+                Ok(stats) => {
+                    let changes = stats.imported
+                        + stats.exported
+                        + stats.completed
+                        + stats.reopened
+                        + stats.closed
+                        + stats.reopened_remotely;
+                    if changes > 0 {
+                        println!(
+                            "{} sync: {} imported, {} exported, {} completed, {} reopened, {} closed, {} reopened remotely",
+                            provider.name(),
+                            stats.imported,
+                            stats.exported,
+                            stats.completed,
+                            stats.reopened,
+                            stats.closed,
+                            stats.reopened_remotely
+                        );
+                    }
+                }
+                Err(err) => eprintln!(
+                    "{} sync with {} failed: {err:#}",
+                    provider.name(),
+                    provider.remote_list()
+                ),
+            }
+        }
+    });
+}
+
+async fn sync_once<P: Provider>(provider: &P, shared: &SharedDatabase) -> Result<SyncStats> {
+    if !provider.is_ready() {
+        return Ok(SyncStats::default());
+    }
+
+    let items = provider.fetch().await?;
+    let name = provider.name();
+    let list = provider.remote_list();
+    let mut stats = SyncStats::default();
+
+    // Local changes first; remote calls are collected so the database lock is
+    // not held across an await point.
+    let mut to_complete = Vec::new();
+    let mut to_reopen = Vec::new();
+    let to_export = {
+        let db = shared.lock();
+        let links = db.sync_links(name, list)?;
+
+        for item in &items {
+            match links.get(&item.id) {
+                Some(Some(todo_id)) => {
+                    let Some(todo) = db.get_todo(*todo_id)? else {
+                        continue;
+                    };
+                    match (item.completed, todo.completed_at) {
+                        (true, None) => {
+                            // A todo reopened since the item was completed stays open.
+                            let modified_at = db.modified_at(todo.id)?.unwrap_or(todo.created_at);
+                            let completed_remotely =
+                                item.modified_at.is_none_or(|remote| remote > modified_at);
+                            if completed_remotely {
+                                db.complete_todo(todo.id)?;
+                                stats.completed += 1;
+                            } else {
+                                to_reopen.push(item.id.as_str());
+                            }
+                        }
+                        (false, Some(completed_at)) => {
+                            let reopened_remotely = item
+                                .modified_at
+                                .is_some_and(|modified_at| modified_at > completed_at);
+                            if reopened_remotely {
+                                db.reopen_todo(todo.id)?;
+                                stats.reopened += 1;
+                            } else {
+                                to_complete.push(item.id.as_str());
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+                // The todo was deleted locally; leave the remote item alone.
+                Some(None) => {}
+                None if !item.completed => {
+                    db.import_remote(name, list, &item.id, &item.title)?;
+                    stats.imported += 1;
+                }
+                None => {}
+            }
+        }
+
+        db.unlinked_todos(name, list)?
+    };
+
+    for id in to_complete {
+        provider.complete(id).await?;
+        stats.closed += 1;
+    }
+
+    for id in to_reopen {
+        provider.reopen(id).await?;
+        stats.reopened_remotely += 1;
+    }
+
+    for todo in to_export {
+        let remote_id = provider.create(&todo.title).await?;
+        shared.lock().link_remote(name, list, &remote_id, todo.id)?;
+        stats.exported += 1;
+    }
+
+    Ok(stats)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use chrono::TimeDelta;
+
+    use super::*;
+    use crate::db::Database;
+
+    /// One remote item, completed at `modified_at`.
+    struct Fake {
+        modified_at: DateTime<Utc>,
+        reopened: Mutex<Vec<String>>,
+    }
+
+    impl Provider for Fake {
+        fn name(&self) -> &'static str {
+            "fake"
+        }
+
+        fn remote_list(&self) -> &str {
+            "list"
+        }
+
+        async fn fetch(&self) -> Result<Vec<RemoteItem>> {
+            Ok(vec![RemoteItem {
+                id: "1".to_string(),
+                title: "Zahnarzt anrufen".to_string(),
+                completed: true,
+                modified_at: Some(self.modified_at),
+            }])
+        }
+
+        async fn create(&self, _title: &str) -> Result<String> {
+            anyhow::bail!("nothing to create")
+        }
+
+        async fn complete(&self, _id: &str) -> Result<()> {
+            anyhow::bail!("nothing to complete")
+        }
+
+        async fn reopen(&self, id: &str) -> Result<()> {
+            self.reopened.lock().unwrap().push(id.to_string());
+            Ok(())
+        }
+    }
+
+    #[tokio::test]
+    async fn the_side_changed_last_wins() {
+        let db = Database::connect(":memory:", None).unwrap();
+        let todo = db.add_todo("Zahnarzt anrufen", None, None).unwrap();
+        db.link_remote("fake", "list", "1", todo.id).unwrap();
+        db.complete_todo(todo.id).unwrap();
+        db.reopen_todo(todo.id).unwrap();
+        let db = SharedDatabase::new(db);
+
+        // Completed remotely, then reopened here: the item is reopened.
+        let provider = Fake {
+            modified_at: Utc::now() - TimeDelta::hours(1),
+            reopened: Mutex::default(),
+        };
+        let stats = sync_once(&provider, &db).await.unwrap();
+        assert_eq!((stats.completed, stats.reopened_remotely), (0, 1));
+        assert_eq!(*provider.reopened.lock().unwrap(), ["1"]);
+        let completed_at = db.lock().get_todo(todo.id).unwrap().unwrap().completed_at;
+        assert!(completed_at.is_none());
+
+        // Completed remotely after the last change here: the todo follows.
+        let provider = Fake {
+            modified_at: Utc::now() + TimeDelta::minutes(1),
+            reopened: Mutex::default(),
+        };
+        let stats = sync_once(&provider, &db).await.unwrap();
+        assert_eq!((stats.completed, stats.reopened_remotely), (1, 0));
+        let completed_at = db.lock().get_todo(todo.id).unwrap().unwrap().completed_at;
+        assert!(completed_at.is_some());
+    }
+}
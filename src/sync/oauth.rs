@@ -0,0 +1,243 @@
+use std::collections::HashSet;
+use std::sync::{Arc, Mutex};
+
+use anyhow::{Context, Result};
+use axum::{
+    extract::Query,
+    http::StatusCode,
+    response::{IntoResponse, Redirect},
+    routing::get,
+    Router,
+};
+use chrono::{Duration, Utc};
+use rand::{distributions::Alphanumeric, Rng};
+use reqwest::Url;
+use serde::Deserialize;
+
+use crate::db::SharedDatabase;
+use crate::models::OAuthToken;
+use crate::{base_path, AppState};
+
+/// Endpoints and scope of an OAuth2 authorization-code provider.
+pub struct OAuthEndpoints {
+    pub provider: &'static str,
+    pub auth_url: &'static str,
+    pub token_url: &'static str,
+    pub scope: &'static str,
+    /// Extra parameters for the authorization request, e.g. to get a refresh token.
+    pub auth_params: &'static [(&'static str, &'static str)],
+}
+
+/// Authorization-code flow with refresh tokens persisted in `oauth_tokens`.
+pub struct OAuthClient {
+    endpoints: OAuthEndpoints,
+    client_id: String,
+    client_secret: String,
+    redirect_uri: String,
+    db: SharedDatabase,
+    pending_states: Mutex<HashSet<String>>,
+    http: reqwest::Client,
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    access_token: String,
+    refresh_token: Option<String>,
+    expires_in: i64,
+}
+
+#[derive(Deserialize)]
+struct CallbackQuery {
+    code: Option<String>,
+    state: Option<String>,
+    error: Option<String>,
+}
+
+impl OAuthClient {
+    pub fn new(
+        endpoints: OAuthEndpoints,
+        client_id: String,
+        client_secret: String,
+        public_url: &str,
+        db: SharedDatabase,
+    ) -> Self {
+        let redirect_uri = format!(
+            "{}{}",
+            public_url.trim_end_matches('/'),
+            callback_path(endpoints.provider)
+        );
+        Self {
+            endpoints,
+            client_id,
+            client_secret,
+            redirect_uri,
+            db,
+            pending_states: Mutex::new(HashSet::new()),
+            http: reqwest::Client::new(),
+        }
+    }
+
+    pub fn provider(&self) -> &'static str {
+        self.endpoints.provider
+    }
+
+    pub fn connect_path(&self) -> String {
+        format!("/sync/{}/connect", self.endpoints.provider)
+    }
+
+    pub fn is_connected(&self) -> Result<bool> {
+        Ok(self
+            .db
+            .lock()
+            .oauth_token(self.endpoints.provider)?
+            .is_some())
+    }
+
+    /// Returns a valid access token, refreshing it when it is about to expire.
+    pub async fn access_token(&self) -> Result<String> {
+        let token = self
+            .db
+            .lock()
+            .oauth_token(self.endpoints.provider)?
+            .with_context(|| format!("{} is not connected", self.endpoints.provider))?;
+        if token.expires_at > Utc::now() + Duration::seconds(60) {
+            return Ok(token.access_token);
+        }
+
+        let refresh_token = token.refresh_token.with_context(|| {
+            format!("{} did not issue a refresh token", self.endpoints.provider)
+        })?;
+        let refreshed = self
+            .request_token(&[
+                ("grant_type", "refresh_token"),
+                ("refresh_token", &refresh_token),
+            ])
+            .await?;
+        let token = OAuthToken {
+            access_token: refreshed.access_token,
+            refresh_token: refreshed.refresh_token.or(Some(refresh_token)),
+            expires_at: Utc::now() + Duration::seconds(refreshed.expires_in),
+        };
+        self.db
+            .lock()
+            .save_oauth_token(self.endpoints.provider, &token)?;
+        Ok(token.access_token)
+    }
+
+    fn authorize_url(&self) -> Result<Url> {
+        let state: String = rand::thread_rng()
+            .sample_iter(&Alphanumeric)
+            .take(32)
+            .map(char::from)
+            .collect();
+        self.pending_states
+            .lock()
+            .expect("oauth state lock poisoned")
+            .insert(state.clone());
+
+        let mut params = vec![
+            ("response_type", "code"),
+            ("client_id", self.client_id.as_str()),
+            ("redirect_uri", self.redirect_uri.as_str()),
+            ("scope", self.endpoints.scope),
+            ("state", state.as_str()),
+        ];
+        params.extend_from_slice(self.endpoints.auth_params);
+        Ok(Url::parse_with_params(self.endpoints.auth_url, &params)?)
+    }
+
+    async fn finish(&self, query: CallbackQuery) -> Result<()> {
+        if let Some(error) = query.error {
+            anyhow::bail!("authorization was denied: {error}");
+        }
+        let state = query.state.context("missing state")?;
+        if !self
+            .pending_states
+            .lock()
+            .expect("oauth state lock poisoned")
+            .remove(&state)
+        {
+            anyhow::bail!("unknown or expired state");
+        }
+        let code = query.code.context("missing authorization code")?;
+
+        let response = self
+            .request_token(&[
+                ("grant_type", "authorization_code"),
+                ("code", &code),
+                ("redirect_uri", &self.redirect_uri),
+            ])
+            .await?;
+        let token = OAuthToken {
+            access_token: response.access_token,
+            refresh_token: response.refresh_token,
+            expires_at: Utc::now() + Duration::seconds(response.expires_in),
+        };
+        self.db
+            .lock()
+            .save_oauth_token(self.endpoints.provider, &token)?;
+        Ok(())
+    }
+
+    async fn request_token(&self, params: &[(&str, &str)]) -> Result<TokenResponse> {
+        let mut form = vec![
+            ("client_id", self.client_id.as_str()),
+            ("client_secret", self.client_secret.as_str()),
+        ];
+        form.extend_from_slice(params);
+        let response = self
+            .http
+            .post(self.endpoints.token_url)
+            .form(&form)
+            .send()
+            .await
+            .with_context(|| format!("requesting {} token", self.endpoints.provider))?
+            .error_for_status()?
+            .json()
+            .await?;
+        Ok(response)
+    }
+}
+
+fn callback_path(provider: &str) -> String {
+    format!("/sync/{provider}/callback")
+}
+
+/// Routes that start the flow and receive the provider's redirect.
+pub fn router(client: Arc<OAuthClient>) -> Router<AppState> {
+    let connect = {
+        let client = client.clone();
+        move || {
+            let client = client.clone();
+            async move {
+                match client.authorize_url() {
+                    Ok(url) => Redirect::to(url.as_str()).into_response(),
+                    Err(_) => StatusCode::INTERNAL_SERVER_ERROR.into_response(),
+                }
+            }
+        }
+    };
+    let callback = {
+        let client = client.clone();
+        move |Query(query): Query<CallbackQuery>| {
+            let client = client.clone();
+            async move {
+                match client.finish(query).await {
+                    Ok(()) => Redirect::to(&format!("{}/", base_path())).into_response(),
+                    Err(err) => {
+                        eprintln!("{} authorization failed: {err:#}", client.provider());
+                        (
+                            StatusCode::BAD_REQUEST,
+                            format!("Verbindung fehlgeschlagen: {err}"),
+                        )
+                            .into_response()
+                    }
+                }
+            }
+        }
+    };
+
+    Router::new()
+        .route(&client.connect_path(), get(connect))
+        .route(&callback_path(client.provider()), get(callback))
+}
@@ -0,0 +1,327 @@
+use anyhow::Result;
+use chrono::Local;
+use ratatui::{
+    crossterm::event::{self, Event, KeyCode, KeyEventKind},
+    layout::{Constraint, Layout},
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+    widgets::{Block, List, ListItem, ListState, Paragraph},
+    DefaultTerminal, Frame,
+};
+
+use simpletodo::client::{Client, ListQuery, NewTodo};
+use crate::db::Database;
+use crate::models::Todo;
+use crate::{preferences, validate};
+
+/// Where the TUI reads and writes todos.
+pub enum Backend {
+    Local(Database),
+    Remote(Client),
+}
+
+impl Backend {
+    async fn list_todos(&self, include_completed: bool) -> Result<Vec<Todo>> {
+        match self {
+            Backend::Local(db) => db.list_todos(include_completed),
+            Backend::Remote(client) => {
+                let query = ListQuery {
+                    completed: (!include_completed).then_some(false),
+                    ..Default::default()
+                };
+                Ok(client.list_todos(&query).await?)
+            }
+        }
+    }
+
+    async fn add_todo(&self, title: &str) -> Result<()> {
+        match self {
+            Backend::Local(db) => {
+                let title = validate::title(title).map_err(|err| anyhow::anyhow!(err.message))?;
+                db.add_todo(&title, None, None).map(|_| ())
+            }
+            Backend::Remote(client) => {
+                client.add_todo(&NewTodo::new(title)).await?;
+                Ok(())
+            }
+        }
+    }
+
+    async fn complete_todo(&self, id: i64) -> Result<()> {
+        match self {
+            Backend::Local(db) => db.complete_todo(id),
+            Backend::Remote(client) => {
+                client.complete_todo(id).await?;
+                Ok(())
+            }
+        }
+    }
+
+    async fn delete_todo(&self, id: i64) -> Result<()> {
+        match self {
+            Backend::Local(db) => db.delete_todo(id).map(|_| ()),
+            Backend::Remote(client) => Ok(client.delete_todo(id).await?),
+        }
+    }
+}
+
+enum Mode {
+    List,
+    Detail,
+    Input(String),
+}
+
+struct App {
+    backend: Backend,
+    todos: Vec<Todo>,
+    list_state: ListState,
+    mode: Mode,
+    show_completed: bool,
+    message: Option<String>,
+}
+
+pub async fn run(backend: Backend) -> Result<()> {
+    let mut app = App {
+        backend,
+        todos: Vec::new(),
+        list_state: ListState::default(),
+        mode: Mode::List,
+        show_completed: true,
+        message: None,
+    };
+    app.refresh().await;
+
+    let mut terminal = ratatui::init();
+    let result = app.run(&mut terminal).await;
+    ratatui::restore();
+    result
+}
+
+impl App {
+    async fn run(&mut self, terminal: &mut DefaultTerminal) -> Result<()> {
+        loop {
+            terminal.draw(|frame| self.draw(frame))?;
+
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+
+            match &mut self.mode {
+                Mode::Input(input) => match key.code {
+                    KeyCode::Esc => self.mode = Mode::List,
+                    KeyCode::Backspace => {
+                        input.pop();
+                    }
+                    KeyCode::Char(c) => input.push(c),
+                    KeyCode::Enter => {
+                        let title = input.trim().to_string();
+                        self.mode = Mode::List;
+                        if !title.is_empty() {
+                            let result = self.backend.add_todo(&title).await;
+                            self.report(result, &format!("added \"{title}\""));
+                            self.refresh().await;
+                        }
+                    }
+                    _ => {}
+                },
+                Mode::Detail => match key.code {
+                    KeyCode::Esc | KeyCode::Enter | KeyCode::Char('q') => self.mode = Mode::List,
+                    KeyCode::Char('d') | KeyCode::Char(' ') => self.complete_selected().await,
+                    KeyCode::Char('x') | KeyCode::Delete => {
+                        self.delete_selected().await;
+                        self.mode = Mode::List;
+                    }
+                    _ => {}
+                },
+                Mode::List => match key.code {
+                    KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                    KeyCode::Down | KeyCode::Char('j') => self.list_state.select_next(),
+                    KeyCode::Up | KeyCode::Char('k') => self.list_state.select_previous(),
+                    KeyCode::Home | KeyCode::Char('g') => self.list_state.select_first(),
+                    KeyCode::End | KeyCode::Char('G') => self.list_state.select_last(),
+                    KeyCode::Enter if self.selected().is_some() => self.mode = Mode::Detail,
+                    KeyCode::Char('a') => self.mode = Mode::Input(String::new()),
+                    KeyCode::Char('d') | KeyCode::Char(' ') => self.complete_selected().await,
+                    KeyCode::Char('x') | KeyCode::Delete => self.delete_selected().await,
+                    KeyCode::Char('c') => {
+                        self.show_completed = !self.show_completed;
+                        self.refresh().await;
+                    }
+                    KeyCode::Char('r') => self.refresh().await,
+                    _ => {}
+                },
+            }
+        }
+    }
+
+    async fn refresh(&mut self) {
+        match self.backend.list_todos(self.show_completed).await {
+            Ok(todos) => {
+                self.todos = todos;
+                if self.todos.is_empty() {
+                    self.list_state.select(None);
+                } else {
+                    let selected = self.list_state.selected().unwrap_or(0);
+                    self.list_state
+                        .select(Some(selected.min(self.todos.len() - 1)));
+                }
+            }
+            Err(err) => self.message = Some(format!("error: {err:#}")),
+        }
+    }
+
+    fn selected(&self) -> Option<&Todo> {
+        self.list_state
+            .selected()
+            .and_then(|index| self.todos.get(index))
+    }
+
+    async fn complete_selected(&mut self) {
+        let Some(todo) = self.selected() else {
+            return;
+        };
+        if todo.completed_at.is_some() {
+            self.message = Some(format!("todo {} is already completed", todo.id));
+            return;
+        }
+        let id = todo.id;
+        let result = self.backend.complete_todo(id).await;
+        self.report(result, &format!("completed todo {id}"));
+        self.refresh().await;
+    }
+
+    async fn delete_selected(&mut self) {
+        let Some(id) = self.selected().map(|todo| todo.id) else {
+            return;
+        };
+        let result = self.backend.delete_todo(id).await;
+        self.report(result, &format!("deleted todo {id}"));
+        self.refresh().await;
+    }
+
+    fn report(&mut self, result: Result<()>, success: &str) {
+        self.message = Some(match result {
+            Ok(()) => success.to_string(),
+            Err(err) => format!("error: {err:#}"),
+        });
+    }
+
+    fn draw(&mut self, frame: &mut Frame) {
+        let [main, input_area, footer] = Layout::vertical([
+            Constraint::Min(1),
+            Constraint::Length(if matches!(self.mode, Mode::Input(_)) {
+                3
+            } else {
+                0
+            }),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+
+        match self.mode {
+            Mode::Detail => self.draw_detail(frame, main),
+            _ => self.draw_list(frame, main),
+        }
+
+        if let Mode::Input(input) = &self.mode {
+            let paragraph = Paragraph::new(input.as_str())
+                .block(Block::bordered().title(" New todo (Enter to save, Esc to cancel) "));
+            frame.render_widget(paragraph, input_area);
+            frame.set_cursor_position((
+                input_area.x + 1 + input.chars().count() as u16,
+                input_area.y + 1,
+            ));
+        }
+
+        let help = match self.mode {
+            Mode::List => "j/k move  enter details  a add  d done  x delete  c toggle completed  r refresh  q quit",
+            Mode::Detail => "esc back  d done  x delete",
+            Mode::Input(_) => "",
+        };
+        let footer_text = match &self.message {
+            Some(message) => format!("{message}  |  {help}"),
+            None => help.to_string(),
+        };
+        frame.render_widget(
+            Paragraph::new(footer_text).style(Style::default().fg(Color::DarkGray)),
+            footer,
+        );
+    }
+
+    fn draw_list(&mut self, frame: &mut Frame, area: ratatui::layout::Rect) {
+        let items: Vec<ListItem> = self
+            .todos
+            .iter()
+            .map(|todo| {
+                let (marker, style) = if todo.completed_at.is_some() {
+                    ("[x]", Style::default().fg(Color::DarkGray))
+                } else {
+                    ("[ ]", Style::default())
+                };
+                ListItem::new(Line::from(vec![
+                    Span::styled(format!("{marker} "), style),
+                    Span::styled(
+                        format!("#{:<5} ", todo.id),
+                        Style::default().fg(Color::DarkGray),
+                    ),
+                    Span::styled(todo.title.clone(), style),
+                ]))
+            })
+            .collect();
+
+        let title = if self.show_completed {
+            " simpletodo — all todos "
+        } else {
+            " simpletodo — open todos "
+        };
+        let list = List::new(items)
+            .block(Block::bordered().title(title))
+            .highlight_style(Style::default().add_modifier(Modifier::REVERSED));
+        frame.render_stateful_widget(list, area, &mut self.list_state);
+    }
+
+    fn draw_detail(&self, frame: &mut Frame, area: ratatui::layout::Rect) {
+        let Some(todo) = self.selected() else {
+            return;
+        };
+
+        let label = Style::default().fg(Color::DarkGray);
+        let mut lines = vec![
+            Line::from(Span::styled(
+                todo.title.clone(),
+                Style::default().add_modifier(Modifier::BOLD),
+            )),
+            Line::default(),
+            Line::from(vec![
+                Span::styled("id:        ", label),
+                Span::raw(todo.id.to_string()),
+            ]),
+            Line::from(vec![
+                Span::styled("status:    ", label),
+                Span::raw(if todo.completed_at.is_some() {
+                    "done"
+                } else {
+                    "open"
+                }),
+            ]),
+            Line::from(vec![
+                Span::styled("created:   ", label),
+                Span::raw(todo.created_at.with_timezone(&Local).format(preferences::date_time()).to_string()),
+            ]),
+        ];
+        if let Some(completed_at) = todo.completed_at {
+            lines.push(Line::from(vec![
+                Span::styled("completed: ", label),
+                Span::raw(completed_at.with_timezone(&Local).format(preferences::date_time()).to_string()),
+            ]));
+        }
+
+        frame.render_widget(
+            Paragraph::new(lines).block(Block::bordered().title(format!(" Todo #{} ", todo.id))),
+            area,
+        );
+    }
+}
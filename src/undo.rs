@@ -0,0 +1,172 @@
+//! Undo for deletes: deleted todos, notes and goals are kept as tombstones
+//! in the trash, and `/undo/:action_id` puts them back. Right after a delete
+//! the page offers to undo it; later, `/trash` still does until the trash is
+//! purged.
+
+use std::time::Duration;
+
+use axum::{
+    extract::{Path, State},
+    http::StatusCode,
+    response::{Html, IntoResponse, Redirect, Response},
+    routing::{get, post},
+    Router,
+};
+use chrono::{DateTime, Local, TimeDelta, Utc};
+use serde::Deserialize;
+
+use crate::db::{Database, SharedDatabase};
+use crate::{base_path, error_status, html_escape, page_start, preferences, AppState, PAGE_END};
+
+/// How often the job drops tombstones past the trash retention.
+const CHECK_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Asks a page to offer undoing the delete that led there.
+#[derive(Deserialize)]
+pub struct UndoQuery {
+    pub undo: Option<i64>,
+}
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/trash", get(trash))
+        .route("/undo/:action_id", post(undo))
+}
+
+/// Tombstones made since then can still be restored.
+pub fn since(window: TimeDelta) -> DateTime<Utc> {
+    Utc::now() - window
+}
+
+/// How long tombstones are kept: the trash retention, but at least the undo
+/// `window`.
+pub fn kept(db: &Database, window: TimeDelta) -> anyhow::Result<TimeDelta> {
+    let retention = TimeDelta::days(db.trash_retention()?.into());
+    Ok(retention.max(window))
+}
+
+/// Drops tombstones older than they are kept, now and then every minute.
+pub fn spawn(db: SharedDatabase, window: TimeDelta) {
+    tokio::spawn(async move {
+        let mut ticker = tokio::time::interval(CHECK_INTERVAL);
+        loop {
+            ticker.tick().await;
+            if db.is_read_only() {
+                continue;
+            }
+            let db = db.clone();
+            let job = tokio::task::spawn_blocking(move || {
+                let db = db.lock();
+                db.purge_tombstones(since(kept(&db, window)?))
+            });
+            match job.await {
+                Ok(Ok(_)) => {}
+                Ok(Err(err)) => eprintln!("purging tombstones failed: {err:#}"),
+                Err(err) => eprintln!("purging tombstones panicked: {err}"),
+            }
+        }
+    });
+}
+
+/// Restores what was deleted and shows it again; 404 once it was purged
+/// from the trash.
+async fn undo(
+    State(state): State<AppState>,
+    Path(action_id): Path<i64>,
+) -> Result<Response, StatusCode> {
+    let db = state.db.lock();
+    let kept = kept(&db, state.undo_window).map_err(error_status)?;
+    let tombstone = db
+        .restore(action_id, since(kept))
+        .map_err(error_status)?
+        .ok_or(StatusCode::NOT_FOUND)?;
+    let base = base_path();
+    let location = match tombstone.kind.as_str() {
+        "todo" => format!("{base}/todo/{}", tombstone.object_id),
+        "note" => format!("{base}/notes"),
+        "goal" => format!("{base}/goals"),
+        _ => format!("{base}/"),
+    };
+    Ok(Redirect::to(&location).into_response())
+}
+
+/// The trash: what was deleted and is still kept, each with the day it will
+/// be deleted for good.
+async fn trash(State(state): State<AppState>) -> Result<Html<String>, StatusCode> {
+    let db = state.db.lock();
+    let kept = kept(&db, state.undo_window).map_err(error_status)?;
+    let tombstones = db.tombstones(since(kept)).map_err(error_status)?;
+
+    let base = base_path();
+    let mut body = page_start();
+    body.push_str("    <h1>Papierkorb</h1>\n");
+    // Newest first, so the last one goes first.
+    match tombstones.last() {
+        Some(oldest) => body.push_str(&format!(
+            "    <div class=\"subtitle\">Gelöschte Todos, Notizen und Ziele. Die nächste endgültige Löschung ist am {}.</div>\n",
+            purge_date(oldest.deleted_at, kept),
+        )),
+        None => body.push_str("    <div class=\"subtitle\">Der Papierkorb ist leer.</div>\n"),
+    }
+    body.push_str("    <div class=\"todo-list\">\n");
+    for tombstone in &tombstones {
+        let kind = match tombstone.kind.as_str() {
+            "todo" => "Todo",
+            "note" => "Notiz",
+            "goal" => "Ziel",
+            _ => "Eintrag",
+        };
+        body.push_str(&format!(
+            r#"<div class="todo">
+  <div class="meta">
+    <div class="title">{title}</div>
+    <div class="time">{kind} · gelöscht am {deleted} · endgültig weg am {purged}</div>
+  </div>
+  <div class="actions">
+    <form method="post" action="{base}/undo/{id}">
+      <button type="submit">Wiederherstellen</button>
+    </form>
+  </div>
+</div>
+"#,
+            title = html_escape(&tombstone.title),
+            deleted = tombstone
+                .deleted_at
+                .with_timezone(&Local)
+                .format(preferences::date_time()),
+            purged = purge_date(tombstone.deleted_at, kept),
+            id = tombstone.id,
+        ));
+    }
+    body.push_str(&format!(
+        "    </div>\n    <a class=\"back\" href=\"{base}/\">Zurück zur Liste</a>\n"
+    ));
+    body.push_str(PAGE_END);
+    Ok(Html(body))
+}
+
+/// When something deleted at `deleted_at` leaves the trash, to the minute
+/// the purge job runs.
+fn purge_date(deleted_at: DateTime<Utc>, kept: TimeDelta) -> String {
+    (deleted_at + kept)
+        .with_timezone(&Local)
+        .format(preferences::date_time())
+        .to_string()
+}
+
+/// A notice saying what was deleted, with a button to undo it, while that
+/// is still possible.
+pub fn notice(db: &Database, window: TimeDelta, action_id: i64) -> anyhow::Result<Option<String>> {
+    let Some(tombstone) = db.tombstone(action_id, since(window))? else {
+        return Ok(None);
+    };
+    Ok(Some(format!(
+        r#"„{title}“ wurde gelöscht.
+    <form method="post" action="{base}/undo/{id}">
+      <button type="submit">Rückgängig</button>
+    </form>"#,
+        title = html_escape(&tombstone.title),
+        base = base_path(),
+        id = tombstone.id,
+    )))
+}
@@ -0,0 +1,406 @@
+//! Checks for user input shared by the HTML forms, the JSON API and the TUI.
+//!
+//! Every check reports a [`FieldError`] naming the offending field, so forms
+//! can point at the input and the API can return all problems at once.
+
+use axum::http::StatusCode;
+use axum::response::{IntoResponse, Response};
+use axum::Json;
+use chrono::NaiveDate;
+use serde::Serialize;
+use serde_json::json;
+
+use crate::preferences::{DateFormat, Preferences, WEEK_STARTS};
+
+pub const MAX_TITLE_CHARS: usize = 200;
+pub const MAX_NOTE_CHARS: usize = 20_000;
+pub const MAX_LOCATION_CHARS: usize = 100;
+pub const MAX_POINTS: u32 = 100;
+pub const MAX_REMINDER_OFFSETS: usize = 10;
+pub const MAX_STATUS_CHARS: usize = 40;
+pub const MAX_ICON_CHARS: usize = 8;
+pub const MAX_DAYS: u32 = 3650;
+pub const MAX_MONTHS: u32 = 120;
+
+/// Longest accepted reminder offset, in minutes (a year).
+const MAX_REMINDER_OFFSET: i64 = 365 * 24 * 60;
+
+/// Accepted deadline formats; the first one is also how deadlines are stored.
+const DATE_FORMATS: &[&str] = &["%Y-%m-%d", "%d.%m.%Y"];
+
+/// A rejected value, with a message meant for the person who typed it.
+#[derive(Serialize)]
+pub struct FieldError {
+    pub field: &'static str,
+    pub message: String,
+}
+
+/// Every problem found in one submission.
+pub struct Invalid(pub Vec<FieldError>);
+
+/// A todo's fields as submitted; fields a form doesn't have stay empty.
+#[derive(Default)]
+pub struct TodoFields<'a> {
+    pub title: &'a str,
+    pub deadline: &'a str,
+    pub location: &'a str,
+    pub points: &'a str,
+    pub start: &'a str,
+}
+
+/// The fields of a todo as entered in the add and edit forms.
+pub struct TodoInput {
+    pub title: String,
+    pub deadline: Option<NaiveDate>,
+    pub location: Option<String>,
+    pub points: Option<u32>,
+    pub start: Option<NaiveDate>,
+}
+
+/// The fields of a note as entered on the notes page.
+pub struct NoteInput {
+    pub title: String,
+    pub body: String,
+}
+
+impl Invalid {
+    pub fn has(&self, field: &str) -> bool {
+        self.0.iter().any(|error| error.field == field)
+    }
+}
+
+impl IntoResponse for Invalid {
+    fn into_response(self) -> Response {
+        (
+            StatusCode::UNPROCESSABLE_ENTITY,
+            Json(json!({ "errors": self.0 })),
+        )
+            .into_response()
+    }
+}
+
+pub fn todo(fields: &TodoFields) -> Result<TodoInput, Invalid> {
+    let title = self::title(fields.title);
+    let deadline = self::deadline(fields.deadline);
+    let location = self::location(fields.location);
+    let points = self::points(fields.points);
+    let start = self::start(fields.start);
+    let start = match (start, &deadline) {
+        (Ok(Some(start)), Ok(Some(deadline))) if start > *deadline => Err(FieldError {
+            field: "start",
+            message: "Der Beginn darf nicht nach der Deadline liegen.".to_string(),
+        }),
+        (start, _) => start,
+    };
+    match (title, deadline, location, points, start) {
+        (Ok(title), Ok(deadline), Ok(location), Ok(points), Ok(start)) => Ok(TodoInput {
+            title,
+            deadline,
+            location,
+            points,
+            start,
+        }),
+        (title, deadline, location, points, start) => Err(Invalid(
+            [
+                title.err(),
+                deadline.err(),
+                location.err(),
+                points.err(),
+                start.err(),
+            ]
+            .into_iter()
+            .flatten()
+            .collect(),
+        )),
+    }
+}
+
+pub fn note(title: &str, body: &str) -> Result<NoteInput, Invalid> {
+    let title = self::title(title);
+    let body = self::note_body(body);
+    match (title, body) {
+        (Ok(title), Ok(body)) => Ok(NoteInput { title, body }),
+        (title, body) => Err(Invalid(
+            [title.err(), body.err()].into_iter().flatten().collect(),
+        )),
+    }
+}
+
+/// Trims a title and checks it is a non-empty single line of sane length.
+pub fn title(input: &str) -> Result<String, FieldError> {
+    let title = input.trim();
+    let error = |message: String| FieldError {
+        field: "title",
+        message,
+    };
+
+    if title.is_empty() {
+        return Err(error("Bitte gib einen Titel ein.".to_string()));
+    }
+    if title.chars().count() > MAX_TITLE_CHARS {
+        return Err(error(format!(
+            "Der Titel darf höchstens {MAX_TITLE_CHARS} Zeichen lang sein."
+        )));
+    }
+    if title.chars().any(char::is_control) {
+        return Err(error(
+            "Der Titel darf keine Zeilenumbrüche oder Steuerzeichen enthalten.".to_string(),
+        ));
+    }
+    Ok(title.to_string())
+}
+
+/// Trims a note's Markdown text, which may be empty or span many lines.
+pub fn note_body(input: &str) -> Result<String, FieldError> {
+    let body = input.trim();
+    if body.chars().count() > MAX_NOTE_CHARS {
+        return Err(FieldError {
+            field: "body",
+            message: format!("Die Notiz darf höchstens {MAX_NOTE_CHARS} Zeichen lang sein."),
+        });
+    }
+    Ok(body.to_string())
+}
+
+/// Trims an optional location or context like `@home`; blank means none.
+pub fn location(input: &str) -> Result<Option<String>, FieldError> {
+    let location = input.trim();
+    let error = |message: String| FieldError {
+        field: "location",
+        message,
+    };
+
+    if location.chars().count() > MAX_LOCATION_CHARS {
+        return Err(error(format!(
+            "Der Ort darf höchstens {MAX_LOCATION_CHARS} Zeichen lang sein."
+        )));
+    }
+    if location.chars().any(char::is_control) {
+        return Err(error(
+            "Der Ort darf keine Zeilenumbrüche oder Steuerzeichen enthalten.".to_string(),
+        ));
+    }
+    Ok((!location.is_empty()).then(|| location.to_string()))
+}
+
+/// Parses optional story points, a whole number up to [`MAX_POINTS`].
+pub fn points(input: &str) -> Result<Option<u32>, FieldError> {
+    let input = input.trim();
+    if input.is_empty() {
+        return Ok(None);
+    }
+
+    input
+        .parse::<u32>()
+        .ok()
+        .filter(|points| *points <= MAX_POINTS)
+        .map(Some)
+        .ok_or_else(|| FieldError {
+            field: "points",
+            message: format!("Punkte müssen eine ganze Zahl von 0 bis {MAX_POINTS} sein."),
+        })
+}
+
+/// Parses an optional deadline; blank input means no deadline.
+pub fn deadline(input: &str) -> Result<Option<NaiveDate>, FieldError> {
+    let input = input.trim();
+    if input.is_empty() {
+        return Ok(None);
+    }
+
+    DATE_FORMATS
+        .iter()
+        .find_map(|format| NaiveDate::parse_from_str(input, format).ok())
+        .map(Some)
+        .ok_or_else(|| FieldError {
+            field: "deadline",
+            message: format!("„{input}“ ist kein gültiges Datum (TT.MM.JJJJ)."),
+        })
+}
+
+/// Parses an optional start date, in the same formats as a deadline.
+pub fn start(input: &str) -> Result<Option<NaiveDate>, FieldError> {
+    deadline(input).map_err(|error| FieldError {
+        field: "start",
+        ..error
+    })
+}
+
+/// Trims the name of a workflow status.
+pub fn status_name(input: &str) -> Result<String, FieldError> {
+    let name = input.trim();
+    let error = |message: String| FieldError {
+        field: "name",
+        message,
+    };
+
+    if name.is_empty() {
+        return Err(error("Bitte gib einen Namen ein.".to_string()));
+    }
+    if name.chars().count() > MAX_STATUS_CHARS {
+        return Err(error(format!(
+            "Der Name darf höchstens {MAX_STATUS_CHARS} Zeichen lang sein."
+        )));
+    }
+    if name.chars().any(char::is_control) {
+        return Err(error(
+            "Der Name darf keine Zeilenumbrüche oder Steuerzeichen enthalten.".to_string(),
+        ));
+    }
+    Ok(name.to_string())
+}
+
+/// Parses an optional color as `#rrggbb`; blank means none.
+pub fn color(input: &str) -> Result<Option<String>, FieldError> {
+    let color = input.trim();
+    if color.is_empty() {
+        return Ok(None);
+    }
+    let valid = color.len() == 7
+        && color.starts_with('#')
+        && color[1..].chars().all(|c| c.is_ascii_hexdigit());
+    if !valid {
+        return Err(FieldError {
+            field: "color",
+            message: format!("„{color}“ ist keine Farbe wie #2563eb."),
+        });
+    }
+    Ok(Some(color.to_ascii_lowercase()))
+}
+
+/// Trims an optional icon, an emoji or a few characters; blank means none.
+pub fn icon(input: &str) -> Result<Option<String>, FieldError> {
+    let icon = input.trim();
+    if icon.chars().count() > MAX_ICON_CHARS
+        || icon.chars().any(|c| c.is_whitespace() || c.is_control())
+    {
+        return Err(FieldError {
+            field: "icon",
+            message: format!(
+                "Das Symbol darf höchstens {MAX_ICON_CHARS} Zeichen ohne Leerzeichen haben."
+            ),
+        });
+    }
+    Ok((!icon.is_empty()).then(|| icon.to_string()))
+}
+
+/// Parses how long before a deadline to remind, e.g. `3d`, `2h` or `30m`,
+/// into minutes.
+pub fn reminder_offset(input: &str, field: &'static str) -> Result<i64, FieldError> {
+    let input = input.trim();
+    let minutes = input
+        .char_indices()
+        .last()
+        .and_then(|(unit_at, unit)| {
+            let factor = match unit.to_ascii_lowercase() {
+                'd' => 24 * 60,
+                'h' => 60,
+                'm' => 1,
+                _ => return None,
+            };
+            let count: i64 = input[..unit_at].trim().parse().ok()?;
+            count.checked_mul(factor)
+        })
+        .filter(|minutes| (1..=MAX_REMINDER_OFFSET).contains(minutes));
+    minutes.ok_or_else(|| FieldError {
+        field,
+        message: format!(
+            "„{input}“ ist kein gültiger Abstand, z. B. 3d (Tage), 2h (Stunden) oder 30m (Minuten)."
+        ),
+    })
+}
+
+/// Parses a comma-separated list of reminder offsets; blank input means
+/// none. Duplicates are dropped, the rest sorted from earliest reminder on.
+pub fn reminder_offsets(input: &str) -> Result<Vec<i64>, FieldError> {
+    let mut offsets = Vec::new();
+    for part in input.split(',').filter(|part| !part.trim().is_empty()) {
+        offsets.push(reminder_offset(part, "offsets")?);
+    }
+    offsets.sort_unstable_by(|a, b| b.cmp(a));
+    offsets.dedup();
+    if offsets.len() > MAX_REMINDER_OFFSETS {
+        return Err(FieldError {
+            field: "offsets",
+            message: format!("Höchstens {MAX_REMINDER_OFFSETS} Erinnerungen."),
+        });
+    }
+    Ok(offsets)
+}
+
+/// Parses a number of days for a setting; blank input means 0.
+pub fn days(input: &str, field: &'static str) -> Result<u32, FieldError> {
+    let input = input.trim();
+    if input.is_empty() {
+        return Ok(0);
+    }
+    input
+        .parse::<u32>()
+        .ok()
+        .filter(|days| *days <= MAX_DAYS)
+        .ok_or_else(|| FieldError {
+            field,
+            message: format!("Tage müssen eine ganze Zahl von 0 bis {MAX_DAYS} sein."),
+        })
+}
+
+/// Parses a number of months for a setting; blank input means 0.
+pub fn months(input: &str, field: &'static str) -> Result<u32, FieldError> {
+    let input = input.trim();
+    if input.is_empty() {
+        return Ok(0);
+    }
+    input
+        .parse::<u32>()
+        .ok()
+        .filter(|months| *months <= MAX_MONTHS)
+        .ok_or_else(|| FieldError {
+            field,
+            message: format!("Monate müssen eine ganze Zahl von 0 bis {MAX_MONTHS} sein."),
+        })
+}
+
+/// Parses the days after which open todos count as aging and as stale;
+/// blank input or 0 turns a level off. Stale has to come after aging.
+pub fn age_thresholds(aging: &str, stale: &str) -> Result<(u32, u32), Invalid> {
+    match (days(aging, "aging"), days(stale, "stale")) {
+        (Ok(aging), Ok(stale)) if aging > 0 && stale > 0 && stale <= aging => {
+            Err(Invalid(vec![FieldError {
+                field: "stale",
+                message: "„Vergessen“ muss nach „Angestaubt“ kommen.".to_string(),
+            }]))
+        }
+        (Ok(aging), Ok(stale)) => Ok((aging, stale)),
+        (aging, stale) => Err(Invalid(
+            [aging.err(), stale.err()].into_iter().flatten().collect(),
+        )),
+    }
+}
+
+/// Reads the choices of the display settings: a weekday as written by
+/// chrono (`Mon`) and the name of a date format.
+pub fn preferences(week_start: &str, date_format: &str) -> Result<Preferences, Invalid> {
+    let week_start = WEEK_STARTS
+        .into_iter()
+        .find(|day| day.to_string() == week_start)
+        .ok_or_else(|| FieldError {
+            field: "week_start",
+            message: "Die Woche beginnt montags oder sonntags.".to_string(),
+        });
+    let date_format = DateFormat::from_name(date_format).ok_or_else(|| FieldError {
+        field: "date_format",
+        message: "Unbekanntes Datumsformat.".to_string(),
+    });
+    match (week_start, date_format) {
+        (Ok(week_start), Ok(date_format)) => Ok(Preferences {
+            week_start,
+            date_format,
+        }),
+        (week_start, date_format) => Err(Invalid(
+            [week_start.err(), date_format.err()]
+                .into_iter()
+                .flatten()
+                .collect(),
+        )),
+    }
+}
@@ -0,0 +1,426 @@
+//! Web Push: browsers subscribe on the list page, and notifications reach
+//! them through their vendor's push service even while no tab is open.
+//!
+//! Messages are signed with a VAPID key (RFC 8292), generated on first start
+//! and kept in the database, and encrypted for each browser (RFC 8291).
+
+use aes_gcm::aead::{Aead, KeyInit};
+use aes_gcm::{Aes128Gcm, Key, Nonce};
+use anyhow::{anyhow, Context, Result};
+use axum::{
+    extract::State,
+    http::{header, StatusCode},
+    response::IntoResponse,
+    routing::{get, post},
+    Json, Router,
+};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use chrono::{TimeDelta, Utc};
+use hkdf::Hkdf;
+use p256::ecdh::diffie_hellman;
+use p256::ecdsa::signature::Signer;
+use p256::ecdsa::{Signature, SigningKey};
+use p256::elliptic_curve::sec1::ToEncodedPoint;
+use p256::{PublicKey, SecretKey};
+use rand::rngs::OsRng;
+use reqwest::Url;
+use serde::Deserialize;
+use serde_json::json;
+use sha2::Sha256;
+
+use crate::db::SharedDatabase;
+use crate::models::PushSubscription;
+use crate::notify::Notification;
+use crate::{base_path, error_status, AppState};
+
+/// Setting that holds the VAPID private key, base64url.
+const KEY_SETTING: &str = "vapid_private_key";
+
+/// How long push services keep a message for a browser that is offline.
+const TTL_SECONDS: u32 = 24 * 60 * 60;
+
+/// Record size announced in the encrypted body; messages fit in one record.
+const RECORD_SIZE: u32 = 4096;
+
+/// Shows pushed messages and opens the todo when one is clicked.
+const SERVICE_WORKER: &str = r#"self.addEventListener("push", (event) => {
+  const message = event.data ? event.data.json() : {};
+  event.waitUntil(
+    self.registration.showNotification(message.title || "simpletodo", {
+      body: message.body,
+      data: { url: message.url },
+    })
+  );
+});
+
+self.addEventListener("notificationclick", (event) => {
+  event.notification.close();
+  const url = event.notification.data && event.notification.data.url;
+  if (url) {
+    event.waitUntil(clients.openWindow(url));
+  }
+});
+"#;
+
+/// Drives the button from [`button`], served as `/push.js` since the
+/// Content-Security-Policy keeps out inline scripts.
+const BUTTON_SCRIPT: &str = r#"(() => {
+  const button = document.getElementById("push");
+  if (!("serviceWorker" in navigator) || !("PushManager" in window)) return;
+  const base = button.dataset.base;
+  const post = (path, body) =>
+    fetch(base + path, {
+      method: "POST",
+      headers: { "Content-Type": "application/json" },
+      body: JSON.stringify(body),
+    });
+  navigator.serviceWorker.register(base + "/sw.js").then(async (registration) => {
+    const show = (subscription) => {
+      button.textContent = subscription
+        ? "Benachrichtigungen deaktivieren"
+        : "Benachrichtigungen aktivieren";
+      button.hidden = false;
+    };
+    show(await registration.pushManager.getSubscription());
+    button.addEventListener("click", async () => {
+      const current = await registration.pushManager.getSubscription();
+      if (current) {
+        await post("/push/unsubscribe", { endpoint: current.endpoint });
+        await current.unsubscribe();
+        show(null);
+        return;
+      }
+      const key = await (await fetch(base + "/push/key")).text();
+      const subscription = await registration.pushManager.subscribe({
+        userVisibleOnly: true,
+        applicationServerKey: key,
+      });
+      await post("/push/subscribe", subscription.toJSON());
+      show(subscription);
+    });
+  });
+})();
+"#;
+
+/// A subscription as serialized by the browser's `PushSubscription.toJSON()`.
+#[derive(Deserialize)]
+struct SubscribeRequest {
+    endpoint: String,
+    keys: SubscriptionKeys,
+}
+
+#[derive(Deserialize)]
+struct SubscriptionKeys {
+    p256dh: String,
+    auth: String,
+}
+
+#[derive(Deserialize)]
+struct UnsubscribeRequest {
+    endpoint: String,
+}
+
+pub struct WebPush {
+    db: SharedDatabase,
+    key: SigningKey,
+    /// Uncompressed public key, base64url, as browsers expect it.
+    public_key: String,
+    /// How push services can reach whoever runs the instance; without one,
+    /// they would refuse every message.
+    subject: Option<String>,
+}
+
+/// Whether push services accept `subject` as the VAPID contact (RFC 8292).
+pub fn is_valid_subject(subject: &str) -> bool {
+    subject.starts_with("mailto:") || subject.starts_with("https://")
+}
+
+impl WebPush {
+    /// Loads the VAPID key, generating one on first start. `subject` is a
+    /// `mailto:` or `https:` URL identifying the instance to push services.
+    pub fn load(db: SharedDatabase, subject: Option<String>) -> Result<Self> {
+        if let Some(subject) = subject
+            .as_deref()
+            .filter(|subject| !is_valid_subject(subject))
+        {
+            anyhow::bail!("the VAPID subject {subject} is neither a mailto: nor an https: URL");
+        }
+        let stored = db.lock().setting(KEY_SETTING)?;
+        let key = match stored {
+            Some(stored) => URL_SAFE_NO_PAD
+                .decode(stored)
+                .ok()
+                .and_then(|bytes| SigningKey::from_slice(&bytes).ok())
+                .context("the stored VAPID key is invalid")?,
+            None => {
+                let key = SigningKey::random(&mut OsRng);
+                db.lock()
+                    .set_setting(KEY_SETTING, &URL_SAFE_NO_PAD.encode(key.to_bytes()))?;
+                key
+            }
+        };
+        let public_key =
+            URL_SAFE_NO_PAD.encode(key.verifying_key().to_encoded_point(false).as_bytes());
+        Ok(Self {
+            db,
+            key,
+            public_key,
+            subject,
+        })
+    }
+
+    /// Pushes `notification` to every subscribed browser. Fails only if it
+    /// reached none of them, since retrying would repeat it for the others.
+    pub async fn send(&self, http: &reqwest::Client, notification: &Notification) -> Result<()> {
+        let subscriptions = self.db.lock().push_subscriptions()?;
+        if subscriptions.is_empty() {
+            return Ok(());
+        }
+        let Some(subject) = &self.subject else {
+            anyhow::bail!("Web Push needs --vapid-subject, or an https: --public-url");
+        };
+        let payload = serde_json::to_vec(&json!({
+            "title": notification.title,
+            "body": notification.body,
+            "url": notification.url,
+        }))?;
+
+        let mut delivered = false;
+        let mut failure = None;
+        for subscription in subscriptions {
+            match self.push(http, subject, &subscription, &payload).await {
+                Ok(true) => delivered = true,
+                Ok(false) => self
+                    .db
+                    .lock()
+                    .delete_push_subscription(&subscription.endpoint)?,
+                Err(err) => failure = Some(err),
+            }
+        }
+        match failure {
+            Some(err) if !delivered => Err(err),
+            _ => Ok(()),
+        }
+    }
+
+    /// Returns `false` if the browser has unsubscribed in the meantime.
+    async fn push(
+        &self,
+        http: &reqwest::Client,
+        subject: &str,
+        subscription: &PushSubscription,
+        payload: &[u8],
+    ) -> Result<bool> {
+        let endpoint: Url = subscription.endpoint.parse()?;
+        let body = encrypt(subscription, payload)?;
+        let response = http
+            .post(endpoint.clone())
+            .header("TTL", TTL_SECONDS)
+            .header(header::CONTENT_ENCODING, "aes128gcm")
+            .header(header::CONTENT_TYPE, "application/octet-stream")
+            .header(
+                header::AUTHORIZATION,
+                format!(
+                    "vapid t={}, k={}",
+                    self.token(&endpoint, subject),
+                    self.public_key
+                ),
+            )
+            .body(body)
+            .send()
+            .await?;
+        if matches!(response.status(), StatusCode::NOT_FOUND | StatusCode::GONE) {
+            return Ok(false);
+        }
+        response.error_for_status()?;
+        Ok(true)
+    }
+
+    /// A signed VAPID token for the push service behind `endpoint`.
+    fn token(&self, endpoint: &Url, subject: &str) -> String {
+        let header = URL_SAFE_NO_PAD.encode(r#"{"typ":"JWT","alg":"ES256"}"#);
+        let claims = URL_SAFE_NO_PAD.encode(
+            json!({
+                "aud": endpoint.origin().ascii_serialization(),
+                "exp": (Utc::now() + TimeDelta::hours(12)).timestamp(),
+                "sub": subject,
+            })
+            .to_string(),
+        );
+        let signing_input = format!("{header}.{claims}");
+        let signature: Signature = self.key.sign(signing_input.as_bytes());
+        format!(
+            "{signing_input}.{}",
+            URL_SAFE_NO_PAD.encode(signature.to_bytes())
+        )
+    }
+}
+
+/// Encrypts `payload` for one browser as a single `aes128gcm` record.
+fn encrypt(subscription: &PushSubscription, payload: &[u8]) -> Result<Vec<u8>> {
+    let browser_key = URL_SAFE_NO_PAD
+        .decode(&subscription.p256dh)
+        .ok()
+        .and_then(|bytes| PublicKey::from_sec1_bytes(&bytes).ok())
+        .context("invalid browser key")?;
+    let auth = URL_SAFE_NO_PAD
+        .decode(&subscription.auth)
+        .context("invalid authentication secret")?;
+    let ephemeral = SecretKey::random(&mut OsRng);
+    encrypt_with(&browser_key, &auth, &ephemeral, rand::random(), payload)
+}
+
+/// [`encrypt`] with the key and salt that are otherwise made up per message.
+fn encrypt_with(
+    browser_key: &PublicKey,
+    auth: &[u8],
+    ephemeral: &SecretKey,
+    salt: [u8; 16],
+    payload: &[u8],
+) -> Result<Vec<u8>> {
+    let server_key = ephemeral.public_key().to_encoded_point(false);
+    let shared = diffie_hellman(ephemeral.to_nonzero_scalar(), browser_key.as_affine());
+
+    let mut info = b"WebPush: info\0".to_vec();
+    info.extend_from_slice(browser_key.to_encoded_point(false).as_bytes());
+    info.extend_from_slice(server_key.as_bytes());
+    let mut ikm = [0; 32];
+    Hkdf::<Sha256>::new(Some(auth), shared.raw_secret_bytes())
+        .expand(&info, &mut ikm)
+        .map_err(|_| anyhow!("deriving the push key failed"))?;
+
+    let hkdf = Hkdf::<Sha256>::new(Some(&salt), &ikm);
+    let mut content_key = [0; 16];
+    let mut nonce = [0; 12];
+    hkdf.expand(b"Content-Encoding: aes128gcm\0", &mut content_key)
+        .and_then(|()| hkdf.expand(b"Content-Encoding: nonce\0", &mut nonce))
+        .map_err(|_| anyhow!("deriving the push key failed"))?;
+
+    let mut plaintext = payload.to_vec();
+    // Marks the last record.
+    plaintext.push(2);
+    let ciphertext = Aes128Gcm::new(Key::<Aes128Gcm>::from_slice(&content_key))
+        .encrypt(Nonce::from_slice(&nonce), plaintext.as_slice())
+        .map_err(|_| anyhow!("encrypting the push message failed"))?;
+
+    let mut body = Vec::with_capacity(21 + server_key.len() + ciphertext.len());
+    body.extend_from_slice(&salt);
+    body.extend_from_slice(&RECORD_SIZE.to_be_bytes());
+    body.push(server_key.len() as u8);
+    body.extend_from_slice(server_key.as_bytes());
+    body.extend_from_slice(&ciphertext);
+    Ok(body)
+}
+
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/sw.js", get(service_worker))
+        .route("/push.js", get(button_script))
+        .route("/push/key", get(public_key))
+        .route("/push/subscribe", post(subscribe))
+        .route("/push/unsubscribe", post(unsubscribe))
+}
+
+/// Button for the list page that subscribes the browser, or unsubscribes it
+/// again; hidden where the browser can't receive pushes.
+pub fn button() -> String {
+    format!(
+        "    <button id=\"push\" type=\"button\" data-base=\"{base}\" hidden>Benachrichtigungen aktivieren</button>\n    <script src=\"{base}/push.js\"></script>\n",
+        base = base_path()
+    )
+}
+
+async fn service_worker() -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "text/javascript; charset=utf-8")],
+        SERVICE_WORKER,
+    )
+}
+
+async fn button_script() -> impl IntoResponse {
+    (
+        [(header::CONTENT_TYPE, "text/javascript; charset=utf-8")],
+        BUTTON_SCRIPT,
+    )
+}
+
+async fn public_key(State(state): State<AppState>) -> String {
+    state.web_push.public_key.clone()
+}
+
+async fn subscribe(
+    State(state): State<AppState>,
+    Json(request): Json<SubscribeRequest>,
+) -> Result<StatusCode, StatusCode> {
+    // Push services are always reached over HTTPS; anything else would let
+    // the server be pointed at arbitrary addresses.
+    let endpoint = Url::parse(&request.endpoint).map_err(|_| StatusCode::UNPROCESSABLE_ENTITY)?;
+    let subscription = PushSubscription {
+        endpoint: request.endpoint,
+        p256dh: request.keys.p256dh,
+        auth: request.keys.auth,
+    };
+    if endpoint.scheme() != "https" || encrypt(&subscription, b"").is_err() {
+        return Err(StatusCode::UNPROCESSABLE_ENTITY);
+    }
+    state
+        .db
+        .lock()
+        .add_push_subscription(&subscription)
+        .map_err(error_status)?;
+    Ok(StatusCode::CREATED)
+}
+
+async fn unsubscribe(
+    State(state): State<AppState>,
+    Json(request): Json<UnsubscribeRequest>,
+) -> Result<StatusCode, StatusCode> {
+    state
+        .db
+        .lock()
+        .delete_push_subscription(&request.endpoint)
+        .map_err(error_status)?;
+    Ok(StatusCode::NO_CONTENT)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn decode(value: &str) -> Vec<u8> {
+        URL_SAFE_NO_PAD.decode(value).unwrap()
+    }
+
+    /// The example from RFC 8291, section 5.
+    #[test]
+    fn encrypts_the_rfc_8291_example() {
+        let browser_key = PublicKey::from_sec1_bytes(&decode(
+            "BCVxsr7N_eNgVRqvHtD0zTZsEc6-VV-JvLexhqUzORcxaOzi6-AYWXvTBHm4bjyPjs7Vd8pZGH6SRpkNtoIAiw4",
+        ))
+        .unwrap();
+        let ephemeral =
+            SecretKey::from_slice(&decode("yfWPiYE-n46HLnH0KqZOF1fJJU3MYrct3AELtAQ-oRw")).unwrap();
+        let salt = decode("DGv6ra1nlYgDCS1FRnbzlw").try_into().unwrap();
+        let body = encrypt_with(
+            &browser_key,
+            &decode("BTBZMqHH6r4Tts7J_aSIgg"),
+            &ephemeral,
+            salt,
+            b"When I grow up, I want to be a watermelon",
+        )
+        .unwrap();
+        assert_eq!(
+            URL_SAFE_NO_PAD.encode(body),
+            "DGv6ra1nlYgDCS1FRnbzlwAAEABBBP4z9KsN6nGRTbVYI_c7VJSPQTBtkgcy27mlmlMoZIIgDll6e3vCYLocInmYWAmS6TlzAC8wEqKK6PBru3jl7A_yl95bQpu6cVPTpK4Mqgkf1CXztLVBSt2Ks3oZwbuwXPXLWyouBWLVWGNWQexSgSxsj_Qulcy4a-fN"
+        );
+    }
+
+    #[test]
+    fn accepts_only_mailto_and_https_subjects() {
+        assert!(is_valid_subject("mailto:admin@example.org"));
+        assert!(is_valid_subject("https://todo.example.org"));
+        assert!(!is_valid_subject("http://localhost:5876"));
+        assert!(!is_valid_subject("admin@example.org"));
+    }
+}